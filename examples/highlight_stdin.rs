@@ -0,0 +1,62 @@
+//! Filter stdin with a logviewer expression and print the matching lines
+//! highlighted with ANSI colors.
+//!
+//! ```sh
+//! cat app.log | cargo run --example highlight_stdin -- 'error || warn' 'timeout'
+//! ```
+
+use logviewer::{apply_highlights, highlight_line, parse_filter, HeuristicRules, HighlightStyle};
+use std::io::{self, BufRead, Write};
+
+fn sgr(style: HighlightStyle) -> &'static str {
+    match style {
+        HighlightStyle::Error => "1;31",
+        HighlightStyle::Warning => "1;33",
+        HighlightStyle::Info => "1;32",
+        HighlightStyle::Debug => "36",
+        HighlightStyle::Bracket => "34",
+        HighlightStyle::Timestamp => "35",
+        HighlightStyle::IpAddress => "96",
+        HighlightStyle::Url => "4;94",
+        HighlightStyle::Uuid => "95",
+        HighlightStyle::CustomHighlight => "1;30;43",
+        HighlightStyle::SearchMatch => "1;7",
+        HighlightStyle::JsonKey => "36",
+        HighlightStyle::JsonString | HighlightStyle::LogfmtValue => "32",
+        HighlightStyle::JsonNumber => "33",
+        HighlightStyle::JsonBool => "35",
+        HighlightStyle::JsonNull => "31",
+        HighlightStyle::LogfmtKey => "34",
+        HighlightStyle::None | HighlightStyle::UserRule(_) => "",
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let filter = args.next().map(|text| parse_filter(&text)).transpose()?;
+    let highlight = args.next().map(|text| parse_filter(&text)).transpose()?;
+
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if filter.as_ref().is_some_and(|expr| !expr.matches(&line)) {
+            continue;
+        }
+        let spans = highlight_line(
+            &line,
+            highlight.as_ref(),
+            None,
+            HeuristicRules::ALL,
+            true,
+            false,
+        );
+        for (text, style) in apply_highlights(&line, &spans) {
+            match sgr(style) {
+                "" => write!(out, "{}", text)?,
+                code => write!(out, "\x1b[{}m{}\x1b[0m", code, text)?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::highlight::{HighlightConfig, HighlightStyle};
+use ratatui::style::{Color, Modifier, Style};
+
+const BASE_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+];
+
+const BRIGHT_COLORS: [Color; 8] = [
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Split `input` into runs of plain text with the SGR style active for each run.
+/// All escape sequences are removed; a sequence cut off at the end of the line
+/// is dropped.
+pub fn parse_ansi(input: &str) -> Vec<(String, Style)> {
+    let mut runs = Vec::new();
+    let mut style = Style::default();
+    let mut text = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                let mut params = String::new();
+                let mut final_byte = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        final_byte = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if final_byte == Some('m') {
+                    if !text.is_empty() {
+                        runs.push((std::mem::take(&mut text), style));
+                    }
+                    style = apply_sgr(style, &params);
+                }
+            }
+            Some(']') => {
+                // OSC, terminated by BEL or ESC \
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        if chars.peek() == Some(&'\\') {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !text.is_empty() {
+        runs.push((text, style));
+    }
+    runs
+}
+
+pub fn strip_ansi(input: &str) -> String {
+    if !input.contains('\x1b') {
+        return input.to_string();
+    }
+    parse_ansi(input)
+        .into_iter()
+        .map(|(text, _)| text)
+        .collect()
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            code @ 30..=37 => style.fg = Some(BASE_COLORS[(code - 30) as usize]),
+            code @ (38 | 48) => {
+                if let Some((color, used)) = extended_color(&codes[i + 1..]) {
+                    if code == 38 {
+                        style.fg = Some(color);
+                    } else {
+                        style.bg = Some(color);
+                    }
+                    i += used;
+                }
+            }
+            39 => style.fg = None,
+            code @ 40..=47 => style.bg = Some(BASE_COLORS[(code - 40) as usize]),
+            49 => style.bg = None,
+            code @ 90..=97 => style.fg = Some(BRIGHT_COLORS[(code - 90) as usize]),
+            code @ 100..=107 => style.bg = Some(BRIGHT_COLORS[(code - 100) as usize]),
+            _ => {}
+        }
+        i += 1;
+    }
+    style
+}
+
+/// `5;n` or `2;r;g;b` after a 38/48 code, with the number of codes consumed.
+fn extended_color(rest: &[u16]) -> Option<(Color, usize)> {
+    match rest {
+        [5, n, ..] => Some((Color::Indexed(*n as u8), 2)),
+        [2, r, g, b, ..] => Some((Color::Rgb(*r as u8, *g as u8, *b as u8), 4)),
+        _ => None,
+    }
+}
+
+/// Combine ANSI runs with highlight runs over the same plain text. Heuristic and
+/// JSON colors only apply where the input set no color of its own; custom and
+/// search highlights always show.
+pub fn layer_highlights(
+    ansi: &[(String, Style)],
+    highlights: &[(String, HighlightStyle)],
+    config: &HighlightConfig,
+) -> Vec<(String, Style)> {
+    let mut result: Vec<(String, Style)> = Vec::new();
+    let mut ansi_iter = ansi.iter();
+    let mut hl_iter = highlights.iter();
+    let mut ansi_run = ansi_iter.next().map(|(t, s)| (t.as_str(), *s));
+    let mut hl_run = hl_iter.next().map(|(t, s)| (t.as_str(), *s));
+
+    while let (Some((ansi_text, ansi_style)), Some((hl_text, hl_style))) = (ansi_run, hl_run) {
+        let len = ansi_text.len().min(hl_text.len());
+        let style = combine(ansi_style, config.style_for(hl_style), hl_style);
+        match result.last_mut() {
+            Some((text, last)) if *last == style => text.push_str(&ansi_text[..len]),
+            _ => result.push((ansi_text[..len].to_string(), style)),
+        }
+        ansi_run = if len < ansi_text.len() {
+            Some((&ansi_text[len..], ansi_style))
+        } else {
+            ansi_iter.next().map(|(t, s)| (t.as_str(), *s))
+        };
+        hl_run = if len < hl_text.len() {
+            Some((&hl_text[len..], hl_style))
+        } else {
+            hl_iter.next().map(|(t, s)| (t.as_str(), *s))
+        };
+    }
+    result
+}
+
+/// `styled` is how `highlight` is drawn.
+fn combine(ansi: Style, styled: Style, highlight: HighlightStyle) -> Style {
+    let explicit = ansi.fg.is_some() || ansi.bg.is_some();
+    match highlight {
+        HighlightStyle::None => ansi,
+        HighlightStyle::CustomHighlight | HighlightStyle::SearchMatch => ansi.patch(styled),
+        _ if explicit => ansi,
+        _ => ansi.patch(styled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sgr_colors() {
+        let runs = parse_ansi("plain \x1b[1;31mred\x1b[0m \x1b[38;5;208mo\x1b[48;2;1;2;3mx\x1b[m");
+        assert_eq!(runs[0], ("plain ".to_string(), Style::default()));
+        assert_eq!(
+            runs[1],
+            (
+                "red".to_string(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            )
+        );
+        assert_eq!(runs[2], (" ".to_string(), Style::default()));
+        assert_eq!(
+            runs[3],
+            ("o".to_string(), Style::default().fg(Color::Indexed(208)))
+        );
+        assert_eq!(
+            runs[4],
+            (
+                "x".to_string(),
+                Style::default()
+                    .fg(Color::Indexed(208))
+                    .bg(Color::Rgb(1, 2, 3))
+            )
+        );
+    }
+
+    #[test]
+    fn test_truncated_and_non_sgr_sequences_are_stripped() {
+        assert_eq!(strip_ansi("a\x1b[2Kb\x1b]0;title\x07c"), "abc");
+        assert_eq!(strip_ansi("tail\x1b[3"), "tail");
+        assert_eq!(strip_ansi("tail\x1b"), "tail");
+        assert_eq!(strip_ansi("no escapes"), "no escapes");
+    }
+
+    #[test]
+    fn test_heuristics_yield_to_explicit_colors() {
+        let ansi = parse_ansi("\x1b[32mERROR\x1b[0m ERROR");
+        let highlights = vec![
+            ("ERROR".to_string(), HighlightStyle::Error),
+            (" ".to_string(), HighlightStyle::None),
+            ("ERROR".to_string(), HighlightStyle::Error),
+        ];
+        let layered = layer_highlights(&ansi, &highlights, &HighlightConfig::default());
+        assert_eq!(
+            layered[0],
+            ("ERROR".to_string(), Style::default().fg(Color::Green))
+        );
+        assert_eq!(
+            layered[2],
+            (
+                "ERROR".to_string(),
+                HighlightStyle::Error.to_ratatui_style()
+            )
+        );
+
+        let custom = vec![("ERROR ERROR".to_string(), HighlightStyle::CustomHighlight)];
+        let layered = layer_highlights(&ansi, &custom, &HighlightConfig::default());
+        assert_eq!(layered[0].1.bg, Some(Color::Yellow));
+    }
+}
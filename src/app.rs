@@ -1,108 +1,503 @@
-use crate::constants::{PREFIX_WIDTH_WITHOUT_TIME, PREFIX_WIDTH_WITH_TIME};
-use crate::core::{FilterState, InputFields, InputMode, ListenState, LogLine, LogState};
-use crate::filter::parse_filter;
-use crate::highlight::{apply_highlights_ratatui, highlight_line};
+use crate::ansi::{layer_highlights, parse_ansi, strip_ansi};
+use crate::config::Config;
+use crate::constants::{
+    DEFAULT_TAB_WIDTH, EXTRACT_COLUMN_WIDTH, GUTTER_SEPARATOR_WIDTH, HIGHLIGHT_CACHE_FACTOR,
+    JSON_SAMPLE_LINES, LEVEL_GUTTER_WIDTH, LINE_NUMBER_WIDTH, MOUSE_SCROLL_LINES,
+    PREFIX_WIDTH_WITHOUT_TIME, PREFIX_WIDTH_WITH_TIME, VALIDATE_DELAY_MS,
+};
+use crate::core::{
+    compile_hide_regex, format_absolute_time, format_bytes, ErrorLog, FilterState, InputFields,
+    InputHistory, InputMode, LineKind, ListenState, LogLine, LogState, OutputTemplate, SearchState,
+    Throughput, TimeFormat,
+};
+use crate::filter::{parse_filter_opts, FilterOptions, LineFields};
+use crate::highlight::{
+    self, apply_highlights, contains_json, detect_level, is_error_line, pretty_json_rows,
+    HeuristicRules, HighlightConfig, HighlightStyle, HEURISTIC_RULE_NAMES,
+};
+use crate::input::TextInput;
+use crate::keymap::{edit_action, EditAction};
 use crate::source::SourceEvent;
-use crate::state::AppState;
-use crossterm::event::KeyCode;
+use crate::state::{state_path, AppState, FilePosition, Presets, Profile, WindowGeometry};
+use crate::syslog::parse_frame;
+use crate::timeparse::TimestampParser;
+use crate::tui::theme::Theme;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use fancy_regex::Regex;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Modifier, Style};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::ops::Range;
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Rendered runs of recently drawn lines by absolute line index, each with
+/// the frame it was last drawn in.
+#[derive(Default)]
+struct HighlightCache {
+    frame: u64,
+    entries: HashMap<usize, (u64, Vec<(String, Style)>)>,
+}
+
+/// Read-only rows shown in a scrollable popup, like the JSON of one line.
+pub struct TextPopup {
+    pub title: String,
+    pub rows: Vec<Vec<(String, Style)>>,
+    /// First row shown; clamped to the popup height when drawn.
+    pub scroll: usize,
+}
 
 pub struct App {
     pub log_state: LogState,
     pub input_fields: InputFields,
+    pub input_history: InputHistory,
+    /// While recalling history: the entry shown and the text typed before.
+    history_browse: Option<(usize, String)>,
     pub filter_state: FilterState,
     pub listen_state: ListenState,
+    pub search_state: SearchState,
     pub show_time: bool,
+    pub compact_gutter: bool,
+    pub show_line_numbers: bool,
     pub wrap_lines: bool,
+    pub heuristic_highlight: bool,
+    /// Heuristic rules used while `heuristic_highlight` is on.
+    pub heuristic_rules: HeuristicRules,
+    pub json_highlight: bool,
+    pub logfmt_highlight: bool,
+    /// Reads event times from line content; lines without one keep arrival time.
+    pub timestamp_parser: TimestampParser,
+    /// Parse each line as a syslog frame and show only its message.
+    pub syslog: bool,
+    pub time_format: TimeFormat,
+    /// Short file names, or client addresses when listening, shown before
+    /// lines tagged with their position.
+    pub source_labels: Vec<String>,
+    /// What the lines are read from, like `file app.log`, for the diagnostics popup.
+    pub source_description: String,
+    /// Show only lines from this entry of `source_labels`.
+    pub source_only: Option<usize>,
+    /// Collapse runs of identical lines into one with an `(xN)` count.
+    pub dedup: bool,
+    /// Pad whitespace- or delimiter-separated columns to line up on screen.
+    pub align_columns: bool,
+    /// Split columns on this instead of runs of whitespace (`--delimiter`).
+    pub column_delimiter: Option<char>,
+    /// Tabs are drawn as spaces up to the next multiple of this.
+    pub tab_width: usize,
+    /// Unmatched lines shown dimmed before and after each filter match (`[`/`]`).
+    pub context: usize,
+    /// Absolute indices of bookmarked lines.
+    pub bookmarks: HashSet<usize>,
+    /// Absolute index of the line `V` started a range selection at; the cursor
+    /// line is the other end.
+    pub visual_anchor: Option<usize>,
+    highlight_cache: HighlightCache,
+    /// Pulls a field out of each line into its own column after the line number.
+    pub extract_regex: Option<Regex>,
+    /// Render ANSI SGR colors instead of showing the escape bytes.
+    pub ansi_colors: bool,
+    /// `--output-template`: how copied lines are written instead of the gutter.
+    pub output_template: Option<OutputTemplate>,
+    /// `NO_COLOR` or `--no-color`: lines render unstyled and `tui::draw` drops
+    /// every other color.
+    pub no_color: bool,
+    pub theme: Theme,
+    /// Highlight theme, user rules and char cap from the config file, swapped on reload.
+    pub highlight: Arc<HighlightConfig>,
+    pub case_insensitive: bool,
+    /// Filter patterns without uppercase ignore case (`I`).
+    pub smart_case: bool,
+    pub literal: bool,
+    /// Patterns only match whole words.
+    pub whole_word: bool,
+    /// Still waiting to sample the first lines for JSON auto-detection.
+    pub auto_json: bool,
     pub input_mode: InputMode,
     pub source_rx: Receiver<SourceEvent>,
     pub status_message: Option<String>,
     pub show_quit_confirm: bool,
+    /// Ask before quitting with `q`; off with `--no-confirm`.
+    pub confirm_quit: bool,
+    pub profiles: HashMap<String, Profile>,
+    pub active_profile: Option<String>,
+    /// Canonical path of the viewed file, under which its scroll position is saved.
+    pub position_key: Option<String>,
+    /// Jump to the saved position for `position_key` once the file is read.
+    pub restore_position: bool,
+    file_positions: HashMap<String, FilePosition>,
+    /// Selected row of the profile popup, `Some` while it is open.
+    pub profile_popup: Option<usize>,
+    /// Selected row of the heuristic rules popup, `Some` while it is open.
+    pub heuristic_popup: Option<usize>,
+    pub text_popup: Option<TextPopup>,
+    /// Scroll offset of the full-screen key help (`?`), `None` when closed.
+    pub help_scroll: Option<usize>,
+    /// Errors from the source, counted in the status bar instead of replacing it.
+    pub source_errors: ErrorLog,
+    /// Scroll offset of the source error list (`!`), `None` when closed.
+    pub error_scroll: Option<usize>,
+    /// Filtered-index range `[start, end)` rendered in the last frame.
+    pub visible_range: Option<(usize, usize)>,
+    /// Log view area (including its border) in the last frame.
+    pub log_view_area: Rect,
+    /// Error-density column at the right edge of the log view, from the last frame.
+    pub minimap_area: Rect,
+    /// Columns of content skipped on each line when wrapping is off.
+    pub h_scroll: usize,
+    /// Largest useful `h_scroll` for the lines rendered in the last frame.
+    pub h_scroll_max: usize,
+    /// Lines and bytes received per second, for the status bar.
+    pub throughput: Throughput,
+    /// The source sent `SourceEvent::Eof`; no more lines will arrive.
+    pub input_ended: bool,
+    /// Pause in typing after which the field being edited is checked.
+    pub validate_delay: Duration,
+    /// When the field being edited last changed, until it is checked.
+    pending_validation: Option<Instant>,
+    persist_state: bool,
+    /// The GUI's window geometry, kept so saving from the TUI doesn't drop it.
+    window: Option<WindowGeometry>,
+    /// Saved expressions replaced by command-line presets, written back in
+    /// their place until the user edits that field.
+    shadowed: Presets,
 }
 
 impl App {
-    pub fn new(source_rx: Receiver<SourceEvent>, listen_port: Option<u16>) -> Self {
-        let state = AppState::load();
+    pub fn new(
+        source_rx: Receiver<SourceEvent>,
+        listen_port: Option<u16>,
+        presets: &Presets,
+    ) -> Self {
+        let mut state = AppState::load();
+        let shadowed = presets.apply_to(&mut state);
+        let mut app = Self::from_state(source_rx, listen_port, state, true);
+        app.shadowed = shadowed;
+        app
+    }
+
+    pub fn from_state(
+        source_rx: Receiver<SourceEvent>,
+        listen_port: Option<u16>,
+        state: AppState,
+        persist_state: bool,
+    ) -> Self {
         let mut app = Self {
             log_state: LogState::default(),
             input_fields: InputFields::from_state(&state),
-            filter_state: FilterState::default(),
+            input_history: InputHistory::from_state(&state),
+            history_browse: None,
+            filter_state: FilterState {
+                filter_after_hide: state.filter_after_hide,
+                ..FilterState::default()
+            },
             listen_state: ListenState::new(listen_port),
+            search_state: SearchState::default(),
             show_time: true,
+            compact_gutter: state.compact_gutter,
+            show_line_numbers: state.show_line_numbers,
             wrap_lines: state.wrap_lines,
+            heuristic_highlight: true,
+            heuristic_rules: HeuristicRules::without(&state.disabled_heuristics),
+            json_highlight: false,
+            logfmt_highlight: false,
+            timestamp_parser: TimestampParser::default(),
+            syslog: false,
+            time_format: TimeFormat::default(),
+            extract_regex: None,
+            highlight_cache: HighlightCache::default(),
+            dedup: false,
+            align_columns: false,
+            column_delimiter: None,
+            tab_width: DEFAULT_TAB_WIDTH.into(),
+            context: 0,
+            bookmarks: HashSet::new(),
+            visual_anchor: None,
+            source_labels: Vec::new(),
+            source_description: "stdin".to_string(),
+            source_only: None,
+            ansi_colors: true,
+            no_color: false,
+            theme: Theme::default(),
+            highlight: highlight::current_config(),
+            output_template: None,
+            case_insensitive: state.case_insensitive,
+            smart_case: state.smart_case,
+            literal: state.literal,
+            whole_word: state.whole_word,
+            auto_json: true,
             input_mode: InputMode::Normal,
             source_rx,
             status_message: None,
             show_quit_confirm: false,
+            confirm_quit: true,
+            profiles: state.profiles.clone(),
+            active_profile: state.active_profile.clone(),
+            position_key: None,
+            restore_position: false,
+            file_positions: state.file_positions.clone(),
+            window: state.window,
+            profile_popup: None,
+            heuristic_popup: None,
+            text_popup: None,
+            help_scroll: None,
+            source_errors: ErrorLog::default(),
+            error_scroll: None,
+            visible_range: None,
+            log_view_area: Rect::default(),
+            minimap_area: Rect::default(),
+            h_scroll: 0,
+            h_scroll_max: 0,
+            throughput: Throughput::default(),
+            input_ended: false,
+            validate_delay: Duration::from_millis(VALIDATE_DELAY_MS),
+            pending_validation: None,
+            persist_state: false,
+            shadowed: Presets::default(),
         };
         app.apply_hide();
         app.apply_filter();
         app.apply_highlight();
+        app.persist_state = persist_state;
         app
     }
 
-    pub fn poll_source(&mut self) {
+    /// Handle everything the source sent since the last call. Returns whether
+    /// anything arrived.
+    pub fn poll_source(&mut self) -> bool {
+        let mut received = false;
+        let now = self.throughput.now();
         while let Ok(event) = self.source_rx.try_recv() {
+            received = true;
+            if let SourceEvent::Line(content)
+            | SourceEvent::TaggedLine(_, content)
+            | SourceEvent::PeerLine(_, content)
+            | SourceEvent::StderrLine(content)
+            | SourceEvent::ReplaceLast(content) = &event
+            {
+                self.throughput.record(now, content.len());
+            }
             match event {
-                SourceEvent::Line(content) => {
-                    let idx = self.log_state.add_line(content);
-                    if self.matches_filter(idx) {
-                        self.log_state.filtered_indices.push(idx);
-                    }
+                SourceEvent::Line(content) => self.push_line(content, LineKind::Output, None),
+                SourceEvent::TaggedLine(source, content) => {
+                    self.push_line(content, LineKind::Output, Some(source))
+                }
+                SourceEvent::PeerLine(peer, content) => {
+                    let source = self.peer_source(peer);
+                    self.push_line(content, LineKind::Output, Some(source));
                 }
+                SourceEvent::StderrLine(content) => self.push_line(content, LineKind::Stderr, None),
                 SourceEvent::SystemLine(content) => {
-                    let idx = self.log_state.add_line_with_update(content, false);
-                    if self.matches_filter(idx) {
-                        self.log_state.filtered_indices.push(idx);
-                    }
+                    let idx = self.log_state.add_system_line(content);
+                    self.index_new_line(idx);
                 }
-                SourceEvent::Error(e) => {
-                    self.status_message = Some(format!("Source error: {}", e));
+                SourceEvent::Error(e) => self.source_errors.push(chrono::Local::now(), e),
+                SourceEvent::Connected(peer) => {
+                    self.listen_state.on_connected(peer);
                 }
-                SourceEvent::Connected(_peer) => {
-                    self.listen_state.has_connection = true;
+                SourceEvent::Disconnected(peer) => {
+                    self.listen_state.on_disconnected(&peer);
                 }
-                SourceEvent::Disconnected(_peer) => {}
+                SourceEvent::CaughtUp => self.restore_file_position(),
+                SourceEvent::Eof => self.input_ended = true,
+                SourceEvent::ReplaceLast(content) => self.replace_last_line(content),
             }
         }
+        if self.auto_json && self.log_state.lines.len() >= JSON_SAMPLE_LINES {
+            self.detect_json();
+        }
+        received
+    }
+
+    fn push_line(&mut self, content: String, kind: LineKind, source: Option<usize>) {
+        // Malformed frames are shown as they came.
+        let (content, timestamp, syslog) =
+            match self.syslog.then(|| parse_frame(&content)).flatten() {
+                Some(frame) => {
+                    let timestamp = frame
+                        .timestamp
+                        .or_else(|| self.timestamp_parser.parse(&frame.message));
+                    (frame.message, timestamp, Some(Box::new(frame.meta)))
+                }
+                None => {
+                    let timestamp = self.timestamp_parser.parse(&content);
+                    (content, timestamp, None)
+                }
+            };
+        let idx = self
+            .log_state
+            .add_line_at(content, timestamp, kind, source, true);
+        if let Some(line) = self.log_state.lines.back_mut() {
+            line.syslog = syslog;
+        }
+        self.index_new_line(idx);
+    }
+
+    /// Enable JSON highlighting once if most of the first lines contain JSON.
+    fn detect_json(&mut self) {
+        self.auto_json = false;
+        let json_lines = self
+            .log_state
+            .lines
+            .iter()
+            .take(JSON_SAMPLE_LINES)
+            .filter(|line| contains_json(&line.content))
+            .count();
+        if json_lines * 2 > JSON_SAMPLE_LINES && !self.json_highlight {
+            self.json_highlight = true;
+            self.invalidate_highlights();
+            self.status_message =
+                Some("JSON detected: highlighting enabled (J to toggle)".to_string());
+        }
     }
 
-    pub fn handle_input_key(&mut self, key_code: KeyCode) -> bool {
+    pub fn handle_input_key(&mut self, key: KeyEvent) -> bool {
+        let key_code = key.code;
+        let action = edit_action(
+            key_code,
+            key.modifiers.contains(KeyModifiers::CONTROL),
+            key.modifiers.contains(KeyModifiers::ALT),
+        );
+        if let Some(EditAction::HistoryPrev | EditAction::HistoryNext) = action {
+            self.recall_history(action == Some(EditAction::HistoryPrev));
+            return false;
+        }
+        self.history_browse = None;
         if let Some(input) = self.input_fields.get_active_mut(self.input_mode) {
-            match key_code {
-                KeyCode::Left => input.move_cursor_left(),
-                KeyCode::Right => input.move_cursor_right(),
-                KeyCode::Home => input.move_cursor_to_start(),
-                KeyCode::End => input.move_cursor_to_end(),
-                KeyCode::Char(c) => input.insert_char(c),
-                KeyCode::Backspace => input.delete_char_before_cursor(),
-                KeyCode::Delete => input.delete_char_at_cursor(),
-                KeyCode::Enter => return true,
-                KeyCode::Esc => {
+            match (action, key_code) {
+                (Some(EditAction::Left), _) => input.move_cursor_left(),
+                (Some(EditAction::Right), _) => input.move_cursor_right(),
+                (Some(EditAction::WordLeft), _) => input.move_word_left(),
+                (Some(EditAction::WordRight), _) => input.move_word_right(),
+                (Some(EditAction::Start), _) => input.move_cursor_to_start(),
+                (Some(EditAction::End), _) => input.move_cursor_to_end(),
+                (Some(EditAction::Backspace), _) => input.delete_char_before_cursor(),
+                (Some(EditAction::Delete), _) => input.delete_char_at_cursor(),
+                (Some(EditAction::DeleteWordBefore), _) => input.delete_word_before(),
+                (Some(EditAction::DeleteToStart), _) => input.delete_to_start(),
+                (Some(EditAction::Apply), _) => {
+                    self.pending_validation = None;
+                    return true;
+                }
+                (Some(EditAction::Cancel), _) => {
                     self.input_mode = InputMode::Normal;
+                    self.pending_validation = None;
                 }
-                _ => {}
+                // Handled above, before the field is borrowed.
+                (Some(EditAction::HistoryPrev | EditAction::HistoryNext), _) => {}
+                (None, KeyCode::Char(c)) => input.insert_char(c),
+                (None, _) => return false,
+            }
+            let edited = match action {
+                Some(action) => matches!(
+                    action,
+                    EditAction::Backspace
+                        | EditAction::Delete
+                        | EditAction::DeleteWordBefore
+                        | EditAction::DeleteToStart
+                ),
+                None => true,
+            };
+            if edited {
+                self.pending_validation = Some(Instant::now());
             }
         }
         false
     }
 
+    /// Replace the field being edited with an older or newer applied
+    /// expression; going past the newest brings back what was typed.
+    fn recall_history(&mut self, older: bool) {
+        let mode = self.input_mode;
+        let (Some(history), Some(input)) = (
+            self.input_history.get(mode),
+            self.input_fields.get_active_mut(mode),
+        ) else {
+            return;
+        };
+        let (pos, draft) = self
+            .history_browse
+            .take()
+            .unwrap_or_else(|| (history.len(), input.text.clone()));
+        let pos = if older {
+            pos.saturating_sub(1)
+        } else {
+            (pos + 1).min(history.len())
+        };
+        *input = TextInput::new(history.get(pos).cloned().unwrap_or_else(|| draft.clone()));
+        if pos < history.len() {
+            self.history_browse = Some((pos, draft));
+        }
+        self.pending_validation = Some(Instant::now());
+    }
+
+    /// Insert bracketed-paste text into the field being edited; pastes in
+    /// normal mode are ignored.
+    pub fn handle_paste(&mut self, text: &str) {
+        if let Some(input) = self.input_fields.get_active_mut(self.input_mode) {
+            input.insert_str(text);
+            self.pending_validation = Some(Instant::now());
+        }
+    }
+
+    /// Time left until the field being edited is checked, if it changed.
+    pub fn validation_due_in(&self) -> Option<Duration> {
+        self.pending_validation
+            .map(|changed| self.validate_delay.saturating_sub(changed.elapsed()))
+    }
+
+    /// Once typing has paused for `validate_delay`, check the field being
+    /// edited and show its error without applying it; Enter still applies.
+    pub fn validate_pending_input(&mut self) {
+        if self.validation_due_in() != Some(Duration::ZERO) {
+            return;
+        }
+        self.pending_validation = None;
+        let options = self.filter_options();
+        let case_insensitive = self.case_insensitive;
+        let mode = self.input_mode;
+        let Some(input) = self.input_fields.get_active_mut(mode) else {
+            return;
+        };
+        if input.is_empty() {
+            input.clear_error();
+            return;
+        }
+        let error = match mode {
+            InputMode::HideEdit => compile_hide_regex(&input.text, case_insensitive).err(),
+            InputMode::FilterEdit | InputMode::HighlightEdit | InputMode::SearchEdit => {
+                parse_filter_opts(&input.text, options)
+                    .err()
+                    .map(|e| e.to_string())
+            }
+            InputMode::LineStartEdit | InputMode::ExtractEdit => {
+                Regex::new(&input.text).err().map(|e| e.to_string())
+            }
+            _ => return,
+        };
+        input.set_error(error);
+    }
+
     pub fn apply_current_input(&mut self) {
         match self.input_mode {
             InputMode::HideEdit => {
+                self.shadowed.hide = None;
                 self.apply_hide();
                 if !self.input_fields.hide.has_error() {
                     self.input_mode = InputMode::Normal;
                 }
             }
             InputMode::FilterEdit => {
+                self.shadowed.filter = None;
                 self.apply_filter();
                 if !self.input_fields.filter.has_error() {
                     self.input_mode = InputMode::Normal;
                 }
             }
             InputMode::HighlightEdit => {
+                self.shadowed.highlight = None;
                 self.apply_highlight();
                 if !self.input_fields.highlight.has_error() {
                     self.input_mode = InputMode::Normal;
@@ -114,6 +509,30 @@ impl App {
                     self.input_mode = InputMode::Normal;
                 }
             }
+            InputMode::SearchEdit => {
+                self.apply_search();
+                if !self.input_fields.search.has_error() {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::GotoLineEdit => {
+                self.apply_goto_line();
+                if !self.input_fields.goto_line.has_error() {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::ExtractEdit => {
+                self.apply_extract();
+                if !self.input_fields.extract.has_error() {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::ProfileNameEdit => {
+                self.save_profile();
+                if !self.input_fields.profile_name.has_error() {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
             InputMode::Normal => {}
         }
     }
@@ -122,53 +541,248 @@ impl App {
         self.filter_state.apply_hide(&line.content)
     }
 
+    fn hidden_content(&self, content: &str) -> Option<String> {
+        self.filter_state.hide_regex.as_ref()?;
+        self.filter_state
+            .apply_hide(content)
+            .ok()
+            .filter(|hidden| hidden != content)
+    }
+
     fn matches_filter(&self, idx: usize) -> bool {
-        if idx >= self.log_state.lines.len() {
+        let line = self.log_state.line(idx);
+        if line.kind == LineKind::System {
+            return true;
+        }
+        if self
+            .source_only
+            .is_some_and(|only| line.source != Some(only))
+        {
             return false;
         }
-        let line = &self.log_state.lines[idx];
-        let content = self.get_display_content(line).unwrap_or_else(|_| line.content.clone());
-        self.filter_state.matches_filter(&content)
+        let syslog = line.syslog.as_deref();
+        let fields = LineFields {
+            file: self.source_label(line),
+            host: syslog.and_then(|meta| meta.host.as_deref()),
+            app: syslog.and_then(|meta| meta.app.as_deref()),
+            line: Some(idx + 1),
+            last_line: Some(self.log_state.line_range().end),
+            time: line.timestamp_parsed.then_some(line.timestamp),
+        };
+        let content = if self.filter_state.filter_after_hide {
+            self.log_state.display_content(idx)
+        } else {
+            &line.content
+        };
+        self.filter_state.matches_filter(content, &fields)
+    }
+
+    /// Short name of the file `line` came from when viewing several files.
+    pub fn source_label(&self, line: &LogLine) -> Option<&str> {
+        line.source
+            .and_then(|source| self.source_labels.get(source))
+            .map(String::as_str)
+    }
+
+    /// Position of `peer` in `source_labels`, adding it the first time it sends.
+    fn peer_source(&mut self, peer: String) -> usize {
+        match self.source_labels.iter().position(|label| *label == peer) {
+            Some(source) => source,
+            None => {
+                self.source_labels.push(peer);
+                self.source_labels.len() - 1
+            }
+        }
+    }
+
+    /// Width of the label column, 0 unless lines came from several files or clients.
+    pub fn source_label_width(&self) -> usize {
+        if self.source_labels.len() < 2 {
+            return 0;
+        }
+        self.source_labels
+            .iter()
+            .map(|label| label.chars().count() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Put `content` in place of the newest line, keeping its kind and source.
+    /// A notice from the viewer in between means there is nothing to redraw.
+    fn replace_last_line(&mut self, content: String) {
+        let Some(last) = self
+            .log_state
+            .lines
+            .back()
+            .filter(|line| line.kind != LineKind::System)
+        else {
+            self.push_line(content, LineKind::Output, None);
+            return;
+        };
+        let (kind, source) = (last.kind, last.source);
+        self.log_state.pop_line(self.context);
+        self.highlight_cache
+            .entries
+            .remove(&self.log_state.line_range().end);
+        self.push_line(content, kind, source);
+    }
+
+    /// Evaluate a freshly appended line once and extend the caches.
+    fn index_new_line(&mut self, idx: usize) {
+        let hidden = self.hidden_content(&self.log_state.line(idx).content);
+        self.log_state.hidden_content.push_back(hidden);
+        let is_error = is_error_line(self.log_state.display_content(idx))
+            || self
+                .log_state
+                .line(idx)
+                .syslog
+                .as_ref()
+                .is_some_and(|meta| meta.level() == HighlightStyle::Error);
+        self.log_state.error_lines.push_back(is_error);
+        let matches = self.matches_filter(idx);
+        let pos = idx - self.log_state.first_line_idx;
+        let after_match = self
+            .log_state
+            .filter_matches
+            .range(pos.saturating_sub(self.context)..)
+            .any(|&m| m);
+        self.log_state.filter_matches.push_back(matches);
+        if matches {
+            // Pull in the preceding lines not already shown as trailing context.
+            let next = self.log_state.filtered_indices.back().map_or(0, |&i| i + 1);
+            let start = idx
+                .saturating_sub(self.context)
+                .max(next)
+                .max(self.log_state.first_line_idx);
+            self.log_state.filtered_indices.extend(start..=idx);
+        } else if after_match {
+            self.log_state.filtered_indices.push_back(idx);
+        }
+        self.log_state.evict_overflow();
     }
 
     fn save_state(&self) {
-        let state = AppState {
-            hide_input: self.input_fields.hide.text.clone(),
-            filter_input: self.input_fields.filter.text.clone(),
-            highlight_input: self.input_fields.highlight.text.clone(),
+        if self.persist_state {
+            self.persisted_state().save();
+        }
+    }
+
+    fn persisted_state(&self) -> AppState {
+        let saved = |shadowed: &Option<String>, input: &TextInput| {
+            shadowed.clone().unwrap_or_else(|| input.text.clone())
+        };
+        AppState {
+            hide_input: saved(&self.shadowed.hide, &self.input_fields.hide),
+            filter_input: saved(&self.shadowed.filter, &self.input_fields.filter),
+            highlight_input: saved(&self.shadowed.highlight, &self.input_fields.highlight),
             wrap_lines: self.wrap_lines,
             line_start_regex: self.input_fields.line_start.text.clone(),
+            compact_gutter: self.compact_gutter,
+            show_line_numbers: self.show_line_numbers,
+            case_insensitive: self.case_insensitive,
+            smart_case: self.smart_case,
+            literal: self.literal,
+            whole_word: self.whole_word,
+            filter_after_hide: self.filter_state.filter_after_hide,
+            hide_history: self.input_history.hide.clone(),
+            filter_history: self.input_history.filter.clone(),
+            highlight_history: self.input_history.highlight.clone(),
+            disabled_heuristics: self.heuristic_rules.disabled(),
+            profiles: self.profiles.clone(),
+            active_profile: self.active_profile.clone(),
+            file_positions: self.file_positions.clone(),
+            window: self.window,
+        }
+    }
+
+    fn restore_file_position(&mut self) {
+        if !std::mem::take(&mut self.restore_position) {
+            return;
+        }
+        let Some(position) = self
+            .position_key
+            .as_ref()
+            .and_then(|key| self.file_positions.get(key))
+        else {
+            return;
+        };
+        if position.follow_tail || self.log_state.filtered_indices.is_empty() {
+            return;
+        }
+        let indices = &self.log_state.filtered_indices;
+        let bottom = indices.partition_point(|&idx| idx < position.bottom_line);
+        self.log_state.bottom_line_idx = bottom.min(indices.len() - 1);
+        self.log_state.follow_tail = false;
+    }
+
+    /// Remember where the view is in the current file, for the next time it is opened.
+    pub fn save_file_position(&mut self) {
+        let Some(key) = self.position_key.clone() else {
+            return;
         };
-        state.save();
+        let bottom = self.log_state.get_bottom_line_idx();
+        let position = FilePosition {
+            follow_tail: self.log_state.follow_tail,
+            bottom_line: self
+                .log_state
+                .filtered_indices
+                .get(bottom)
+                .copied()
+                .unwrap_or(0),
+        };
+        self.file_positions.insert(key, position);
+        self.save_state();
     }
 
     pub fn apply_hide(&mut self) {
+        let previous = self
+            .filter_state
+            .hide_regex
+            .as_ref()
+            .map(|re| re.as_str().to_string());
         if self.input_fields.hide.is_empty() {
             self.filter_state.hide_regex = None;
             self.input_fields.hide.clear_error();
         } else {
-            match Regex::new(&self.input_fields.hide.text) {
+            match compile_hide_regex(&self.input_fields.hide.text, self.case_insensitive) {
                 Ok(re) => {
                     self.filter_state.hide_regex = Some(re);
                     self.input_fields.hide.clear_error();
                 }
                 Err(e) => {
-                    self.input_fields.hide.set_error(Some(e.to_string()));
+                    self.input_fields.hide.set_error(Some(e));
                     return;
                 }
             }
         }
-        self.rebuild_filtered_indices();
+        self.input_history
+            .push(InputMode::HideEdit, &self.input_fields.hide.text);
+        let current = self.filter_state.hide_regex.as_ref().map(|re| re.as_str());
+        if previous.as_deref() != current {
+            self.rebuild_hidden_content();
+            if self.filter_state.filter_after_hide {
+                self.rebuild_filtered_indices();
+            }
+        }
         self.save_state();
     }
 
     pub fn apply_filter(&mut self) {
+        let mut refine = false;
+        let mut unchanged = false;
         if self.input_fields.filter.is_empty() {
+            unchanged = self.filter_state.filter_expr.is_none();
             self.filter_state.filter_expr = None;
             self.input_fields.filter.clear_error();
         } else {
-            match parse_filter(&self.input_fields.filter.text) {
+            match parse_filter_opts(&self.input_fields.filter.text, self.filter_options()) {
                 Ok(expr) => {
+                    if let Some(previous) = &self.filter_state.filter_expr {
+                        unchanged = expr.same_as(previous);
+                        refine = expr.refines(previous)
+                            && !self.filter_state.inverted
+                            && self.context == 0;
+                    }
                     self.filter_state.filter_expr = Some(expr);
                     self.input_fields.filter.clear_error();
                 }
@@ -178,16 +792,42 @@ impl App {
                 }
             }
         }
-        self.rebuild_filtered_indices();
+        self.input_history
+            .push(InputMode::FilterEdit, &self.input_fields.filter.text);
+        if refine {
+            self.refine_filtered_indices();
+        } else if !unchanged {
+            self.rebuild_filtered_indices();
+        }
+        if self.filter_state.filter_expr.is_some() {
+            self.status_message = Some(self.filter_summary());
+        }
         self.save_state();
     }
 
+    fn filter_summary(&self) -> String {
+        let matched = self.log_state.filtered_indices.len() - self.context_line_count();
+        let total = self.log_state.lines.len();
+        let percent = if total == 0 {
+            0.0
+        } else {
+            matched as f64 * 100.0 / total as f64
+        };
+        format!(
+            "Filter matched {} of {} line{} ({:.1}%)",
+            matched,
+            total,
+            if total == 1 { "" } else { "s" },
+            percent
+        )
+    }
+
     pub fn apply_highlight(&mut self) {
         if self.input_fields.highlight.is_empty() {
             self.filter_state.highlight_expr = None;
             self.input_fields.highlight.clear_error();
         } else {
-            match parse_filter(&self.input_fields.highlight.text) {
+            match parse_filter_opts(&self.input_fields.highlight.text, self.filter_options()) {
                 Ok(expr) => {
                     self.filter_state.highlight_expr = Some(expr);
                     self.input_fields.highlight.clear_error();
@@ -197,6 +837,11 @@ impl App {
                 }
             }
         }
+        if !self.input_fields.highlight.has_error() {
+            self.input_history
+                .push(InputMode::HighlightEdit, &self.input_fields.highlight.text);
+        }
+        self.invalidate_highlights();
         self.save_state();
     }
 
@@ -218,52 +863,2393 @@ impl App {
         self.status_message = Some("Line start regex saved. Restart to apply.".to_string());
     }
 
+    fn rebuild_hidden_content(&mut self) {
+        let hidden: VecDeque<Option<String>> = self
+            .log_state
+            .lines
+            .iter()
+            .map(|line| self.hidden_content(&line.content))
+            .collect();
+        self.log_state.hidden_content = hidden;
+        self.log_state.error_lines = self
+            .log_state
+            .line_range()
+            .map(|idx| is_error_line(self.log_state.display_content(idx)))
+            .collect();
+        self.invalidate_highlights();
+    }
+
     fn rebuild_filtered_indices(&mut self) {
-        self.log_state.filtered_indices.clear();
-        for i in 0..self.log_state.lines.len() {
+        let range = self.log_state.line_range();
+        let matches: VecDeque<bool> = range.clone().map(|i| self.matches_filter(i)).collect();
+        self.log_state.filtered_indices = with_context(&matches, range.start, self.context);
+        self.log_state.filter_matches = matches;
+        self.log_state.bottom_line_idx = 0;
+        self.log_state.selected_idx = None;
+    }
+
+    /// Re-check only the currently visible lines. Valid only when the new filter
+    /// is a narrowing of the one that produced `filtered_indices`.
+    fn refine_filtered_indices(&mut self) {
+        let previous = std::mem::take(&mut self.log_state.filtered_indices);
+        let mut kept = VecDeque::with_capacity(previous.len());
+        for i in previous {
             if self.matches_filter(i) {
-                self.log_state.filtered_indices.push(i);
+                kept.push_back(i);
+            } else {
+                self.log_state.filter_matches[i - self.log_state.first_line_idx] = false;
             }
         }
+        self.log_state.filtered_indices = kept;
         self.log_state.bottom_line_idx = 0;
+        self.log_state.selected_idx = None;
     }
 
-    pub fn clear(&mut self) {
-        self.log_state.clear();
-        self.status_message = Some("Cleared".to_string());
+    /// Compile the search pattern and jump to the nearest match at or above
+    /// the bottom line.
+    pub fn apply_search(&mut self) {
+        if self.input_fields.search.is_empty() {
+            self.input_fields.search.clear_error();
+            self.clear_search();
+            return;
+        }
+        match parse_filter_opts(&self.input_fields.search.text, self.filter_options()) {
+            Ok(expr) => {
+                self.search_state.expr = Some(expr);
+                self.input_fields.search.clear_error();
+                self.invalidate_highlights();
+                self.jump_to_match(false, true);
+            }
+            Err(e) => self.input_fields.search.set_error(Some(e.to_string())),
+        }
     }
 
-    pub fn render_line(&mut self, line: &LogLine) -> Vec<(String, ratatui::style::Style)> {
-        let content = match self.get_display_content(line) {
-            Ok(c) => c,
-            Err(e) => {
-                self.input_fields.hide.set_error(Some(format!("Runtime error: {}", e)));
-                line.content.clone()
+    pub fn clear_search(&mut self) {
+        self.search_state = SearchState::default();
+        self.invalidate_highlights();
+    }
+
+    pub fn search_next(&mut self) {
+        self.jump_to_match(true, false);
+    }
+
+    pub fn search_prev(&mut self) {
+        self.jump_to_match(false, false);
+    }
+
+    /// Move the bottom line to the next matching filtered line in the given
+    /// direction, wrapping around at either end.
+    fn jump_to_match(&mut self, forward: bool, include_current: bool) {
+        let Some(expr) = &self.search_state.expr else {
+            self.status_message = Some("No search pattern (/ to search)".to_string());
+            return;
+        };
+        let matches: Vec<usize> = self
+            .log_state
+            .filtered_indices
+            .iter()
+            .enumerate()
+            .filter(|(_, &line_idx)| expr.matches(self.log_state.display_content(line_idx)))
+            .map(|(pos, _)| pos)
+            .collect();
+        if matches.is_empty() {
+            self.search_state.position = None;
+            self.status_message = Some(format!(
+                "Pattern not found: {}",
+                self.input_fields.search.text
+            ));
+            return;
+        }
+        let current = self.log_state.get_bottom_line_idx();
+        let found = if forward {
+            matches
+                .iter()
+                .position(|&p| p > current || (include_current && p == current))
+        } else {
+            matches
+                .iter()
+                .rposition(|&p| p < current || (include_current && p == current))
+        };
+        let index = found.unwrap_or(if forward { 0 } else { matches.len() - 1 });
+        self.log_state.bottom_line_idx = matches[index];
+        self.log_state.follow_tail = false;
+        self.search_state.position = Some((index + 1, matches.len()));
+        if found.is_none() {
+            self.status_message = Some(format!("match {}/{} (wrapped)", index + 1, matches.len()));
+        }
+    }
+
+    /// Bookmark the cursor line, or remove its bookmark.
+    pub fn toggle_bookmark(&mut self) {
+        let Some(line_idx) = self.cursor_line_idx() else {
+            return;
+        };
+        self.status_message = Some(if self.bookmarks.remove(&line_idx) {
+            format!("Removed bookmark on line {}", line_idx + 1)
+        } else {
+            self.bookmarks.insert(line_idx);
+            format!("Bookmarked line {}", line_idx + 1)
+        });
+    }
+
+    /// Move the cursor to the next bookmark in the given direction, wrapping
+    /// around and skipping bookmarks on lines the filter hides.
+    pub fn jump_to_bookmark(&mut self, forward: bool) {
+        let range = self.log_state.line_range();
+        let mut marks: Vec<usize> = self
+            .bookmarks
+            .iter()
+            .copied()
+            .filter(|idx| range.contains(idx))
+            .collect();
+        if marks.is_empty() {
+            self.status_message = Some("No bookmarks (m to add one)".to_string());
+            return;
+        }
+        marks.sort_unstable();
+        let current = self.cursor_line_idx();
+        let split = match current {
+            Some(current) if forward => marks.partition_point(|&idx| idx <= current),
+            Some(current) => marks.partition_point(|&idx| idx < current),
+            None => 0,
+        };
+        marks.rotate_left(split);
+        if !forward {
+            marks.reverse();
+        }
+        let mut skipped = 0;
+        for idx in marks {
+            let Ok(pos) = self.log_state.filtered_indices.binary_search(&idx) else {
+                skipped += 1;
+                continue;
+            };
+            self.log_state.selected_idx = Some(pos);
+            self.log_state.bottom_line_idx = pos;
+            self.log_state.follow_tail = false;
+            if skipped > 0 {
+                self.status_message = Some(format!("Skipped {} filtered-out bookmark(s)", skipped));
+            }
+            return;
+        }
+        self.status_message = Some(format!("All {} bookmark(s) are filtered out", skipped));
+    }
+
+    pub fn apply_extract(&mut self) {
+        if self.input_fields.extract.is_empty() {
+            self.extract_regex = None;
+            self.input_fields.extract.clear_error();
+            return;
+        }
+        match Regex::new(&self.input_fields.extract.text) {
+            Ok(re) => {
+                self.extract_regex = Some(re);
+                self.input_fields.extract.clear_error();
+            }
+            Err(e) => self.input_fields.extract.set_error(Some(e.to_string())),
+        }
+    }
+
+    /// First capture group of the extract regex in the raw line (the whole match
+    /// if it has no groups), so a field can be hidden and still shown as a column.
+    pub fn extracted_field(&self, line: &LogLine) -> Option<String> {
+        let captures = self
+            .extract_regex
+            .as_ref()?
+            .captures(&line.content)
+            .ok()??;
+        let field = captures.get(1).or_else(|| captures.get(0))?;
+        Some(field.as_str().to_string())
+    }
+
+    /// Jump to the absolute line number typed after `:`. A line hidden by the
+    /// filter jumps to the nearest visible line instead.
+    pub fn apply_goto_line(&mut self) {
+        let number = match self.input_fields.goto_line.text.trim().parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                self.input_fields
+                    .goto_line
+                    .set_error(Some("Not a line number".to_string()));
+                return;
             }
         };
-        let spans = highlight_line(
-            &content,
-            self.filter_state.highlight_expr.as_ref(),
-            true,
-            true,
-        );
-        apply_highlights_ratatui(&content, &spans)
+        self.input_fields.goto_line.clear_error();
+        let indices = &self.log_state.filtered_indices;
+        if indices.is_empty() {
+            self.status_message = Some("No visible lines".to_string());
+            return;
+        }
+        let target = number - 1;
+        let position = match indices.binary_search(&target) {
+            Ok(pos) => pos,
+            Err(pos) => {
+                self.status_message = Some(if self.log_state.line_range().contains(&target) {
+                    format!("line {} is filtered out", number)
+                } else {
+                    format!("line {} is not in the buffer", number)
+                });
+                if pos == 0 {
+                    0
+                } else if pos == indices.len() || target - indices[pos - 1] <= indices[pos] - target
+                {
+                    pos - 1
+                } else {
+                    pos
+                }
+            }
+        };
+        self.log_state.bottom_line_idx = position;
+        self.log_state.follow_tail = false;
     }
 
-    pub fn toggle_time(&mut self) {
-        self.show_time = !self.show_time;
+    pub fn select_up(&mut self) {
+        self.forget_stale_visual();
+        self.log_state.select_up(1);
+        if let (Some(selected), Some((top, _))) = (self.log_state.selected_idx, self.visible_range)
+        {
+            if selected < top {
+                self.log_state.scroll_up(top - selected);
+            }
+        }
     }
 
-    pub fn toggle_wrap(&mut self) {
-        self.wrap_lines = !self.wrap_lines;
+    pub fn select_down(&mut self) {
+        self.forget_stale_visual();
+        self.log_state.select_down(1);
+    }
+
+    /// A range selection ends when the cursor line is dropped, e.g. by a new
+    /// filter, so `j`/`k` start a plain cursor again.
+    fn forget_stale_visual(&mut self) {
+        if self.log_state.selected_idx.is_none() {
+            self.visual_anchor = None;
+        }
+    }
+
+    /// Start a range selection at the cursor line, or cancel the current one.
+    pub fn toggle_visual(&mut self) {
+        if self.visual_anchor.take().is_some() {
+            return;
+        }
+        let Some(line_idx) = self.cursor_line_idx() else {
+            return;
+        };
+        if self.log_state.selected_idx.is_none() {
+            self.log_state.selected_idx = Some(self.log_state.get_bottom_line_idx());
+        }
+        self.visual_anchor = Some(line_idx);
+        self.status_message = Some("Visual: j/k extend, y copies, Esc cancels".to_string());
+    }
+
+    /// Filtered positions of the lines between the visual anchor and the
+    /// cursor line, inclusive.
+    pub fn visual_range(&self) -> Option<Range<usize>> {
+        let anchor = self.visual_anchor?;
+        let indices = &self.log_state.filtered_indices;
+        let cursor = *indices.get(self.log_state.selected_idx?)?;
+        let (low, high) = (anchor.min(cursor), anchor.max(cursor));
+        Some(indices.partition_point(|&idx| idx < low)..indices.partition_point(|&idx| idx <= high))
+    }
+
+    /// Number of lines in the visual selection and their text as `y` copies it.
+    pub fn visual_text(&self) -> Option<(usize, String)> {
+        let range = self.visual_range()?;
+        let lines: Vec<String> = self
+            .log_state
+            .filtered_indices
+            .range(range)
+            .map(|&idx| self.copied_line(idx))
+            .collect();
+        Some((lines.len(), lines.join("\n")))
+    }
+
+    /// Absolute line number and display content of the cursor line, or of the
+    /// bottom line when nothing is selected.
+    pub fn selected_line_text(&self) -> Option<(usize, String)> {
+        let line_idx = self.cursor_line_idx()?;
+        Some((line_idx + 1, self.copied_line(line_idx)))
+    }
+
+    /// Line `line_idx` as copied: by `--output-template`, or else its display
+    /// content after the absolute time when times are shown.
+    fn copied_line(&self, line_idx: usize) -> String {
+        let line = self.log_state.line(line_idx);
+        self.templated_line(line_idx)
+            .unwrap_or_else(|| format!("{}{}", self.time_prefix(line), self.plain_content(line)))
+    }
+
+    /// Line `line_idx` as written by `--output-template`, if one was given.
+    pub fn templated_line(&self, line_idx: usize) -> Option<String> {
+        let template = self.output_template.as_ref()?;
+        let line = self.log_state.line(line_idx);
+        Some(template.render(
+            line,
+            line_idx + 1,
+            &self.plain_content(line),
+            self.time_format,
+        ))
+    }
+
+    /// Distinct matches of the highlight expression across the shown lines, in
+    /// order of first appearance, with their count. `None` without a highlight.
+    pub fn highlight_matches_text(&self) -> Option<(usize, String)> {
+        let expr = self.filter_state.highlight_expr.as_ref()?;
+        let mut seen = HashSet::new();
+        let mut matches = Vec::new();
+        for &idx in &self.log_state.filtered_indices {
+            let content = self.log_state.display_content(idx);
+            for (start, end) in expr.find_all_matches(content) {
+                let text = &content[start..end];
+                if !text.is_empty() && seen.insert(text) {
+                    matches.push(text);
+                }
+            }
+        }
+        Some((matches.len(), matches.join("\n")))
+    }
+
+    /// Absolute index of the cursor line, or of the bottom line when nothing is selected.
+    fn cursor_line_idx(&self) -> Option<usize> {
+        let position = self
+            .log_state
+            .selected_idx
+            .unwrap_or_else(|| self.log_state.get_bottom_line_idx());
+        self.log_state.filtered_indices.get(position).copied()
+    }
+
+    /// List the source errors, newest at the bottom, and reset their count.
+    pub fn open_error_list(&mut self) {
+        if self.source_errors.entries().is_empty() {
+            self.status_message = Some("No source errors".to_string());
+            return;
+        }
+        self.source_errors.mark_seen();
+        self.error_scroll = Some(usize::MAX);
+    }
+
+    /// Show the source, buffer size, active patterns and state file.
+    pub fn open_diagnostics(&mut self) {
+        let pattern = |text: &str| {
+            if text.is_empty() {
+                "(none)".to_string()
+            } else {
+                text.to_string()
+            }
+        };
+        let mut info = vec![
+            ("Version", env!("CARGO_PKG_VERSION").to_string()),
+            ("Source", self.source_description.clone()),
+        ];
+        let listen = &self.listen_state;
+        let bound = match (listen.port, &listen.socket_path) {
+            (_, Some(path)) => Some(path.clone()),
+            (Some(port), None) => Some(match listen.bind_addr {
+                Some(addr) => SocketAddr::new(addr, port).to_string(),
+                None => format!("port {} on all interfaces", port),
+            }),
+            (None, None) => None,
+        };
+        if let Some(bound) = bound {
+            info.push((
+                "Listening",
+                format!("{}, {} connection(s)", bound, listen.connections),
+            ));
+        }
+        let log_state = &self.log_state;
+        let max_lines = log_state
+            .max_lines
+            .map_or(String::new(), |max| format!(" (max {})", max));
+        info.extend([
+            (
+                "Lines",
+                format!(
+                    "{} buffered{}, {} shown, {} dropped",
+                    log_state.lines.len(),
+                    max_lines,
+                    log_state.filtered_indices.len(),
+                    log_state.first_line_idx
+                ),
+            ),
+            (
+                "Memory",
+                format!("~{}", format_bytes(log_state.memory_estimate() as f64)),
+            ),
+            ("Filter", pattern(&self.input_fields.filter.text)),
+            ("Hide", pattern(&self.input_fields.hide.text)),
+            ("Highlight", pattern(&self.input_fields.highlight.text)),
+            (
+                "State file",
+                state_path().map_or("off (--no-state)".to_string(), |path| {
+                    path.display().to_string()
+                }),
+            ),
+        ]);
+
+        let label_style = Style::default().fg(self.theme.faint);
+        let rows = info
+            .into_iter()
+            .map(|(label, value)| {
+                vec![
+                    (format!("{:<11}", label), label_style),
+                    (value, Style::default()),
+                ]
+            })
+            .collect();
+        self.text_popup = Some(TextPopup {
+            title: " Diagnostics ".to_string(),
+            rows,
+            scroll: 0,
+        });
+    }
+
+    /// Show the JSON values of the cursor line pretty-printed.
+    pub fn open_json_popup(&mut self) {
+        let Some(line_idx) = self.cursor_line_idx() else {
+            return;
+        };
+        let content = self.plain_content(self.log_state.line(line_idx));
+        let rows: Vec<Vec<(String, Style)>> = pretty_json_rows(&content)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(part, style)| (part, self.highlight.style_for(style)))
+                    .collect()
+            })
+            .collect();
+        if rows.is_empty() {
+            self.status_message = Some(format!("No JSON on line {}", line_idx + 1));
+            return;
+        }
+        self.text_popup = Some(TextPopup {
+            title: format!(" JSON (line {}) ", line_idx + 1),
+            rows,
+            scroll: 0,
+        });
+    }
+
+    pub fn scroll_text_popup(&mut self, up: bool, amount: usize) {
+        if let Some(popup) = &mut self.text_popup {
+            popup.scroll = if up {
+                popup.scroll.saturating_sub(amount)
+            } else {
+                popup
+                    .scroll
+                    .saturating_add(amount)
+                    .min(popup.rows.len().saturating_sub(1))
+            };
+        }
+    }
+
+    /// Absolute timestamp put before copied lines while times are shown, since
+    /// the on-screen relative time means nothing once pasted.
+    pub fn time_prefix(&self, line: &LogLine) -> String {
+        if self.show_time || self.compact_gutter {
+            format!(
+                "{} ",
+                format_absolute_time(line.timestamp, self.time_format)
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Store the current hide/filter/highlight setup under the typed name.
+    pub fn save_profile(&mut self) {
+        let name = self.input_fields.profile_name.text.trim().to_string();
+        if name.is_empty() {
+            self.input_fields
+                .profile_name
+                .set_error(Some("Name is empty".to_string()));
+            return;
+        }
+        self.input_fields.profile_name.clear_error();
+        let profile = Profile {
+            hide_input: self.input_fields.hide.text.clone(),
+            filter_input: self.input_fields.filter.text.clone(),
+            highlight_input: self.input_fields.highlight.text.clone(),
+            wrap_lines: self.wrap_lines,
+            line_start_regex: self.input_fields.line_start.text.clone(),
+        };
+        self.profiles.insert(name.clone(), profile);
+        self.active_profile = Some(name.clone());
         self.save_state();
+        self.status_message = Some(format!("Saved profile '{}'", name));
     }
 
-    pub fn prefix_width(&self) -> usize {
-        if self.show_time {
-            PREFIX_WIDTH_WITH_TIME
+    /// Switch to a saved profile. Expressions that fail to parse keep their
+    /// text and show the error in their input field.
+    pub fn load_profile(&mut self, name: &str) {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            return;
+        };
+        self.input_fields.hide = TextInput::new(profile.hide_input);
+        self.input_fields.filter = TextInput::new(profile.filter_input);
+        self.input_fields.highlight = TextInput::new(profile.highlight_input);
+        self.input_fields.line_start = TextInput::new(profile.line_start_regex);
+        self.wrap_lines = profile.wrap_lines;
+        self.active_profile = Some(name.to_string());
+        self.shadowed = Presets::default();
+        self.apply_hide();
+        self.apply_filter();
+        self.apply_highlight();
+        self.save_state();
+        self.status_message = Some(format!("Loaded profile '{}'", name));
+    }
+
+    pub fn delete_profile(&mut self, name: &str) {
+        if self.profiles.remove(name).is_none() {
+            return;
+        }
+        if self.active_profile.as_deref() == Some(name) {
+            self.active_profile = None;
+        }
+        self.save_state();
+        self.status_message = Some(format!("Deleted profile '{}'", name));
+    }
+
+    pub fn open_profile_popup(&mut self) {
+        let names = self.profile_names();
+        if names.is_empty() {
+            self.status_message = Some("No saved profiles (P to save one)".to_string());
+            return;
+        }
+        let active = self
+            .active_profile
+            .as_ref()
+            .and_then(|active| names.iter().position(|name| name == active));
+        self.profile_popup = Some(active.unwrap_or(0));
+    }
+
+    /// Move the popup selection by `delta`, wrapping around.
+    pub fn move_profile_selection(&mut self, delta: isize) {
+        let count = self.profiles.len() as isize;
+        if let Some(selected) = self.profile_popup.as_mut() {
+            if count > 0 {
+                *selected = (*selected as isize + delta).rem_euclid(count) as usize;
+            }
+        }
+    }
+
+    pub fn selected_profile_name(&self) -> Option<String> {
+        self.profile_names().get(self.profile_popup?).cloned()
+    }
+
+    /// Rows inside the log view border in the last frame, used as the page size.
+    pub fn log_view_height(&self) -> usize {
+        (self.log_view_area.height.saturating_sub(2) as usize).max(1)
+    }
+
+    /// Scroll the log view with the mouse wheel when the pointer is over it.
+    /// Scrolling up stops at a full first page.
+    pub fn mouse_scroll(&mut self, column: u16, row: u16, up: bool) {
+        if !self.log_view_area.contains(Position::new(column, row)) {
+            return;
+        }
+        if up {
+            self.log_state.scroll_up(MOUSE_SCROLL_LINES);
+            let first_page_bottom = (self.log_view_height() - 1)
+                .min(self.log_state.filtered_indices.len().saturating_sub(1));
+            self.log_state.bottom_line_idx = self.log_state.bottom_line_idx.max(first_page_bottom);
         } else {
-            PREFIX_WIDTH_WITHOUT_TIME
+            self.log_state.scroll_down(MOUSE_SCROLL_LINES);
+        }
+    }
+
+    /// Filtered positions summarized by minimap row `row` of `rows`.
+    fn minimap_bucket(&self, row: usize, rows: usize) -> Range<usize> {
+        let total = self.log_state.filtered_indices.len();
+        row * total / rows..(row + 1) * total / rows
+    }
+
+    /// Error and total line counts for each of `rows` minimap rows.
+    pub fn error_density(&self, rows: usize) -> Vec<(usize, usize)> {
+        let first = self.log_state.first_line_idx;
+        (0..rows)
+            .map(|row| {
+                let bucket = self.minimap_bucket(row, rows);
+                let errors = self
+                    .log_state
+                    .filtered_indices
+                    .range(bucket.clone())
+                    .filter(|&&idx| self.log_state.error_lines.get(idx - first) == Some(&true))
+                    .count();
+                (errors, bucket.len())
+            })
+            .collect()
+    }
+
+    /// Jump to the region under a click on the minimap. Returns whether it was hit.
+    pub fn minimap_click(&mut self, column: u16, row: u16) -> bool {
+        if !self.minimap_area.contains(Position::new(column, row)) {
+            return false;
         }
+        let rows = self.minimap_area.height as usize;
+        let bucket = self.minimap_bucket((row - self.minimap_area.y) as usize, rows);
+        if let Some(last) = bucket.end.checked_sub(1) {
+            let first_page_bottom = self.log_view_height() - 1;
+            self.log_state.bottom_line_idx = last
+                .max(first_page_bottom)
+                .min(self.log_state.filtered_indices.len() - 1);
+            self.log_state.follow_tail = false;
+            self.log_state.selected_idx = None;
+        }
+        true
+    }
+
+    /// Pan long lines sideways; only meaningful when wrapping is off.
+    pub fn scroll_horizontal(&mut self, delta: isize) {
+        if self.wrap_lines {
+            return;
+        }
+        self.h_scroll = self
+            .h_scroll
+            .saturating_add_signed(delta)
+            .min(self.h_scroll_max);
+    }
+
+    pub fn clear(&mut self) {
+        self.log_state.clear();
+        self.bookmarks.clear();
+        self.invalidate_highlights();
+        self.status_message = Some("Cleared".to_string());
+    }
+
+    /// Drop every line above the cursor line, or every line below it.
+    pub fn clear_around_cursor(&mut self, above: bool) {
+        let Some(line_idx) = self.cursor_line_idx() else {
+            return;
+        };
+        let before = self.log_state.lines.len();
+        if above {
+            self.log_state.drop_before(line_idx);
+        } else {
+            self.log_state.drop_after(line_idx);
+        }
+        let range = self.log_state.line_range();
+        self.bookmarks.retain(|idx| range.contains(idx));
+        self.invalidate_highlights();
+        self.status_message = Some(format!(
+            "Cleared {} line(s) {} line {}",
+            before - self.log_state.lines.len(),
+            if above { "above" } else { "below" },
+            line_idx + 1
+        ));
+    }
+
+    pub fn render_line(&mut self, line: &LogLine) -> Vec<(String, ratatui::style::Style)> {
+        if self.no_color {
+            return vec![(self.plain_content(line), Style::default())];
+        }
+        let content = match self.get_display_content(line) {
+            Ok(c) => c,
+            Err(e) => {
+                self.input_fields
+                    .hide
+                    .set_error(Some(format!("Runtime error: {}", e)));
+                line.content.clone()
+            }
+        };
+        let ansi_runs =
+            (self.ansi_colors && content.contains('\x1b')).then(|| parse_ansi(&content));
+        let text = match &ansi_runs {
+            Some(runs) => runs.iter().map(|(text, _)| text.as_str()).collect(),
+            None => content,
+        };
+        let spans = self.highlight.highlight_line(
+            &text,
+            self.filter_state.highlight_expr.as_ref(),
+            self.search_state.expr.as_ref(),
+            if self.heuristic_highlight {
+                self.heuristic_rules
+            } else {
+                HeuristicRules::NONE
+            },
+            self.json_highlight,
+            self.logfmt_highlight,
+        );
+        let mut runs = match ansi_runs {
+            Some(runs) => {
+                layer_highlights(&runs, &apply_highlights(&text, &spans), &self.highlight)
+            }
+            None => self.highlight.apply_highlights_ratatui(&text, &spans),
+        };
+        // Syslog errors and warnings take the level color wherever nothing else picked one.
+        if let Some(level) = line.syslog.as_ref().map(|meta| meta.level()) {
+            if matches!(level, HighlightStyle::Error | HighlightStyle::Warning) {
+                let tint = self.highlight.style_for(level);
+                runs = runs
+                    .into_iter()
+                    .map(|(text, style)| (text, tint.patch(style)))
+                    .collect();
+            }
+        }
+        match line.kind {
+            LineKind::Output => runs,
+            LineKind::System => runs
+                .into_iter()
+                .map(|(text, style)| (text, style.add_modifier(Modifier::DIM | Modifier::ITALIC)))
+                .collect(),
+            // Tint stderr wherever nothing else picked a color.
+            LineKind::Stderr => runs
+                .into_iter()
+                .map(|(text, style)| (text, Style::default().fg(self.theme.stderr).patch(style)))
+                .collect(),
+        }
+    }
+
+    /// `render_line` for line `line_idx`, reusing the runs from earlier frames.
+    pub fn render_line_cached(&mut self, line_idx: usize) -> Vec<(String, Style)> {
+        let frame = self.highlight_cache.frame;
+        if let Some((used, runs)) = self.highlight_cache.entries.get_mut(&line_idx) {
+            *used = frame;
+            return runs.clone();
+        }
+        let line = self.log_state.line(line_idx).clone();
+        let runs = self.render_line(&line);
+        let capacity = self.log_view_height() * HIGHLIGHT_CACHE_FACTOR;
+        if self.highlight_cache.entries.len() >= capacity {
+            self.highlight_cache
+                .entries
+                .retain(|_, (used, _)| *used == frame);
+        }
+        self.highlight_cache
+            .entries
+            .insert(line_idx, (frame, runs.clone()));
+        runs
+    }
+
+    /// Start a new frame for the highlight cache; entries not used since the
+    /// previous one are the first to be evicted.
+    pub fn begin_frame(&mut self) {
+        self.highlight_cache.frame += 1;
+    }
+
+    /// Drop cached highlighting after anything that changes how lines render.
+    fn invalidate_highlights(&mut self) {
+        self.highlight_cache.entries.clear();
+    }
+
+    /// Display content as plain text, for copying.
+    pub fn plain_content(&self, line: &LogLine) -> String {
+        let content = self
+            .get_display_content(line)
+            .unwrap_or_else(|_| line.content.clone());
+        if self.ansi_colors {
+            strip_ansi(&content)
+        } else {
+            content
+        }
+    }
+
+    pub fn toggle_ansi(&mut self) {
+        self.ansi_colors = !self.ansi_colors;
+        self.invalidate_highlights();
+    }
+
+    pub fn toggle_time(&mut self) {
+        self.show_time = !self.show_time;
+    }
+
+    /// Re-read the settings and config files and apply them in place.
+    pub fn reload_state(&mut self) {
+        self.reload_from(AppState::load(), Config::load());
+    }
+
+    /// Apply `state` and `config` only if both are valid; otherwise keep the
+    /// current settings, theme and rules and report what failed.
+    fn reload_from(&mut self, state: AppState, config: anyhow::Result<Config>) {
+        let light = self.theme == Theme::LIGHT;
+        let highlight = validate_state(&state)
+            .map_err(anyhow::Error::msg)
+            .and_then(|()| config?.highlight_config(light));
+        let highlight = match highlight {
+            Ok(highlight) => highlight,
+            Err(e) => {
+                self.status_message =
+                    Some(format!("Reload failed, keeping current settings: {:#}", e));
+                return;
+            }
+        };
+        self.highlight = Arc::new(highlight);
+        self.input_fields = InputFields::from_state(&state);
+        self.input_history = InputHistory::from_state(&state);
+        self.shadowed = Presets::default();
+        self.case_insensitive = state.case_insensitive;
+        self.smart_case = state.smart_case;
+        self.literal = state.literal;
+        self.whole_word = state.whole_word;
+        self.filter_state.filter_after_hide = state.filter_after_hide;
+        self.heuristic_rules = HeuristicRules::without(&state.disabled_heuristics);
+        self.wrap_lines = state.wrap_lines;
+        self.compact_gutter = state.compact_gutter;
+        self.show_line_numbers = state.show_line_numbers;
+        self.profiles = state.profiles;
+        self.active_profile = state.active_profile;
+        self.file_positions = state.file_positions;
+        self.window = state.window;
+        self.invalidate_filters();
+        self.apply_hide();
+        self.apply_filter();
+        self.apply_highlight();
+        self.invalidate_highlights();
+        self.status_message = Some("Settings and config reloaded".to_string());
+    }
+
+    /// Drop the compiled patterns so the next `apply_*` rebuilds even though the
+    /// pattern text is unchanged.
+    fn invalidate_filters(&mut self) {
+        self.filter_state.hide_regex = None;
+        self.filter_state.filter_expr = None;
+    }
+
+    pub fn toggle_case_insensitive(&mut self) {
+        self.case_insensitive = !self.case_insensitive;
+        self.invalidate_filters();
+        self.apply_hide();
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    pub fn toggle_smart_case(&mut self) {
+        self.smart_case = !self.smart_case;
+        self.filter_state.filter_expr = None;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    pub fn toggle_literal(&mut self) {
+        self.literal = !self.literal;
+        self.filter_state.filter_expr = None;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    pub fn toggle_whole_word(&mut self) {
+        self.whole_word = !self.whole_word;
+        self.filter_state.filter_expr = None;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    fn filter_options(&self) -> FilterOptions {
+        FilterOptions {
+            case_insensitive: self.case_insensitive,
+            smart_case: self.smart_case,
+            literal: self.literal,
+            whole_word: self.whole_word,
+        }
+    }
+
+    /// Toggle the built-in level/bracket/timestamp colors, reporting how many
+    /// shown lines have an error-level match when turning them on.
+    pub fn toggle_heuristic(&mut self) {
+        self.heuristic_highlight = !self.heuristic_highlight;
+        self.invalidate_highlights();
+        if self.heuristic_highlight {
+            let errors = self
+                .log_state
+                .filtered_indices
+                .iter()
+                .filter(|&&idx| {
+                    detect_level(self.log_state.display_content(idx)) == Some(HighlightStyle::Error)
+                })
+                .count();
+            self.status_message = Some(format!(
+                "Heuristic highlighting on: {} error line{}",
+                errors,
+                if errors == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    pub fn move_heuristic_selection(&mut self, delta: isize) {
+        if let Some(selected) = self.heuristic_popup.as_mut() {
+            let count = HEURISTIC_RULE_NAMES.len() as isize;
+            *selected = (*selected as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Switch one heuristic rule on or off; takes effect on the next draw.
+    pub fn toggle_heuristic_rule(&mut self, idx: usize) {
+        self.heuristic_rules.toggle(idx);
+        self.invalidate_highlights();
+        self.save_state();
+    }
+
+    pub fn toggle_json(&mut self) {
+        self.json_highlight = !self.json_highlight;
+        self.auto_json = false;
+        self.invalidate_highlights();
+    }
+
+    pub fn toggle_dedup(&mut self) {
+        self.dedup = !self.dedup;
+    }
+
+    pub fn toggle_align_columns(&mut self) {
+        self.align_columns = !self.align_columns;
+    }
+
+    /// Widen or narrow the context shown around filter matches.
+    pub fn adjust_context(&mut self, more: bool) {
+        self.context = if more {
+            self.context + 1
+        } else {
+            self.context.saturating_sub(1)
+        };
+        if self.filter_state.filter_expr.is_some() {
+            self.rebuild_filtered_indices();
+        }
+        self.status_message = Some(format!("Context: {} lines", self.context));
+    }
+
+    /// Whether line `idx` is only shown as context around a filter match.
+    pub fn is_context_line(&self, idx: usize) -> bool {
+        self.context > 0
+            && self.filter_state.filter_expr.is_some()
+            && self
+                .log_state
+                .filter_matches
+                .get(idx - self.log_state.first_line_idx)
+                .is_some_and(|&m| !m)
+    }
+
+    /// How many of the filtered lines are context rather than matches.
+    pub fn context_line_count(&self) -> usize {
+        if self.context == 0 || self.filter_state.filter_expr.is_none() {
+            return 0;
+        }
+        self.log_state
+            .filtered_indices
+            .iter()
+            .filter(|&&idx| self.is_context_line(idx))
+            .count()
+    }
+
+    /// Filtered positions around `pos` whose displayed content is identical to it.
+    pub fn duplicate_run(&self, pos: usize) -> Range<usize> {
+        let indices = &self.log_state.filtered_indices;
+        let content = self.log_state.display_content(indices[pos]);
+        let same = |p: usize| self.log_state.display_content(indices[p]) == content;
+        let mut start = pos;
+        while start > 0 && same(start - 1) {
+            start -= 1;
+        }
+        let mut end = pos + 1;
+        while end < indices.len() && same(end) {
+            end += 1;
+        }
+        start..end
+    }
+
+    pub fn toggle_logfmt(&mut self) {
+        self.logfmt_highlight = !self.logfmt_highlight;
+        self.invalidate_highlights();
+    }
+
+    /// Show only the next source's lines, then all of them again after the last.
+    pub fn cycle_source_only(&mut self) {
+        if self.source_labels.len() < 2 {
+            self.source_only = None;
+            self.status_message = Some("Only one source".to_string());
+            return;
+        }
+        self.source_only = match self.source_only {
+            None => Some(0),
+            Some(source) if source + 1 < self.source_labels.len() => Some(source + 1),
+            Some(_) => None,
+        };
+        self.rebuild_filtered_indices();
+        self.status_message = Some(match self.source_only {
+            Some(source) => format!("Showing only source:{}", self.source_labels[source]),
+            None => "Showing all sources".to_string(),
+        });
+    }
+
+    pub fn toggle_invert_filter(&mut self) {
+        self.filter_state.inverted = !self.filter_state.inverted;
+        self.rebuild_filtered_indices();
+        if self.filter_state.filter_expr.is_some() {
+            self.status_message = Some(self.filter_summary());
+        }
+    }
+
+    pub fn toggle_filter_after_hide(&mut self) {
+        self.filter_state.filter_after_hide = !self.filter_state.filter_after_hide;
+        self.rebuild_filtered_indices();
+        self.save_state();
+        self.status_message = Some(
+            if self.filter_state.filter_after_hide {
+                "Filter matches lines after hiding"
+            } else {
+                "Filter matches raw lines"
+            }
+            .to_string(),
+        );
+    }
+
+    pub fn toggle_compact_gutter(&mut self) {
+        self.compact_gutter = !self.compact_gutter;
+        self.save_state();
+    }
+
+    pub fn toggle_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
+        self.save_state();
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap_lines = !self.wrap_lines;
+        self.save_state();
+    }
+
+    pub fn prefix_width(&self) -> usize {
+        let extract_width = if self.extract_regex.is_some() {
+            EXTRACT_COLUMN_WIDTH
+        } else {
+            0
+        };
+        extract_width + self.source_label_width() + self.gutter_width()
+    }
+
+    fn gutter_width(&self) -> usize {
+        let hidden_numbers = if self.show_line_numbers {
+            0
+        } else {
+            LINE_NUMBER_WIDTH - GUTTER_SEPARATOR_WIDTH
+        };
+        let width = if self.compact_gutter {
+            self.time_format.width() + LEVEL_GUTTER_WIDTH + LINE_NUMBER_WIDTH
+        } else if self.show_time {
+            PREFIX_WIDTH_WITH_TIME
+        } else {
+            PREFIX_WIDTH_WITHOUT_TIME
+        };
+        width - hidden_numbers
+    }
+}
+
+/// Absolute indices of the matching lines plus up to `context` lines on either
+/// side of each, in order and without duplicates where windows overlap.
+fn with_context(
+    matches: &VecDeque<bool>,
+    first_line_idx: usize,
+    context: usize,
+) -> VecDeque<usize> {
+    let mut indices = VecDeque::new();
+    let mut next = 0;
+    for pos in (0..matches.len()).filter(|&pos| matches[pos]) {
+        let start = pos.saturating_sub(context).max(next);
+        let end = (pos + context + 1).min(matches.len());
+        indices.extend((start..end).map(|pos| first_line_idx + pos));
+        next = end;
+    }
+    indices
+}
+
+fn validate_state(state: &AppState) -> Result<(), String> {
+    let opts = FilterOptions {
+        case_insensitive: state.case_insensitive,
+        smart_case: state.smart_case,
+        literal: state.literal,
+        whole_word: state.whole_word,
+    };
+    validate_expressions(
+        &state.hide_input,
+        &state.filter_input,
+        &state.highlight_input,
+        opts,
+    )?;
+    if !state.line_start_regex.trim().is_empty() {
+        Regex::new(&state.line_start_regex).map_err(|e| format!("line start: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Check `--hide`/`--filter`/`--highlight` before the UI starts.
+pub fn validate_presets(presets: &Presets, opts: FilterOptions) -> Result<(), String> {
+    let text = |preset: &Option<String>| preset.clone().unwrap_or_default();
+    validate_expressions(
+        &text(&presets.hide),
+        &text(&presets.filter),
+        &text(&presets.highlight),
+        opts,
+    )
+}
+
+fn validate_expressions(
+    hide: &str,
+    filter: &str,
+    highlight: &str,
+    opts: FilterOptions,
+) -> Result<(), String> {
+    if !hide.trim().is_empty() {
+        compile_hide_regex(hide, opts.case_insensitive).map_err(|e| format!("hide: {}", e))?;
+    }
+    if !filter.trim().is_empty() {
+        parse_filter_opts(filter, opts).map_err(|e| format!("filter: {}", e))?;
+    }
+    if !highlight.trim().is_empty() {
+        parse_filter_opts(highlight, opts).map_err(|e| format!("highlight: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+    use std::sync::mpsc;
+
+    fn app_with_lines(lines: &[&str]) -> App {
+        let (_tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        for line in lines {
+            let idx = app.log_state.add_line(line.to_string());
+            app.index_new_line(idx);
+        }
+        app
+    }
+
+    fn set_filter(app: &mut App, text: &str) {
+        app.input_fields.filter = crate::input::TextInput::new(text.to_string());
+        app.apply_filter();
+    }
+
+    #[test]
+    fn test_output_template_for_copied_lines() {
+        let mut app = app_with_lines(&["user=ann token=abc login"]);
+        set_hide(&mut app, r"token=\w+ ");
+        app.output_template = Some(OutputTemplate::parse("{line}|{content}|{raw}").unwrap());
+        assert_eq!(
+            app.selected_line_text(),
+            Some((1, "1|user=ann login|user=ann token=abc login".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_visual_selection() {
+        let mut app = app_with_lines(&["a 1", "b", "a 2", "a 3", "a 4"]);
+        app.show_time = false;
+        set_filter(&mut app, "a");
+        app.log_state.selected_idx = Some(1);
+        app.toggle_visual();
+        app.select_down();
+        app.select_down();
+        assert_eq!(app.visual_range(), Some(1..4));
+        assert_eq!(app.visual_text(), Some((3, "a 2\na 3\na 4".to_string())));
+        app.select_up();
+        app.select_up();
+        app.select_up();
+        assert_eq!(
+            app.visual_text(),
+            Some((2, "a 1\na 2".to_string())),
+            "extends above the anchor"
+        );
+
+        set_filter(&mut app, "4");
+        assert_eq!(
+            app.visual_range(),
+            None,
+            "a new filter drops the cursor line"
+        );
+        app.select_up();
+        assert_eq!(app.visual_anchor, None);
+    }
+
+    #[test]
+    fn test_filter_history() {
+        let mut app = app_with_lines(&["a"]);
+        for text in ["error", "error", "(oops", "warn"] {
+            set_filter(&mut app, text);
+        }
+        assert_eq!(app.input_history.filter, ["error", "warn"]);
+
+        app.input_mode = InputMode::FilterEdit;
+        app.input_fields.filter = crate::input::TextInput::new("draft".to_string());
+        let mut press = |key: KeyCode| {
+            app.handle_input_key(key.into());
+            (
+                app.input_fields.filter.text.clone(),
+                app.input_fields.filter.cursor,
+            )
+        };
+        assert_eq!(press(KeyCode::Up), ("warn".to_string(), 4));
+        assert_eq!(press(KeyCode::Up).0, "error");
+        assert_eq!(press(KeyCode::Up).0, "error", "stops at the oldest");
+        assert_eq!(press(KeyCode::Down).0, "warn");
+        assert_eq!(press(KeyCode::Down).0, "draft");
+        assert_eq!(press(KeyCode::Down).0, "draft");
+
+        app.input_mode = InputMode::GotoLineEdit;
+        app.handle_input_key(KeyCode::Up.into());
+        assert!(app.input_fields.goto_line.is_empty());
+    }
+
+    #[test]
+    fn test_live_validation_does_not_apply() {
+        let mut app = app_with_lines(&["a b", "other"]);
+        app.validate_delay = Duration::ZERO;
+        app.input_mode = InputMode::FilterEdit;
+        for c in "a && (b".chars() {
+            app.handle_input_key(KeyCode::Char(c).into());
+        }
+        app.validate_pending_input();
+        assert!(app.input_fields.filter.has_error());
+        assert_eq!(app.validation_due_in(), None);
+
+        app.handle_input_key(KeyCode::Char(')').into());
+        app.validate_pending_input();
+        assert!(!app.input_fields.filter.has_error());
+        assert!(app.filter_state.filter_expr.is_none(), "only Enter applies");
+        assert_eq!(app.log_state.filtered_indices.len(), 2);
+
+        // Typing restarts the wait.
+        app.validate_delay = Duration::from_secs(60);
+        app.handle_input_key(KeyCode::Backspace.into());
+        app.validate_pending_input();
+        assert!(!app.input_fields.filter.has_error());
+        assert!(app
+            .validation_due_in()
+            .is_some_and(|due| due > Duration::ZERO));
+    }
+
+    #[test]
+    fn test_auto_json_detection() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        for i in 0..JSON_SAMPLE_LINES {
+            tx.send(SourceEvent::Line(format!(
+                r#"{{"level":"info","n":{}}}"#,
+                i
+            )))
+            .unwrap();
+        }
+        assert!(!app.json_highlight);
+        app.poll_source();
+        assert!(app.json_highlight);
+        assert!(!app.auto_json);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .contains("JSON detected"));
+    }
+
+    #[test]
+    fn test_auto_json_skips_plain_logs() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        for i in 0..JSON_SAMPLE_LINES {
+            tx.send(SourceEvent::Line(format!("INFO plain line {}", i)))
+                .unwrap();
+        }
+        app.poll_source();
+        assert!(!app.json_highlight);
+        assert!(!app.auto_json);
+    }
+
+    fn set_hide(app: &mut App, text: &str) {
+        app.input_fields.hide = crate::input::TextInput::new(text.to_string());
+        app.apply_hide();
+    }
+
+    #[test]
+    fn test_hide_capture_groups_on_repeated_matches() {
+        let mut app = app_with_lines(&["foo12bar and foo345bar, foo6bar"]);
+        set_hide(&mut app, r"foo(\d+)bar");
+        let line = app.log_state.line(0).clone();
+        assert_eq!(
+            app.get_display_content(&line).unwrap(),
+            "foobar and foobar, foobar"
+        );
+        assert_eq!(
+            app.log_state.display_content(0),
+            "foobar and foobar, foobar"
+        );
+    }
+
+    #[test]
+    fn test_hide_empty_matches_and_multibyte_text() {
+        let mut app = app_with_lines(&["ünïcødé 12 ok"]);
+        set_hide(&mut app, r"\d*");
+        let line = app.log_state.line(0).clone();
+        assert_eq!(app.get_display_content(&line).unwrap(), "ünïcødé  ok");
+    }
+
+    #[test]
+    fn test_mouse_scroll_over_log_view() {
+        let lines: Vec<String> = (0..50).map(|i| format!("line {}", i)).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut app = app_with_lines(&lines);
+        app.log_view_area = Rect::new(0, 12, 80, 22);
+
+        app.mouse_scroll(5, 2, true);
+        assert!(app.log_state.follow_tail, "outside the log view");
+
+        app.mouse_scroll(5, 20, true);
+        assert!(!app.log_state.follow_tail);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 46);
+
+        for _ in 0..20 {
+            app.mouse_scroll(5, 20, true);
+        }
+        assert_eq!(app.log_state.get_bottom_line_idx(), 19);
+
+        for _ in 0..20 {
+            app.mouse_scroll(5, 20, false);
+        }
+        assert!(app.log_state.follow_tail);
+    }
+
+    #[test]
+    fn test_horizontal_scroll_is_clamped() {
+        let mut app = app_with_lines(&["short"]);
+        app.h_scroll_max = 10;
+        app.scroll_horizontal(8);
+        assert_eq!(app.h_scroll, 0, "wrapping is on by default");
+
+        app.wrap_lines = false;
+        app.scroll_horizontal(8);
+        app.scroll_horizontal(8);
+        assert_eq!(app.h_scroll, 10);
+        app.scroll_horizontal(-16);
+        assert_eq!(app.h_scroll, 0);
+    }
+
+    #[test]
+    fn test_toggle_follow() {
+        let mut app = app_with_lines(&["a", "b", "c"]);
+        app.log_state.toggle_follow();
+        let idx = app.log_state.add_line("d".to_string());
+        app.index_new_line(idx);
+        app.log_state.scroll_down(10);
+        assert!(
+            !app.log_state.follow_tail,
+            "scrolling to the bottom doesn't resume"
+        );
+        assert_eq!(app.log_state.get_bottom_line_idx(), 3);
+
+        app.log_state.scroll_to_start();
+        app.log_state.toggle_follow();
+        assert!(app.log_state.follow_tail && !app.log_state.paused);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 3);
+
+        app.log_state.scroll_to_start();
+        assert!(!app.log_state.follow_tail);
+    }
+
+    #[test]
+    fn test_pause_keeps_view_while_lines_arrive() {
+        let mut app = app_with_lines(&["a", "b", "c"]);
+        app.log_state.toggle_pause();
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+
+        for line in ["d", "e"] {
+            let idx = app.log_state.add_line(line.to_string());
+            app.index_new_line(idx);
+        }
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+        app.log_state.scroll_down(10);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 4);
+        assert!(
+            !app.log_state.follow_tail,
+            "reaching the bottom doesn't resume while paused"
+        );
+
+        app.log_state.toggle_pause();
+        assert!(app.log_state.follow_tail);
+        let idx = app.log_state.add_line("f".to_string());
+        app.index_new_line(idx);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 5);
+    }
+
+    #[test]
+    fn test_filter_and_heuristic_counts() {
+        let mut app = app_with_lines(&["ERROR one", "info two", "ERROR three", "debug four"]);
+        set_filter(&mut app, "three || four");
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Filter matched 2 of 4 lines (50.0%)")
+        );
+
+        app.heuristic_highlight = false;
+        app.toggle_heuristic();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Heuristic highlighting on: 1 error line")
+        );
+
+        set_filter(&mut app, "ERROR");
+        app.toggle_heuristic();
+        app.toggle_heuristic();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Heuristic highlighting on: 2 error lines")
+        );
+    }
+
+    #[test]
+    fn test_heuristic_rule_toggle() {
+        let mut app = app_with_lines(&["[main] 12:00:00 started"]);
+        let styles = |app: &mut App| -> Vec<(String, Style)> { app.render_line_cached(0) };
+        let bracket = HighlightStyle::Bracket.to_ratatui_style();
+        assert!(styles(&mut app).contains(&("[main]".to_string(), bracket)));
+
+        let idx = HEURISTIC_RULE_NAMES
+            .iter()
+            .position(|&name| name == "bracket")
+            .unwrap();
+        app.toggle_heuristic_rule(idx);
+        assert!(!styles(&mut app).iter().any(|(_, style)| *style == bracket));
+        assert!(styles(&mut app).contains(&(
+            "12:00:00".to_string(),
+            HighlightStyle::Timestamp.to_ratatui_style()
+        )));
+        assert_eq!(
+            app.persisted_state().disabled_heuristics,
+            vec!["bracket".to_string()]
+        );
+
+        let (_tx, rx) = mpsc::channel();
+        let reopened = App::from_state(rx, None, app.persisted_state(), false);
+        assert!(!reopened.heuristic_rules.is_enabled(idx));
+    }
+
+    #[test]
+    fn test_syslog_frames() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        app.syslog = true;
+        tx.send(SourceEvent::Line(
+            "<11>1 2003-10-11T22:14:15Z web1 nginx - - - upstream down".to_string(),
+        ))
+        .unwrap();
+        tx.send(SourceEvent::Line(
+            "<14>Oct 11 22:14:15 db1 postgres[7]: checkpoint".to_string(),
+        ))
+        .unwrap();
+        tx.send(SourceEvent::Line("not a frame".to_string()))
+            .unwrap();
+        app.poll_source();
+
+        assert_eq!(app.log_state.line(0).content, "upstream down");
+        assert_eq!(app.log_state.line(0).timestamp.timestamp(), 1065910455);
+        assert_eq!(app.log_state.line(2).content, "not a frame");
+        assert!(app.log_state.error_lines[0]);
+        let red = HighlightStyle::Error.to_ratatui_style().fg;
+        assert!(app
+            .render_line_cached(0)
+            .iter()
+            .all(|(_, style)| style.fg == red));
+
+        set_filter(&mut app, "host:web || app:postgres");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_stderr_lines_are_tinted() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        tx.send(SourceEvent::StderrLine("ERROR boom".to_string()))
+            .unwrap();
+        tx.send(SourceEvent::Line("plain".to_string())).unwrap();
+        app.poll_source();
+
+        let stderr = app.log_state.line(0).clone();
+        assert_eq!(stderr.kind, LineKind::Stderr);
+        let rendered = app.render_line(&stderr);
+        assert_eq!(rendered[0].1, HighlightStyle::Error.to_ratatui_style());
+        assert_eq!(rendered[1].1.fg, Some(ratatui::style::Color::LightRed));
+
+        let plain = app.log_state.line(1).clone();
+        assert_eq!(app.render_line(&plain)[0].1.fg, None);
+    }
+
+    #[test]
+    fn test_replace_last_line() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        set_filter(&mut app, "done");
+        tx.send(SourceEvent::StderrLine("10% done".to_string()))
+            .unwrap();
+        tx.send(SourceEvent::ReplaceLast("20%".to_string()))
+            .unwrap();
+        app.poll_source();
+        assert_eq!(app.log_state.lines.len(), 1);
+        assert_eq!(app.log_state.line(0).content, "20%");
+        assert_eq!(app.log_state.line(0).kind, LineKind::Stderr);
+        assert!(app.log_state.filtered_indices.is_empty());
+
+        tx.send(SourceEvent::ReplaceLast("100% done".to_string()))
+            .unwrap();
+        app.poll_source();
+        assert_eq!(app.log_state.filtered_indices, vec![0]);
+
+        // Leading context pulled in for the replaced match goes with it.
+        app.context = 1;
+        for line in ["a", "b", "3 done"] {
+            tx.send(SourceEvent::Line(line.to_string())).unwrap();
+        }
+        app.poll_source();
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 2, 3]);
+        app.log_state.selected_idx = Some(3);
+        tx.send(SourceEvent::ReplaceLast("4".to_string())).unwrap();
+        app.poll_source();
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1]);
+        assert_eq!(app.log_state.selected_idx, Some(1));
+    }
+
+    #[test]
+    fn test_system_lines_bypass_filter() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        tx.send(SourceEvent::SystemLine(
+            "[connected: 10.0.0.2:5000]".to_string(),
+        ))
+        .unwrap();
+        tx.send(SourceEvent::Line("noise".to_string())).unwrap();
+        tx.send(SourceEvent::Line("wanted".to_string())).unwrap();
+        app.poll_source();
+        set_filter(&mut app, "wanted");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2]);
+
+        let system = app.log_state.line(0).clone();
+        assert!(app.render_line(&system)[0]
+            .1
+            .add_modifier
+            .contains(Modifier::DIM | Modifier::ITALIC));
+    }
+
+    #[test]
+    fn test_filter_cache_follows_hide_and_filter() {
+        let mut app = app_with_lines(&["secret error", "plain info", "error again"]);
+        set_filter(&mut app, "error");
+        assert_eq!(app.log_state.filter_matches, vec![true, false, true]);
+
+        set_hide(&mut app, "secret error");
+        assert_eq!(
+            app.log_state.hidden_content,
+            vec![Some(String::new()), None, None]
+        );
+        assert_eq!(app.log_state.filter_matches, vec![true, false, true]);
+
+        app.toggle_filter_after_hide();
+        assert_eq!(app.log_state.filter_matches, vec![false, false, true]);
+        assert_eq!(app.log_state.filtered_indices, vec![2]);
+
+        let idx = app.log_state.add_line("secret error late".to_string());
+        app.index_new_line(idx);
+        assert_eq!(app.log_state.hidden_content[idx].as_deref(), Some(" late"));
+        assert_eq!(app.log_state.filtered_indices, vec![2]);
+    }
+
+    #[test]
+    fn test_copied_line_has_absolute_time() {
+        let mut app = app_with_lines(&["boot"]);
+        app.show_time = true;
+        let timestamp = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        app.log_state.lines[0].timestamp = timestamp;
+        assert_eq!(
+            app.selected_line_text(),
+            Some((1, "14:07:09 boot".to_string()))
+        );
+
+        app.time_format = TimeFormat::Epoch;
+        let expected = format!("{} boot", timestamp.timestamp());
+        assert_eq!(app.selected_line_text(), Some((1, expected)));
+
+        app.time_format = TimeFormat::Rfc3339;
+        let (_, text) = app.selected_line_text().unwrap();
+        assert!(text.starts_with("2024-03-05T14:07:09"));
+        assert_eq!(text.len(), TimeFormat::Rfc3339.width() + " boot".len());
+    }
+
+    #[test]
+    fn test_minimap_error_density_and_click() {
+        let lines: Vec<String> = (0..40)
+            .map(|i| {
+                if (30..34).contains(&i) {
+                    format!("request {} failed", i)
+                } else {
+                    format!("ok {}", i)
+                }
+            })
+            .collect();
+        let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut app = app_with_lines(&refs);
+        assert_eq!(
+            app.error_density(4),
+            vec![(0, 10), (0, 10), (0, 10), (4, 10)]
+        );
+
+        app.log_view_area = Rect::new(0, 0, 80, 7);
+        app.minimap_area = Rect::new(79, 1, 1, 4);
+        assert!(!app.minimap_click(10, 1));
+        assert!(app.minimap_click(79, 2));
+        assert_eq!(app.log_state.bottom_line_idx, 19);
+        assert!(!app.log_state.follow_tail);
+
+        set_hide(&mut app, "failed");
+        assert_eq!(app.error_density(4)[3], (0, 10));
+    }
+
+    #[test]
+    fn test_extract_column() {
+        let mut app = app_with_lines(&["GET req=ab12 200", "no id here", "POST req=cd34 500"]);
+        set_hide(&mut app, r"req=\w+ ");
+        app.input_fields.extract = crate::input::TextInput::new(r"req=(\w+)".to_string());
+        app.apply_extract();
+        assert_eq!(
+            app.extracted_field(app.log_state.line(0)).as_deref(),
+            Some("ab12")
+        );
+        assert_eq!(app.extracted_field(app.log_state.line(1)), None);
+        assert_eq!(
+            app.prefix_width(),
+            PREFIX_WIDTH_WITH_TIME + EXTRACT_COLUMN_WIDTH
+        );
+
+        app.input_fields.extract = crate::input::TextInput::new("(oops".to_string());
+        app.apply_extract();
+        assert!(app.input_fields.extract.has_error());
+        assert!(app.extract_regex.is_some());
+
+        app.input_fields.extract = crate::input::TextInput::default();
+        app.apply_extract();
+        assert!(app.extract_regex.is_none());
+        assert_eq!(app.prefix_width(), PREFIX_WIDTH_WITH_TIME);
+        app.toggle_line_numbers();
+        assert_eq!(
+            app.prefix_width(),
+            PREFIX_WIDTH_WITH_TIME - LINE_NUMBER_WIDTH + GUTTER_SEPARATOR_WIDTH
+        );
+    }
+
+    #[test]
+    fn test_duplicate_run() {
+        let mut app = app_with_lines(&["retry", "retry", "id=1 ok", "id=2 ok", "id=3 ok", "done"]);
+        assert_eq!(app.duplicate_run(0), 0..2);
+        assert_eq!(app.duplicate_run(3), 3..4);
+
+        set_hide(&mut app, r"id=\d ");
+        assert_eq!(app.duplicate_run(3), 2..5);
+        assert_eq!(app.duplicate_run(5), 5..6);
+
+        let idx = app.log_state.add_line("done".to_string());
+        app.index_new_line(idx);
+        assert_eq!(app.duplicate_run(5), 5..7);
+    }
+
+    #[test]
+    fn test_render_cache_follows_highlight_changes() {
+        let mut app = app_with_lines(&["GET /health 200", "GET /login 500"]);
+        let plain = app.render_line_cached(1);
+        assert_eq!(app.render_line_cached(1), plain);
+
+        app.input_fields.highlight = crate::input::TextInput::new("login".to_string());
+        app.apply_highlight();
+        let highlighted = app.render_line_cached(1);
+        assert_ne!(highlighted, plain);
+        assert!(highlighted.iter().any(|(text, _)| text == "login"));
+
+        set_hide(&mut app, "GET ");
+        assert_eq!(app.render_line_cached(1)[0].0, "/");
+    }
+
+    #[test]
+    fn test_render_cache_is_bounded() {
+        let lines: Vec<String> = (0..100).map(|i| format!("line {}", i)).collect();
+        let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut app = app_with_lines(&refs);
+        app.log_view_area = Rect::new(0, 0, 80, 7);
+        for start in (0..100).step_by(5) {
+            app.begin_frame();
+            for idx in start..start + 5 {
+                app.render_line_cached(idx);
+            }
+        }
+        assert!(app.highlight_cache.entries.len() <= 5 * HIGHLIGHT_CACHE_FACTOR);
+        assert!((95..100).all(|idx| app.highlight_cache.entries.contains_key(&idx)));
+    }
+
+    #[test]
+    fn test_source_labels_and_file_filter() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        app.source_labels = vec!["access".to_string(), "error".to_string()];
+        tx.send(SourceEvent::TaggedLine(0, "GET /login".to_string()))
+            .unwrap();
+        tx.send(SourceEvent::TaggedLine(1, "db timeout".to_string()))
+            .unwrap();
+        tx.send(SourceEvent::TaggedLine(0, "GET /health".to_string()))
+            .unwrap();
+        app.poll_source();
+        assert_eq!(app.source_label(app.log_state.line(1)), Some("error"));
+        assert_eq!(app.source_label_width(), "access ".len());
+
+        set_filter(&mut app, "file:access");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2]);
+        set_filter(&mut app, "file:err || health");
+        assert_eq!(app.log_state.filtered_indices, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_source_errors_are_counted() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        app.status_message = Some("Copied 3 lines".to_string());
+        for peer in ["10.0.0.5", "10.0.0.7"] {
+            tx.send(SourceEvent::Error(format!(
+                "Read error from {}: reset",
+                peer
+            )))
+            .unwrap();
+        }
+        app.poll_source();
+        assert_eq!(app.status_message.as_deref(), Some("Copied 3 lines"));
+        assert_eq!(app.source_errors.unseen(), 2);
+
+        app.open_error_list();
+        assert_eq!(app.source_errors.unseen(), 0);
+        assert_eq!(
+            app.source_errors.entries()[1].1,
+            "Read error from 10.0.0.7: reset"
+        );
+        assert!(app.error_scroll.is_some());
+    }
+
+    #[test]
+    fn test_peer_sources() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::from_state(rx, None, AppState::default(), false);
+        tx.send(SourceEvent::PeerLine(
+            "10.0.0.5".to_string(),
+            "first".to_string(),
+        ))
+        .unwrap();
+        app.poll_source();
+        assert_eq!(app.source_label_width(), 0, "one client needs no label");
+        tx.send(SourceEvent::PeerLine(
+            "10.0.0.7".to_string(),
+            "second".to_string(),
+        ))
+        .unwrap();
+        tx.send(SourceEvent::PeerLine(
+            "10.0.0.5".to_string(),
+            "third".to_string(),
+        ))
+        .unwrap();
+        app.poll_source();
+        assert_eq!(app.source_labels, vec!["10.0.0.5", "10.0.0.7"]);
+        assert_eq!(app.source_label(app.log_state.line(2)), Some("10.0.0.5"));
+
+        set_filter(&mut app, "source:10.0.0.5");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2]);
+        set_filter(&mut app, "");
+        app.cycle_source_only();
+        app.cycle_source_only();
+        assert_eq!(app.log_state.filtered_indices, vec![1]);
+        app.cycle_source_only();
+        assert_eq!(app.source_only, None);
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_invert_filter() {
+        let mut app = app_with_lines(&["error one", "info", "error two timeout", "debug"]);
+        set_filter(&mut app, "error");
+        app.toggle_invert_filter();
+        assert_eq!(app.log_state.filtered_indices, vec![1, 3]);
+        assert_eq!(app.input_fields.filter.text, "error");
+
+        set_filter(&mut app, "error && timeout");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 3]);
+
+        app.toggle_invert_filter();
+        assert_eq!(app.log_state.filtered_indices, vec![2]);
+    }
+
+    #[test]
+    fn test_max_lines_evicts_oldest() {
+        let mut app = app_with_lines(&[]);
+        app.log_state.max_lines = Some(3);
+        set_filter(&mut app, "keep");
+        for i in 0..5 {
+            let idx = app.log_state.add_line(format!("keep {}", i));
+            app.index_new_line(idx);
+        }
+        assert_eq!(app.log_state.lines.len(), 3);
+        assert_eq!(app.log_state.first_line_idx, 2);
+        assert_eq!(app.log_state.filtered_indices, vec![2, 3, 4]);
+        assert_eq!(app.log_state.filter_matches.len(), 3);
+        assert_eq!(app.log_state.line(2).content, "keep 2");
+
+        set_filter(&mut app, "keep.[34]");
+        assert_eq!(app.log_state.filtered_indices, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_eviction_keeps_scroll_on_same_line() {
+        let mut app = app_with_lines(&["a0", "a1", "a2", "a3"]);
+        app.log_state.max_lines = Some(4);
+        app.log_state.scroll_up(1);
+        let bottom = app.log_state.get_bottom_line_idx();
+        let line_before = app.log_state.filtered_indices[bottom];
+        let idx = app.log_state.add_line("a4".to_string());
+        app.index_new_line(idx);
+        let bottom = app.log_state.get_bottom_line_idx();
+        assert_eq!(app.log_state.filtered_indices[bottom], line_before);
+    }
+
+    #[test]
+    fn test_search_jumps_and_wraps() {
+        let mut app = app_with_lines(&["hit 0", "miss", "hit 2", "miss", "hit 4", "miss"]);
+        app.input_fields.search = crate::input::TextInput::new("hit".to_string());
+        app.apply_search();
+        assert_eq!(app.log_state.get_bottom_line_idx(), 4);
+        assert_eq!(app.search_state.position, Some((3, 3)));
+        assert!(!app.log_state.follow_tail);
+
+        app.search_prev();
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+        app.search_next();
+        app.search_next();
+        assert_eq!(app.log_state.get_bottom_line_idx(), 0);
+        assert_eq!(app.search_state.position, Some((1, 3)));
+        assert_eq!(app.status_message.as_deref(), Some("match 1/3 (wrapped)"));
+
+        app.input_fields.search = crate::input::TextInput::new("nothing".to_string());
+        app.apply_search();
+        assert_eq!(app.search_state.position, None);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 0);
+    }
+
+    #[test]
+    fn test_search_span_outranks_highlight() {
+        let mut app = app_with_lines(&["error here"]);
+        app.input_fields.highlight = crate::input::TextInput::new("error".to_string());
+        app.apply_highlight();
+        app.input_fields.search = crate::input::TextInput::new("err".to_string());
+        app.apply_search();
+        let line = app.log_state.line(0).clone();
+        let spans = app.render_line(&line);
+        assert_eq!(spans[0].0, "err");
+        assert_eq!(
+            spans[0].1,
+            crate::highlight::HighlightStyle::SearchMatch.to_ratatui_style()
+        );
+    }
+
+    fn goto_line(app: &mut App, text: &str) {
+        app.input_fields.goto_line = crate::input::TextInput::new(text.to_string());
+        app.apply_goto_line();
+    }
+
+    #[test]
+    fn test_goto_line() {
+        let mut app = app_with_lines(&["a", "b", "a", "b", "b", "b", "a"]);
+        set_filter(&mut app, "a");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2, 6]);
+        app.status_message = None;
+
+        goto_line(&mut app, "3");
+        assert_eq!(app.log_state.get_bottom_line_idx(), 1);
+        assert!(!app.log_state.follow_tail);
+        assert_eq!(app.status_message, None);
+
+        goto_line(&mut app, "6");
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("line 6 is filtered out")
+        );
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+
+        goto_line(&mut app, "4");
+        assert_eq!(app.log_state.get_bottom_line_idx(), 1);
+
+        goto_line(&mut app, "100");
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("line 100 is not in the buffer")
+        );
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+
+        goto_line(&mut app, "x");
+        assert!(app.input_fields.goto_line.has_error());
+    }
+
+    #[test]
+    fn test_diagnostics_popup() {
+        crate::state::set_state_path(None);
+        let mut app = app_with_lines(&["error one", "info two", "error three"]);
+        set_filter(&mut app, "error");
+        app.open_diagnostics();
+        let popup = app.text_popup.as_ref().unwrap();
+        let row = |label: &str| {
+            popup
+                .rows
+                .iter()
+                .find(|row| row[0].0.trim_end() == label)
+                .map(|row| row[1].0.clone())
+                .unwrap()
+        };
+        assert_eq!(row("Source"), "stdin");
+        assert_eq!(row("Lines"), "3 buffered, 2 shown, 0 dropped");
+        assert_eq!(row("Filter"), "error");
+        assert_eq!(row("Hide"), "(none)");
+        assert!(row("Memory").starts_with('~'));
+        assert!(!popup
+            .rows
+            .iter()
+            .any(|row| row[0].0.trim_end() == "Listening"));
+    }
+
+    #[test]
+    fn test_json_popup_for_cursor_line() {
+        let mut app = app_with_lines(&["plain", r#"req {"a":1,"b":[2,3]}"#]);
+        app.open_json_popup();
+        let popup = app.text_popup.as_ref().unwrap();
+        assert_eq!(popup.title, " JSON (line 2) ");
+        assert_eq!(popup.rows.len(), 7);
+
+        app.scroll_text_popup(false, 100);
+        assert_eq!(app.text_popup.as_ref().unwrap().scroll, 6);
+        app.scroll_text_popup(true, 2);
+        assert_eq!(app.text_popup.as_ref().unwrap().scroll, 4);
+        app.scroll_text_popup(false, usize::MAX);
+        assert_eq!(app.text_popup.as_ref().unwrap().scroll, 6);
+
+        app.text_popup = None;
+        app.log_state.select_up(1);
+        app.open_json_popup();
+        assert!(app.text_popup.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("No JSON on line 1"));
+    }
+
+    #[test]
+    fn test_file_position_round_trip() {
+        let mut app = app_with_lines(&["a 1", "b 2", "a 3", "b 4", "a 5"]);
+        app.position_key = Some("/var/log/app.log".to_string());
+        set_filter(&mut app, "b");
+        app.log_state.scroll_up(1);
+        app.save_file_position();
+        assert_eq!(
+            app.file_positions["/var/log/app.log"],
+            FilePosition {
+                follow_tail: false,
+                bottom_line: 1
+            }
+        );
+
+        let (tx, rx) = mpsc::channel();
+        let state = app.persisted_state();
+        let mut reopened = App::from_state(rx, None, state, false);
+        reopened.position_key = Some("/var/log/app.log".to_string());
+        reopened.restore_position = true;
+        for line in ["a 1", "b 2", "a 3", "b 4", "a 5"] {
+            tx.send(SourceEvent::Line(line.to_string())).unwrap();
+        }
+        tx.send(SourceEvent::CaughtUp).unwrap();
+        reopened.poll_source();
+        assert!(!reopened.log_state.follow_tail);
+        assert_eq!(reopened.log_state.bottom_line_idx, 0);
+
+        // Only the first catch-up restores; later ones leave the view alone.
+        reopened.log_state.scroll_to_end();
+        tx.send(SourceEvent::CaughtUp).unwrap();
+        reopened.poll_source();
+        assert!(reopened.log_state.follow_tail);
+    }
+
+    #[test]
+    fn test_bookmarks_cycle_and_skip_filtered() {
+        let mut app = app_with_lines(&["a 1", "b 2", "a 3", "b 4", "a 5"]);
+        for pos in [0, 2, 3] {
+            app.log_state.selected_idx = Some(pos);
+            app.toggle_bookmark();
+        }
+        assert_eq!(app.bookmarks, HashSet::from([0, 2, 3]));
+
+        app.log_state.selected_idx = Some(4);
+        app.jump_to_bookmark(true);
+        assert_eq!(app.cursor_line_idx(), Some(0));
+        app.jump_to_bookmark(true);
+        assert_eq!(app.cursor_line_idx(), Some(2));
+        app.jump_to_bookmark(false);
+        assert_eq!(app.cursor_line_idx(), Some(0));
+        app.jump_to_bookmark(false);
+        assert_eq!(app.cursor_line_idx(), Some(3));
+
+        // Bookmarks stay on their lines across filters; hidden ones are skipped.
+        set_filter(&mut app, "a");
+        app.log_state.selected_idx = Some(0);
+        app.jump_to_bookmark(true);
+        assert_eq!(app.cursor_line_idx(), Some(2));
+        app.jump_to_bookmark(true);
+        assert_eq!(app.cursor_line_idx(), Some(0));
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Skipped 1 filtered-out bookmark(s)")
+        );
+
+        app.toggle_bookmark();
+        assert_eq!(app.bookmarks, HashSet::from([2, 3]));
+    }
+
+    #[test]
+    fn test_clear_around_cursor_keeps_line_numbers() {
+        let mut app = app_with_lines(&["a", "b1", "c", "b2", "d", "b3"]);
+        set_filter(&mut app, "b");
+        app.log_state.selected_idx = Some(1);
+        app.clear_around_cursor(true);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Cleared 3 line(s) above line 4")
+        );
+        assert_eq!(app.log_state.line_range(), 3..6);
+        assert_eq!(app.log_state.filtered_indices, vec![3, 5]);
+        assert_eq!(app.log_state.selected_idx, Some(0));
+        assert_eq!(app.selected_line_text().unwrap().0, 4);
+
+        app.clear_around_cursor(false);
+        assert_eq!(app.log_state.line_range(), 3..4);
+        assert_eq!(app.log_state.filtered_indices, vec![3]);
+
+        let idx = app.log_state.add_line("b4".to_string());
+        app.index_new_line(idx);
+        assert_eq!(app.log_state.filtered_indices, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_highlight_matches_text() {
+        let mut app = app_with_lines(&[
+            "order=A1 ok",
+            "order=B2 order=A1",
+            "skip order=C3",
+            "order=D4",
+        ]);
+        assert_eq!(app.highlight_matches_text(), None);
+        app.input_fields.highlight = crate::input::TextInput::new(r"order=\w+".to_string());
+        app.apply_highlight();
+        set_filter(&mut app, "!skip");
+        assert_eq!(
+            app.highlight_matches_text(),
+            Some((3, "order=A1\norder=B2\norder=D4".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_selected_line_copy_text() {
+        let mut app = app_with_lines(&["a token=1", "b token=2", "c token=3"]);
+        app.show_time = false;
+        set_hide(&mut app, r"token=\d");
+        assert_eq!(app.selected_line_text(), Some((3, "c ".to_string())));
+
+        app.select_up();
+        app.select_up();
+        assert_eq!(app.log_state.selected_idx, Some(0));
+        assert_eq!(app.selected_line_text(), Some((1, "a ".to_string())));
+        app.select_up();
+        assert_eq!(app.log_state.selected_idx, Some(0));
+
+        app.select_down();
+        assert_eq!(app.selected_line_text(), Some((2, "b ".to_string())));
+    }
+
+    #[test]
+    fn test_select_down_scrolls_past_bottom() {
+        let mut app = app_with_lines(&["0", "1", "2", "3"]);
+        app.log_state.scroll_up(2);
+        assert_eq!(app.log_state.get_bottom_line_idx(), 1);
+        app.select_down();
+        assert_eq!(app.log_state.selected_idx, Some(2));
+        assert_eq!(app.log_state.get_bottom_line_idx(), 2);
+        app.select_down();
+        assert_eq!(app.log_state.get_bottom_line_idx(), 3);
+        assert!(app.log_state.follow_tail);
+    }
+
+    #[test]
+    fn test_profiles_save_and_load() {
+        let mut app = app_with_lines(&["nginx GET", "pg query", "nginx POST"]);
+        set_filter(&mut app, "nginx");
+        app.input_fields.profile_name = TextInput::new("web".to_string());
+        app.save_profile();
+        set_filter(&mut app, "pg");
+        app.input_fields.profile_name = TextInput::new("db".to_string());
+        app.save_profile();
+        assert_eq!(app.profile_names(), vec!["db", "web"]);
+        assert_eq!(app.active_profile.as_deref(), Some("db"));
+
+        app.load_profile("web");
+        assert_eq!(app.input_fields.filter.text, "nginx");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2]);
+
+        app.open_profile_popup();
+        assert_eq!(app.profile_popup, Some(1));
+        app.move_profile_selection(1);
+        assert_eq!(app.selected_profile_name().as_deref(), Some("db"));
+
+        app.delete_profile("web");
+        assert_eq!(app.active_profile, None);
+        assert_eq!(app.profile_names(), vec!["db"]);
+    }
+
+    #[test]
+    fn test_profile_with_bad_filter_reports_error() {
+        let mut app = app_with_lines(&["a"]);
+        app.profiles.insert(
+            "broken".to_string(),
+            Profile {
+                filter_input: "(oops".to_string(),
+                ..Profile::default()
+            },
+        );
+        app.load_profile("broken");
+        assert_eq!(app.input_fields.filter.text, "(oops");
+        assert!(app.input_fields.filter.has_error());
+        assert_eq!(app.active_profile.as_deref(), Some("broken"));
+    }
+
+    #[test]
+    fn test_ansi_rendering_toggle() {
+        let mut app = app_with_lines(&["\x1b[31mred\x1b[0m plain"]);
+        app.show_time = false;
+        let line = app.log_state.line(0).clone();
+        let rendered = app.render_line(&line);
+        assert_eq!(rendered[0].0, "red");
+        assert_eq!(rendered[0].1.fg, Some(ratatui::style::Color::Red));
+        assert_eq!(app.selected_line_text(), Some((1, "red plain".to_string())));
+
+        app.toggle_ansi();
+        let rendered: String = app
+            .render_line(&line)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(rendered, line.content);
+
+        app.toggle_ansi();
+        app.no_color = true;
+        assert_eq!(
+            app.render_line(&line),
+            vec![("red plain".to_string(), Style::default())]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_filter_keeps_results() {
+        let mut app = app_with_lines(&["a", "b", "a"]);
+        set_filter(&mut app, "a");
+        app.log_state.bottom_line_idx = 1;
+        set_filter(&mut app, " a ");
+        assert_eq!(app.log_state.bottom_line_idx, 1);
+        set_hide(&mut app, "");
+        assert_eq!(app.log_state.bottom_line_idx, 1);
+        set_filter(&mut app, "b");
+        assert_eq!(app.log_state.filtered_indices, vec![1]);
+    }
+
+    #[test]
+    fn test_case_insensitive_toggle_reapplies_filter() {
+        let mut app = app_with_lines(&["ERROR a", "error b", "info c"]);
+        app.smart_case = false;
+        set_filter(&mut app, "error");
+        assert_eq!(app.log_state.filtered_indices, vec![1]);
+        app.toggle_case_insensitive();
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1]);
+        app.toggle_case_insensitive();
+        assert_eq!(app.log_state.filtered_indices, vec![1]);
+    }
+
+    #[test]
+    fn test_smart_case_is_on_by_default() {
+        let mut app = app_with_lines(&["ERROR a", "error b", "Error c"]);
+        set_filter(&mut app, "error");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 2]);
+        set_filter(&mut app, "Error");
+        assert_eq!(app.log_state.filtered_indices, vec![2]);
+        app.toggle_case_insensitive();
+        assert_eq!(
+            app.log_state.filtered_indices,
+            vec![0, 1, 2],
+            "ignore case wins"
+        );
+        app.toggle_case_insensitive();
+        app.toggle_smart_case();
+        set_filter(&mut app, "error");
+        assert_eq!(app.log_state.filtered_indices, vec![1]);
+    }
+
+    #[test]
+    fn test_literal_toggle_accepts_regex_metacharacters() {
+        let mut app = app_with_lines(&["call foo(bar)", "foobar"]);
+        set_filter(&mut app, "\"foo(\"");
+        assert!(app.input_fields.filter.error.is_some());
+        app.toggle_literal();
+        assert!(app.input_fields.filter.error.is_none());
+        assert_eq!(app.log_state.filtered_indices, vec![0]);
+    }
+
+    #[test]
+    fn test_reload_applies_new_settings() {
+        use crate::config::CustomRuleConfig;
+        use ratatui::style::Color;
+
+        let mut app = app_with_lines(&["zqxreload here", "warn b", "info c"]);
+        app.show_time = false;
+        let line = app.log_state.line(0).clone();
+        let magenta = |runs: Vec<(String, Style)>| {
+            runs.into_iter()
+                .find(|(_, style)| style.fg == Some(Color::Magenta))
+                .map(|(text, _)| text)
+        };
+        assert_eq!(magenta(app.render_line(&line)), None);
+
+        let state = AppState {
+            filter_input: "zqxreload || info".to_string(),
+            wrap_lines: false,
+            ..AppState::default()
+        };
+        let config = Config {
+            custom_rules: vec![CustomRuleConfig {
+                pattern: "zqxreload".to_string(),
+                color: "magenta".to_string(),
+                bold: false,
+                priority: 20,
+            }],
+            ..Config::default()
+        };
+        app.reload_from(state, Ok(config));
+        assert_eq!(app.input_fields.filter.text, "zqxreload || info");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 2]);
+        assert!(!app.wrap_lines);
+        assert_eq!(
+            magenta(app.render_line(&line)).as_deref(),
+            Some("zqxreload")
+        );
+    }
+
+    #[test]
+    fn test_presets_are_not_persisted_until_edited() {
+        let (_tx, rx) = mpsc::channel();
+        let mut state = AppState {
+            filter_input: "saved".to_string(),
+            highlight_input: "kept".to_string(),
+            ..AppState::default()
+        };
+        let presets = Presets {
+            filter: Some("error".to_string()),
+            ..Presets::default()
+        };
+        let shadowed = presets.apply_to(&mut state);
+        let mut app = App::from_state(rx, None, state, false);
+        app.shadowed = shadowed;
+        assert_eq!(app.input_fields.filter.text, "error");
+        assert_eq!(app.persisted_state().filter_input, "saved");
+        assert_eq!(app.persisted_state().highlight_input, "kept");
+
+        app.input_mode = InputMode::FilterEdit;
+        app.input_fields.filter = TextInput::new("warn".to_string());
+        app.apply_current_input();
+        assert_eq!(app.persisted_state().filter_input, "warn");
+    }
+
+    #[test]
+    fn test_reload_keeps_old_settings_on_error() {
+        let mut app = app_with_lines(&["error a", "warn b"]);
+        set_filter(&mut app, "error");
+        let state = AppState {
+            filter_input: "(unclosed".to_string(),
+            ..AppState::default()
+        };
+        app.reload_from(state, Ok(Config::default()));
+        assert_eq!(app.input_fields.filter.text, "error");
+        assert_eq!(app.log_state.filtered_indices, vec![0]);
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .starts_with("Reload failed"));
+
+        set_filter(&mut app, "error");
+        let config = Config {
+            custom_rules: vec![crate::config::CustomRuleConfig {
+                pattern: "(unclosed".to_string(),
+                color: "red".to_string(),
+                bold: false,
+                priority: 20,
+            }],
+            ..Config::default()
+        };
+        app.reload_from(AppState::default(), Ok(config));
+        assert_eq!(app.input_fields.filter.text, "error");
+        assert!(app
+            .status_message
+            .as_deref()
+            .unwrap()
+            .contains("Invalid custom_rules"));
+    }
+
+    #[test]
+    fn test_refined_filter_matches_full_rescan() {
+        let lines = [
+            "error db timeout",
+            "error cache miss",
+            "warn db slow",
+            "info db ok",
+            "error db retry",
+        ];
+        let mut app = app_with_lines(&lines);
+        set_filter(&mut app, "error");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 4]);
+
+        set_filter(&mut app, "error && db && !retry");
+        let refined = app.log_state.filtered_indices.clone();
+        app.rebuild_filtered_indices();
+        assert_eq!(refined, app.log_state.filtered_indices);
+        assert_eq!(refined, vec![0]);
+    }
+
+    #[test]
+    fn test_non_refining_filter_rescans() {
+        let mut app = app_with_lines(&["error a", "warn b", "info c"]);
+        set_filter(&mut app, "error");
+        // Widening the filter must bring back lines the previous filter excluded.
+        set_filter(&mut app, "error || warn");
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let lines = ["a", "error 1", "b", "c", "error 2", "d", "e", "f", "g"];
+        let mut app = app_with_lines(&lines);
+        set_filter(&mut app, "error");
+        app.adjust_context(true);
+        // The windows around both matches touch and are merged.
+        assert_eq!(app.log_state.filtered_indices, vec![0, 1, 2, 3, 4, 5]);
+        assert!(app.is_context_line(3));
+        assert!(!app.is_context_line(4));
+        assert_eq!(app.context_line_count(), 4);
+
+        // Appended lines extend the trailing window and pull in leading context.
+        for line in ["h", "error 3", "i", "j"] {
+            let idx = app.log_state.add_line(line.to_string());
+            app.index_new_line(idx);
+        }
+        let appended = app.log_state.filtered_indices.clone();
+        app.rebuild_filtered_indices();
+        assert_eq!(appended, app.log_state.filtered_indices);
+        assert_eq!(appended, vec![0, 1, 2, 3, 4, 5, 9, 10, 11]);
+
+        app.adjust_context(false);
+        assert_eq!(app.log_state.filtered_indices, vec![1, 4, 10]);
     }
 }
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::io::Write;
+use std::sync::OnceLock;
+
+/// Terminals commonly drop OSC 52 sequences beyond roughly 100KB.
+const OSC52_MAX_ENCODED: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClipboardMode {
+    /// OSC 52 over SSH (`SSH_CONNECTION` set), the system clipboard otherwise.
+    Auto,
+    System,
+    /// Ask the terminal to set its clipboard, which works across SSH.
+    Osc52,
+}
+
+static MODE: OnceLock<ClipboardMode> = OnceLock::new();
+
+/// Pick the clipboard backend; must be called before the first copy.
+pub fn set_mode(mode: ClipboardMode) {
+    let _ = MODE.set(mode);
+}
+
+fn use_osc52() -> bool {
+    match MODE.get().copied().unwrap_or(ClipboardMode::Auto) {
+        ClipboardMode::Auto => std::env::var_os("SSH_CONNECTION").is_some(),
+        ClipboardMode::System => false,
+        ClipboardMode::Osc52 => true,
+    }
+}
+
+/// Copy `text` and return how many of its bytes were copied, which is less
+/// than `text.len()` when OSC 52 had to truncate it.
+pub fn copy_to_clipboard(text: &str) -> Result<usize> {
+    if use_osc52() {
+        let (sequence, copied) = osc52_sequence(text);
+        let mut stdout = std::io::stdout();
+        stdout.write_all(sequence.as_bytes())?;
+        stdout.flush()?;
+        Ok(copied)
+    } else {
+        copy_to_system_clipboard(text)?;
+        Ok(text.len())
+    }
+}
+
+/// The OSC 52 sequence for the longest prefix of `text` that fits the size
+/// limit, and that prefix's length.
+fn osc52_sequence(text: &str) -> (String, usize) {
+    let max_len = OSC52_MAX_ENCODED / 4 * 3;
+    let mut len = text.len().min(max_len);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    (
+        format!("\x1b]52;c;{}\x07", base64_encode(&text.as_bytes()[..len])),
+        len,
+    )
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "arboard")]
+fn copy_to_system_clipboard(text: &str) -> Result<()> {
+    use std::sync::Mutex;
+
+    // On X11 and Wayland the clipboard is served by its owner, so the handle
+    // has to outlive the copy rather than being dropped right away.
+    static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    clipboard.as_mut().unwrap().set_text(text)?;
+    Ok(())
+}
+
+/// Pipe `text` to the platform's clipboard command, for builds without the
+/// `arboard` feature.
+#[cfg(not(feature = "arboard"))]
+fn copy_to_system_clipboard(text: &str) -> Result<()> {
+    use anyhow::anyhow;
+    use std::process::{Command, Stdio};
+
+    let commands: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    for (command, args) in commands {
+        let Ok(mut child) = Command::new(command)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        return match child.wait()? {
+            status if status.success() => Ok(()),
+            status => Err(anyhow!("{} exited with {}", command, status)),
+        };
+    }
+    Err(anyhow!("no clipboard command found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(
+            base64_encode("héllo wörld".as_bytes()),
+            "aMOpbGxvIHfDtnJsZA=="
+        );
+    }
+
+    #[test]
+    fn test_osc52_truncates_on_char_boundary() {
+        let (sequence, copied) = osc52_sequence("hi");
+        assert_eq!(sequence, "\x1b]52;c;aGk=\x07");
+        assert_eq!(copied, 2);
+
+        let long = "é".repeat(OSC52_MAX_ENCODED);
+        let (sequence, copied) = osc52_sequence(&long);
+        assert!(copied < long.len() && long.is_char_boundary(copied));
+        assert!(sequence.len() <= OSC52_MAX_ENCODED + 8);
+    }
+}
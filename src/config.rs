@@ -0,0 +1,129 @@
+use crate::constants::VALIDATE_DELAY_MS;
+use crate::highlight::{
+    CustomRule, HighlightConfig, HighlightTheme, DEFAULT_CUSTOM_RULE_PRIORITY,
+    DEFAULT_MAX_HIGHLIGHT_CHARS,
+};
+use crate::state;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// User configuration from `config.json` next to the state file. Unlike the
+/// state file it is never written by the app.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// Highlight rule name to color, see `HighlightTheme::from_names`.
+    #[serde(default)]
+    pub theme: HashMap<String, String>,
+    /// Extra regex highlight rules, applied in addition to the built-in ones.
+    #[serde(default)]
+    pub custom_rules: Vec<CustomRuleConfig>,
+    /// Pause in typing before an input field is checked for errors.
+    pub validate_delay_ms: Option<u64>,
+    /// Chars of a line the highlighters look at, 0 for all of them.
+    pub max_highlight_chars: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CustomRuleConfig {
+    pub pattern: String,
+    pub color: String,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default = "default_rule_priority")]
+    pub priority: u8,
+}
+
+fn default_rule_priority() -> u8 {
+    DEFAULT_CUSTOM_RULE_PRIORITY
+}
+
+impl Config {
+    /// Defaults when there is no config file; a malformed one is an error.
+    pub fn load() -> Result<Self> {
+        match state::config_dir() {
+            Some(dir) => Self::load_from(&dir.join(CONFIG_FILE_NAME)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn theme(&self) -> Result<HighlightTheme> {
+        HighlightTheme::from_names(&self.theme).context("Invalid theme in config file")
+    }
+
+    pub fn validate_delay(&self) -> Duration {
+        Duration::from_millis(self.validate_delay_ms.unwrap_or(VALIDATE_DELAY_MS))
+    }
+
+    /// The theme, custom rules and highlight cap together, for `--theme` `light`.
+    pub fn highlight_config(&self, light: bool) -> Result<HighlightConfig> {
+        Ok(HighlightConfig {
+            theme: self.theme()?.for_background(light),
+            custom_rules: self.custom_rules()?,
+            max_chars: self.max_highlight_chars(),
+        })
+    }
+
+    pub fn max_highlight_chars(&self) -> usize {
+        self.max_highlight_chars
+            .unwrap_or(DEFAULT_MAX_HIGHLIGHT_CHARS)
+    }
+
+    pub fn custom_rules(&self) -> Result<Vec<CustomRule>> {
+        self.custom_rules
+            .iter()
+            .map(|rule| CustomRule::new(&rule.pattern, &rule.color, rule.bold, rule.priority))
+            .collect::<Result<_>>()
+            .context("Invalid custom_rules in config file")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let config = Config::load_from(Path::new("/nonexistent/config.json")).unwrap();
+        assert!(config.theme.is_empty());
+        assert_eq!(config.max_highlight_chars(), DEFAULT_MAX_HIGHLIGHT_CHARS);
+    }
+
+    #[test]
+    fn test_theme_is_read_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("logviewer-config-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r##"{"theme": {"error": "#ff0000", "debug": "245"},
+                "custom_rules": [{"pattern": "GET|POST", "color": "cyan"}, {"pattern": "(", "color": "red"}]}"##,
+        )
+        .unwrap();
+        let config = Config::load_from(&path);
+        let _ = fs::remove_file(&path);
+        let config = config.unwrap();
+        assert_eq!(config.theme["error"], "#ff0000");
+        assert!(config.theme().is_ok());
+        assert_eq!(
+            config.custom_rules[0].priority,
+            DEFAULT_CUSTOM_RULE_PRIORITY
+        );
+        // Bad patterns are reported instead of being dropped.
+        assert!(config.custom_rules().is_err());
+    }
+}
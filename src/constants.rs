@@ -1,15 +1,44 @@
 pub const TIMESTAMP_WIDTH: usize = 7;
 pub const LINE_NUMBER_WIDTH: usize = 9;
+/// The `│ ` or bookmark `◆ ` after the line number, kept when numbers are off.
+pub const GUTTER_SEPARATOR_WIDTH: usize = 2;
 pub const PREFIX_WIDTH_WITH_TIME: usize = TIMESTAMP_WIDTH + LINE_NUMBER_WIDTH;
 pub const PREFIX_WIDTH_WITHOUT_TIME: usize = LINE_NUMBER_WIDTH;
+/// The ` E ` level letter after the compact gutter's time.
+pub const LEVEL_GUTTER_WIDTH: usize = 3;
+/// Extracted field column, including its trailing separator space.
+pub const EXTRACT_COLUMN_WIDTH: usize = 13;
 
 pub const POLL_INTERVAL_MS: u64 = 50;
+/// The poll timeout doubles up to this while idle. Well under a second, so
+/// relative times still tick every second.
+pub const POLL_INTERVAL_MAX_MS: u64 = 250;
+/// Longest the TUI waits between frames while lines stream in.
+pub const RENDER_INTERVAL_MAX_MS: u64 = 150;
+/// Default pause in typing before an input field is checked for errors.
+pub const VALIDATE_DELAY_MS: u64 = 300;
+pub const MOUSE_SCROLL_LINES: usize = 3;
+pub const H_SCROLL_STEP: usize = 8;
+pub const MINIMAP_WIDTH: u16 = 1;
+/// Longest file label shown when viewing several files.
+pub const SOURCE_LABEL_MAX: usize = 12;
+/// Columns a tab advances to the next multiple of (`--tab-width`).
+pub const DEFAULT_TAB_WIDTH: u8 = 4;
+/// Drawn for each line break in a multi-line entry while lines aren't wrapped.
+pub const LINE_BREAK_MARKER: &str = "↵";
+/// Columns aligned by `C`; the last one takes the rest of the line.
+pub const ALIGN_MAX_COLUMNS: usize = 16;
+/// Highlighted lines kept per row of the log view.
+pub const HIGHLIGHT_CACHE_FACTOR: usize = 4;
+
+pub const JSON_SAMPLE_LINES: usize = 10;
 
 pub const INPUT_FIELD_HEIGHT: u16 = 3;
 pub const STATUS_BAR_HEIGHT: u16 = 1;
 
+/// Minimum width of the editing help shown next to the input fields.
 pub const HELP_POPUP_WIDTH: u16 = 40;
-pub const HELP_POPUP_HEIGHT: u16 = 5;
 
 pub const QUIT_POPUP_WIDTH: u16 = 40;
 pub const QUIT_POPUP_HEIGHT: u16 = 5;
+pub const TEXT_POPUP_MIN_WIDTH: u16 = 24;
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+/// Most errors kept; older ones are dropped.
+const ERROR_LOG_LIMIT: usize = 200;
+
+/// Errors reported by the log source, oldest first, with how many arrived
+/// since they were last looked at.
+#[derive(Clone, Default)]
+pub struct ErrorLog {
+    entries: VecDeque<(DateTime<Local>, String)>,
+    unseen: usize,
+}
+
+impl ErrorLog {
+    pub fn push(&mut self, time: DateTime<Local>, message: String) {
+        if self.entries.len() == ERROR_LOG_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((time, message));
+        self.unseen += 1;
+    }
+
+    pub fn entries(&self) -> &VecDeque<(DateTime<Local>, String)> {
+        &self.entries
+    }
+
+    /// Errors since `mark_seen`, counting ones already dropped.
+    pub fn unseen(&self) -> usize {
+        self.unseen
+    }
+
+    pub fn mark_seen(&mut self) {
+        self.unseen = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.unseen = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_log_is_bounded() {
+        let mut log = ErrorLog::default();
+        let now = Local::now();
+        for i in 0..ERROR_LOG_LIMIT + 5 {
+            log.push(now, format!("read error {}", i));
+        }
+        assert_eq!(log.entries().len(), ERROR_LOG_LIMIT);
+        assert_eq!(log.entries()[0].1, "read error 5");
+        assert_eq!(log.unseen(), ERROR_LOG_LIMIT + 5);
+
+        log.mark_seen();
+        log.push(now, "again".to_string());
+        assert_eq!(log.unseen(), 1);
+        log.clear();
+        assert!(log.entries().is_empty());
+        assert_eq!(log.unseen(), 0);
+    }
+}
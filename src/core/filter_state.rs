@@ -1,11 +1,23 @@
-use crate::filter::FilterExpr;
-use fancy_regex::Regex;
+use crate::filter::{FilterExpr, LineFields};
+use fancy_regex::{Regex, RegexBuilder};
+
+pub fn compile_hide_regex(pattern: &str, case_insensitive: bool) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| e.to_string())
+}
 
 #[derive(Clone)]
 pub struct FilterState {
     pub hide_regex: Option<Regex>,
     pub filter_expr: Option<FilterExpr>,
     pub highlight_expr: Option<FilterExpr>,
+    /// Show the lines `filter_expr` excludes instead of the ones it matches.
+    pub inverted: bool,
+    /// Match `filter_expr` against the content left after `hide_regex` instead
+    /// of the raw line. Off by default, so hiding never changes which lines show.
+    pub filter_after_hide: bool,
 }
 
 impl Default for FilterState {
@@ -14,6 +26,8 @@ impl Default for FilterState {
             hide_regex: None,
             filter_expr: None,
             highlight_expr: None,
+            inverted: false,
+            filter_after_hide: false,
         }
     }
 }
@@ -49,7 +63,12 @@ impl FilterState {
                         let abs_end = search_start + full_match.end();
                         ranges_to_remove.push((abs_start, abs_end));
                     }
-                    search_start += full_match.end().max(1);
+                    search_start += match full_match.end() {
+                        // Step over one whole char so an empty match never
+                        // leaves the next slice inside a multi-byte char.
+                        0 => hay.chars().next().map_or(1, char::len_utf8),
+                        end => end,
+                    };
                 }
                 Ok(None) => break,
                 Err(e) => return Err(e.to_string()),
@@ -86,9 +105,10 @@ impl FilterState {
         Ok(result)
     }
 
-    pub fn matches_filter(&self, content: &str) -> bool {
+    /// `fields` holds the line's file label and syslog host/app for field terms.
+    pub fn matches_filter(&self, content: &str, fields: &LineFields) -> bool {
         match &self.filter_expr {
-            Some(expr) => expr.matches(content),
+            Some(expr) => expr.matches_line(content, fields) != self.inverted,
             None => true,
         }
     }
@@ -8,6 +8,10 @@ pub enum InputMode {
     FilterEdit,
     HighlightEdit,
     LineStartEdit,
+    SearchEdit,
+    GotoLineEdit,
+    ProfileNameEdit,
+    ExtractEdit,
 }
 
 #[derive(Clone)]
@@ -16,6 +20,10 @@ pub struct InputFields {
     pub filter: TextInput,
     pub highlight: TextInput,
     pub line_start: TextInput,
+    pub search: TextInput,
+    pub goto_line: TextInput,
+    pub profile_name: TextInput,
+    pub extract: TextInput,
 }
 
 impl InputFields {
@@ -25,6 +33,10 @@ impl InputFields {
             filter: TextInput::new(state.filter_input.clone()),
             highlight: TextInput::new(state.highlight_input.clone()),
             line_start: TextInput::new(state.line_start_regex.clone()),
+            search: TextInput::default(),
+            goto_line: TextInput::default(),
+            profile_name: TextInput::default(),
+            extract: TextInput::default(),
         }
     }
 
@@ -34,7 +46,60 @@ impl InputFields {
             InputMode::FilterEdit => Some(&mut self.filter),
             InputMode::HighlightEdit => Some(&mut self.highlight),
             InputMode::LineStartEdit => Some(&mut self.line_start),
+            InputMode::SearchEdit => Some(&mut self.search),
+            InputMode::GotoLineEdit => Some(&mut self.goto_line),
+            InputMode::ProfileNameEdit => Some(&mut self.profile_name),
+            InputMode::ExtractEdit => Some(&mut self.extract),
             InputMode::Normal => None,
         }
     }
 }
+
+/// Most expressions kept per field.
+const HISTORY_LIMIT: usize = 50;
+
+/// Applied hide, filter and highlight expressions, oldest first, recalled with
+/// Up/Down while editing that field.
+#[derive(Clone, Default)]
+pub struct InputHistory {
+    pub hide: Vec<String>,
+    pub filter: Vec<String>,
+    pub highlight: Vec<String>,
+}
+
+impl InputHistory {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            hide: state.hide_history.clone(),
+            filter: state.filter_history.clone(),
+            highlight: state.highlight_history.clone(),
+        }
+    }
+
+    pub fn get(&self, mode: InputMode) -> Option<&Vec<String>> {
+        match mode {
+            InputMode::HideEdit => Some(&self.hide),
+            InputMode::FilterEdit => Some(&self.filter),
+            InputMode::HighlightEdit => Some(&self.highlight),
+            _ => None,
+        }
+    }
+
+    /// Append `entry` to the field's history unless it is blank or repeats the
+    /// newest entry, dropping the oldest past `HISTORY_LIMIT`.
+    pub fn push(&mut self, mode: InputMode, entry: &str) {
+        let history = match mode {
+            InputMode::HideEdit => &mut self.hide,
+            InputMode::FilterEdit => &mut self.filter,
+            InputMode::HighlightEdit => &mut self.highlight,
+            _ => return,
+        };
+        if entry.trim().is_empty() || history.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        history.push(entry.to_string());
+        if history.len() > HISTORY_LIMIT {
+            history.drain(..history.len() - HISTORY_LIMIT);
+        }
+    }
+}
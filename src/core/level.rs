@@ -0,0 +1,100 @@
+use crate::highlight::HighlightStyle;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Severity of a log line. Variants are ordered from least to most severe,
+/// so `level>=warn` is a plain comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+/// Words that mark each level, matched case-insensitively between word
+/// boundaries. The heuristic highlight rules are built from these too.
+const LEVEL_WORDS: [(Level, &str); 6] = [
+    (Level::Trace, "trace"),
+    (Level::Debug, "debug"),
+    (Level::Info, "info"),
+    (Level::Warn, "warn|warning"),
+    (Level::Error, "error|err|fail|failed"),
+    (Level::Fatal, "fatal|panic"),
+];
+
+impl Level {
+    /// A level by name as written in filters, e.g. `warn` or `WARNING`.
+    pub fn from_name(name: &str) -> Option<Level> {
+        let name = name.to_ascii_lowercase();
+        LEVEL_WORDS
+            .iter()
+            .find(|(_, words)| words.split('|').any(|word| word == name))
+            .map(|&(level, _)| level)
+    }
+
+    /// The heuristic highlight style lines of this level are colored with.
+    pub fn style(self) -> HighlightStyle {
+        match self {
+            Level::Trace | Level::Debug => HighlightStyle::Debug,
+            Level::Info => HighlightStyle::Info,
+            Level::Warn => HighlightStyle::Warning,
+            Level::Error | Level::Fatal => HighlightStyle::Error,
+        }
+    }
+}
+
+/// Case-insensitive, word-bounded pattern for any of `levels`.
+pub fn level_pattern(levels: &[Level]) -> String {
+    let words: Vec<&str> = LEVEL_WORDS
+        .iter()
+        .filter(|(level, _)| levels.contains(level))
+        .map(|&(_, words)| words)
+        .collect();
+    format!(r"(?i)\b({})\b", words.join("|"))
+}
+
+static LEVEL_REGEXES: OnceLock<Vec<(Level, Regex)>> = OnceLock::new();
+
+/// The level of the leftmost level word in `text`, `None` if it has none.
+pub fn detect_level(text: &str) -> Option<Level> {
+    LEVEL_REGEXES
+        .get_or_init(|| {
+            LEVEL_WORDS
+                .iter()
+                .map(|&(level, _)| (level, Regex::new(&level_pattern(&[level])).unwrap()))
+                .collect()
+        })
+        .iter()
+        .filter_map(|(level, regex)| regex.find(text).map(|m| (m.start(), *level)))
+        .min_by_key(|(start, _)| *start)
+        .map(|(_, level)| level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_level() {
+        assert_eq!(
+            detect_level("12:00 WARN disk 91% full, error soon"),
+            Some(Level::Warn)
+        );
+        assert_eq!(
+            detect_level("thread panicked: panic at main.rs"),
+            Some(Level::Fatal)
+        );
+        assert_eq!(detect_level("[trace] enter"), Some(Level::Trace));
+        assert_eq!(
+            detect_level("stderr: information"),
+            None,
+            "whole words only"
+        );
+        assert_eq!(Level::from_name("WARNING"), Some(Level::Warn));
+        assert_eq!(Level::from_name("nope"), None);
+        assert!(Level::Trace < Level::Debug && Level::Error < Level::Fatal);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::netinfo::{get_network_interfaces, InterfaceInfo};
+use crate::core::format_relative_time;
+use crate::netinfo::{get_network_interfaces, AddressInfo, InterfaceInfo};
+use chrono::{DateTime, Local};
 use std::net::IpAddr;
 
 #[derive(Clone, Copy, PartialEq, Default)]
@@ -19,12 +21,18 @@ pub struct ListenAddrEntry {
 
 pub struct ListenState {
     pub port: Option<u16>,
-    pub has_connection: bool,
+    /// Local address the port is bound to (`--bind`), instead of all of them.
+    pub bind_addr: Option<IpAddr>,
+    /// Unix socket being listened on, shown instead of a port.
+    pub socket_path: Option<String>,
+    /// Clients currently connected.
+    pub connections: usize,
     pub network_interfaces: Vec<InterfaceInfo>,
     pub display_mode: ListenDisplayMode,
     pub addr_list: Vec<ListenAddrEntry>,
     pub selected_idx: usize,
     pub popup_area: Option<(u16, u16, u16, u16)>,
+    pub active_peer: Option<(String, DateTime<Local>)>,
 }
 
 impl ListenState {
@@ -36,17 +44,92 @@ impl ListenState {
         };
         Self {
             port,
-            has_connection: false,
+            bind_addr: None,
+            socket_path: None,
+            connections: 0,
             network_interfaces,
             display_mode: ListenDisplayMode::default(),
             addr_list: Vec::new(),
             selected_idx: 0,
             popup_area: None,
+            active_peer: None,
         }
     }
 
+    /// Listen on `addr` only: the popup then lists just it, under its interface
+    /// when it has one. An unspecified address still lists every interface.
+    pub fn set_bind_addr(&mut self, addr: IpAddr) {
+        self.bind_addr = Some(addr);
+        if addr.is_unspecified() {
+            return;
+        }
+        let owner = self
+            .network_interfaces
+            .iter()
+            .find(|iface| iface.addresses.iter().any(|info| info.ip == addr));
+        let iface = match owner {
+            Some(iface) => InterfaceInfo {
+                addresses: iface
+                    .addresses
+                    .iter()
+                    .filter(|info| info.ip == addr)
+                    .cloned()
+                    .collect(),
+                ..iface.clone()
+            },
+            // Loopback addresses aren't enumerated.
+            None => InterfaceInfo {
+                name: if addr.is_loopback() {
+                    "loopback"
+                } else {
+                    "bound address"
+                }
+                .to_string(),
+                addresses: vec![AddressInfo {
+                    ip: addr,
+                    is_self_assigned: false,
+                }],
+                is_default: false,
+            },
+        };
+        self.network_interfaces = vec![iface];
+    }
+
+    pub fn on_connected(&mut self, peer: String) {
+        self.connections += 1;
+        self.active_peer = Some((peer, Local::now()));
+    }
+
+    pub fn on_disconnected(&mut self, peer: &str) {
+        self.connections = self.connections.saturating_sub(1);
+        if self.active_peer.as_ref().is_some_and(|(p, _)| p == peer) {
+            self.active_peer = None;
+        }
+    }
+
+    /// Short connection summary for the status bar, or `None` when not listening.
+    pub fn connection_status(&self) -> Option<String> {
+        if self.port.is_none() && self.socket_path.is_none() {
+            return None;
+        }
+        let clients = match self.connections {
+            0 => return Some("waiting".to_string()),
+            1 => "1 client".to_string(),
+            n => format!("{} clients", n),
+        };
+        Some(match &self.active_peer {
+            Some((peer, since)) => format!(
+                "conn: {} since {}, {}",
+                peer,
+                format_relative_time(*since),
+                clients
+            ),
+            None => clients,
+        })
+    }
+
     pub fn show_popup(&self) -> bool {
-        self.port.is_some() && !self.has_connection
+        self.port.is_some() && self.connections == 0
     }
 
     pub fn toggle_display_mode(&mut self) {
@@ -117,3 +200,88 @@ impl ListenState {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_connection_status_tracks_peer() {
+        let mut state = ListenState::new(Some(9000));
+        assert_eq!(state.connection_status().as_deref(), Some("waiting"));
+
+        state.on_connected("10.0.0.5:51234".to_string());
+        state.active_peer.as_mut().unwrap().1 = Local::now() - Duration::minutes(3);
+        assert_eq!(
+            state.connection_status().as_deref(),
+            Some("conn: 10.0.0.5:51234 since -3m, 1 client")
+        );
+
+        state.on_connected("10.0.0.9:4000".to_string());
+        state.on_disconnected("10.0.0.9:4000");
+        assert!(state.active_peer.is_none());
+        assert_eq!(state.connection_status().as_deref(), Some("1 client"));
+
+        state.on_disconnected("10.0.0.5:51234");
+        assert_eq!(state.connection_status().as_deref(), Some("waiting"));
+    }
+
+    #[test]
+    fn test_popup_returns_after_last_client_leaves() {
+        let mut state = ListenState::new(Some(9000));
+        assert!(state.show_popup());
+
+        state.on_connected("10.0.0.5:1".to_string());
+        state.on_connected("10.0.0.6:2".to_string());
+        state.on_disconnected("10.0.0.5:1");
+        assert!(!state.show_popup());
+
+        state.on_disconnected("10.0.0.6:2");
+        assert!(state.show_popup());
+
+        state.on_connected("10.0.0.5:3".to_string());
+        assert!(!state.show_popup());
+    }
+
+    #[test]
+    fn test_bind_addr_limits_listed_addresses() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let mut state = ListenState::new(None);
+        state.network_interfaces = vec![InterfaceInfo {
+            name: "eth0".to_string(),
+            addresses: vec![
+                AddressInfo {
+                    ip: ip("192.168.1.5"),
+                    is_self_assigned: false,
+                },
+                AddressInfo {
+                    ip: ip("fd00::5"),
+                    is_self_assigned: false,
+                },
+            ],
+            is_default: true,
+        }];
+
+        let mut bound = ListenState {
+            network_interfaces: state.network_interfaces.clone(),
+            ..ListenState::new(None)
+        };
+        bound.set_bind_addr(ip("192.168.1.5"));
+        assert_eq!(bound.network_interfaces.len(), 1);
+        assert_eq!(bound.network_interfaces[0].name, "eth0");
+        assert_eq!(bound.network_interfaces[0].addresses.len(), 1);
+
+        bound.set_bind_addr(ip("127.0.0.1"));
+        assert_eq!(bound.network_interfaces[0].name, "loopback");
+        assert_eq!(bound.network_interfaces[0].addresses[0].ip, ip("127.0.0.1"));
+
+        state.set_bind_addr(ip("0.0.0.0"));
+        assert_eq!(state.network_interfaces[0].addresses.len(), 2);
+    }
+
+    #[test]
+    fn test_connection_status_hidden_without_port() {
+        assert_eq!(ListenState::new(None).connection_status(), None);
+    }
+}
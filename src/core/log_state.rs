@@ -1,9 +1,31 @@
-use chrono::{DateTime, Local};
+use crate::core::{nav, NavCommand, Viewport};
+use crate::syslog::SyslogMeta;
+use chrono::{DateTime, Local, SecondsFormat};
+use std::collections::VecDeque;
+use std::ops::Range;
 
 #[derive(Clone, PartialEq)]
 pub struct LogLine {
     pub timestamp: DateTime<Local>,
+    /// Whether `timestamp` was parsed from the line rather than being its arrival time.
+    pub timestamp_parsed: bool,
     pub content: String,
+    pub kind: LineKind,
+    /// Position of the file this came from when viewing several files.
+    pub source: Option<usize>,
+    /// Header of the syslog frame this line's message came from (`--syslog`).
+    pub syslog: Option<Box<SyslogMeta>>,
+}
+
+/// Where a line came from, for sources that distinguish streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineKind {
+    #[default]
+    Output,
+    /// Written to stderr by a spawned command.
+    Stderr,
+    /// A notice from the viewer itself, like `[connected: ...]`. Never filtered out.
+    System,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -18,12 +40,12 @@ pub enum TimeAge {
 pub fn format_relative_time(timestamp: DateTime<Local>) -> String {
     let now = Local::now();
     let duration = now.signed_duration_since(timestamp);
-    
+
     let total_secs = duration.num_seconds();
     if total_secs < 0 {
         return "+0s".to_string();
     }
-    
+
     if total_secs < 60 {
         format!("-{}s", total_secs)
     } else if total_secs < 3600 {
@@ -35,11 +57,41 @@ pub fn format_relative_time(timestamp: DateTime<Local>) -> String {
     }
 }
 
+/// Absolute time style for the compact gutter and copied lines (`--time-format`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeFormat {
+    /// `%H:%M:%S`
+    #[default]
+    Clock,
+    Rfc3339,
+    /// Seconds since the Unix epoch.
+    Epoch,
+}
+
+impl TimeFormat {
+    /// Columns taken by a formatted timestamp.
+    pub fn width(self) -> usize {
+        match self {
+            TimeFormat::Clock => 8,
+            TimeFormat::Rfc3339 => 25,
+            TimeFormat::Epoch => 10,
+        }
+    }
+}
+
+pub fn format_absolute_time(timestamp: DateTime<Local>, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::Clock => timestamp.format("%H:%M:%S").to_string(),
+        TimeFormat::Rfc3339 => timestamp.to_rfc3339_opts(SecondsFormat::Secs, false),
+        TimeFormat::Epoch => timestamp.timestamp().to_string(),
+    }
+}
+
 pub fn get_time_age(timestamp: DateTime<Local>) -> TimeAge {
     let now = Local::now();
     let duration = now.signed_duration_since(timestamp);
     let total_secs = duration.num_seconds();
-    
+
     if total_secs < 15 {
         TimeAge::VeryRecent
     } else if total_secs < 60 {
@@ -55,20 +107,43 @@ pub fn get_time_age(timestamp: DateTime<Local>) -> TimeAge {
 
 #[derive(Clone)]
 pub struct LogState {
-    pub lines: Vec<LogLine>,
-    pub filtered_indices: Vec<usize>,
+    /// Buffered lines. Line indices are absolute: line `idx` is stored at
+    /// `lines[idx - first_line_idx]`, so they stay stable when old lines are evicted.
+    pub lines: VecDeque<LogLine>,
+    /// Per-line content after the hide regex, `None` where it left the line unchanged.
+    pub hidden_content: VecDeque<Option<String>>,
+    /// Per-line result of the current filter, parallel to `lines`.
+    pub filter_matches: VecDeque<bool>,
+    /// Per-line: whether the displayed content hits the error rule, for the minimap.
+    pub error_lines: VecDeque<bool>,
+    pub filtered_indices: VecDeque<usize>,
+    /// Absolute index of the oldest buffered line, i.e. how many lines were evicted.
+    pub first_line_idx: usize,
+    /// Evict the oldest lines once more than this many are buffered.
+    pub max_lines: Option<usize>,
     pub bottom_line_idx: usize,
+    /// Filtered position of the cursor line, `None` until it is moved with `j`/`k`.
+    pub selected_idx: Option<usize>,
     pub follow_tail: bool,
+    /// New lines never move the view, even at the bottom, until unpaused.
+    pub paused: bool,
     pub last_update_time: Option<DateTime<Local>>,
 }
 
 impl Default for LogState {
     fn default() -> Self {
         Self {
-            lines: Vec::new(),
-            filtered_indices: Vec::new(),
+            lines: VecDeque::new(),
+            hidden_content: VecDeque::new(),
+            filter_matches: VecDeque::new(),
+            error_lines: VecDeque::new(),
+            filtered_indices: VecDeque::new(),
+            first_line_idx: 0,
+            max_lines: None,
             bottom_line_idx: 0,
+            selected_idx: None,
             follow_tail: true,
+            paused: false,
             last_update_time: None,
         }
     }
@@ -80,53 +155,244 @@ impl LogState {
     }
 
     pub fn add_line_with_update(&mut self, content: String, update_time: bool) -> usize {
+        self.add_line_at(content, None, LineKind::Output, None, update_time)
+    }
+
+    pub fn add_system_line(&mut self, content: String) -> usize {
+        self.add_line_at(content, None, LineKind::System, None, false)
+    }
+
+    /// Append a line stamped with `timestamp`, or the arrival time if `None`.
+    pub fn add_line_at(
+        &mut self,
+        content: String,
+        timestamp: Option<DateTime<Local>>,
+        kind: LineKind,
+        source: Option<usize>,
+        update_time: bool,
+    ) -> usize {
         let now = Local::now();
         let line = LogLine {
-            timestamp: now,
+            timestamp: timestamp.unwrap_or(now),
+            timestamp_parsed: timestamp.is_some(),
             content,
+            kind,
+            source,
+            syslog: None,
         };
-        let idx = self.lines.len();
-        self.lines.push(line);
+        let idx = self.first_line_idx + self.lines.len();
+        self.lines.push_back(line);
         if update_time {
             self.last_update_time = Some(now);
         }
         idx
     }
 
+    pub fn line(&self, idx: usize) -> &LogLine {
+        &self.lines[idx - self.first_line_idx]
+    }
+
+    /// Absolute indices of the buffered lines.
+    pub fn line_range(&self) -> Range<usize> {
+        self.first_line_idx..self.first_line_idx + self.lines.len()
+    }
+
+    /// Rough size of the buffer in bytes: the lines, their text and the per-line
+    /// caches. Allocator overhead and spare capacity are not counted.
+    pub fn memory_estimate(&self) -> usize {
+        let per_line = size_of::<LogLine>() + size_of::<Option<String>>() + 2 * size_of::<bool>();
+        let text: usize = self.lines.iter().map(|line| line.content.len()).sum();
+        let hidden: usize = self.hidden_content.iter().flatten().map(String::len).sum();
+        self.lines.len() * per_line
+            + text
+            + hidden
+            + self.filtered_indices.len() * size_of::<usize>()
+    }
+
+    /// Content of line `idx` after hiding, from the per-line cache.
+    pub fn display_content(&self, idx: usize) -> &str {
+        let pos = idx - self.first_line_idx;
+        match &self.hidden_content[pos] {
+            Some(hidden) => hidden,
+            None => &self.lines[pos].content,
+        }
+    }
+
+    /// Drop the oldest lines beyond `max_lines`. When not following the tail,
+    /// the scroll position is shifted so the same line stays at the bottom.
+    pub fn evict_overflow(&mut self) {
+        let Some(max_lines) = self.max_lines else {
+            return;
+        };
+        while self.lines.len() > max_lines {
+            self.lines.pop_front();
+            self.hidden_content.pop_front();
+            self.filter_matches.pop_front();
+            self.error_lines.pop_front();
+            if self.filtered_indices.front() == Some(&self.first_line_idx) {
+                self.filtered_indices.pop_front();
+                if !self.follow_tail {
+                    self.bottom_line_idx = self.bottom_line_idx.saturating_sub(1);
+                }
+                self.selected_idx = self.selected_idx.map(|idx| idx.saturating_sub(1));
+            }
+            self.first_line_idx += 1;
+        }
+    }
+
+    /// Remove the newest line, e.g. to put a redrawn version in its place, and
+    /// the lines that were shown only as leading context for it. `context` is
+    /// how many lines are shown around each match.
+    pub fn pop_line(&mut self, context: usize) -> Option<LogLine> {
+        let line = self.lines.pop_back()?;
+        self.hidden_content.pop_back();
+        self.filter_matches.pop_back();
+        self.error_lines.pop_back();
+        // Past the last remaining match, only its trailing context is shown.
+        let shown_until = self
+            .filter_matches
+            .iter()
+            .rposition(|&matches| matches)
+            .map(|pos| self.first_line_idx + pos + context);
+        let end = self.first_line_idx + self.lines.len();
+        while let Some(&idx) = self.filtered_indices.back() {
+            if idx < end && shown_until.is_some_and(|until| idx <= until) {
+                break;
+            }
+            self.filtered_indices.pop_back();
+        }
+        let last = self.filtered_indices.len().saturating_sub(1);
+        self.bottom_line_idx = self.bottom_line_idx.min(last);
+        self.selected_idx = self.selected_idx.map(|pos| pos.min(last));
+        Some(line)
+    }
+
     pub fn clear(&mut self) {
         self.lines.clear();
+        self.hidden_content.clear();
+        self.filter_matches.clear();
+        self.error_lines.clear();
         self.filtered_indices.clear();
+        self.first_line_idx = 0;
         self.bottom_line_idx = 0;
+        self.selected_idx = None;
         self.last_update_time = None;
     }
 
+    /// Drop the buffered lines before absolute index `idx`. The rest keep their
+    /// line numbers, like evicted lines do.
+    pub fn drop_before(&mut self, idx: usize) {
+        let count = idx
+            .saturating_sub(self.first_line_idx)
+            .min(self.lines.len());
+        self.lines.drain(..count);
+        self.hidden_content.drain(..count);
+        self.filter_matches.drain(..count);
+        self.error_lines.drain(..count);
+        self.first_line_idx += count;
+        let removed = self.filtered_indices.partition_point(|&i| i < idx);
+        self.filtered_indices.drain(..removed);
+        self.bottom_line_idx = self.bottom_line_idx.saturating_sub(removed);
+        self.selected_idx = self.selected_idx.map(|pos| pos.saturating_sub(removed));
+    }
+
+    /// Drop the buffered lines after absolute index `idx`. Lines arriving later
+    /// are numbered on from `idx`, reusing the dropped numbers.
+    pub fn drop_after(&mut self, idx: usize) {
+        let keep = (idx + 1)
+            .saturating_sub(self.first_line_idx)
+            .min(self.lines.len());
+        self.lines.truncate(keep);
+        self.hidden_content.truncate(keep);
+        self.filter_matches.truncate(keep);
+        self.error_lines.truncate(keep);
+        let kept = self.filtered_indices.partition_point(|&i| i <= idx);
+        self.filtered_indices.truncate(kept);
+        let last = kept.saturating_sub(1);
+        self.bottom_line_idx = self.bottom_line_idx.min(last);
+        self.selected_idx = self.selected_idx.map(|pos| pos.min(last));
+    }
+
+    /// Scroll for a navigation key, `page` lines at a time for page up/down.
+    pub fn nav(&mut self, cmd: NavCommand, page: usize) {
+        self.apply_nav(cmd, 1, page);
+    }
+
+    /// Positions are bottom line indices; following never resumes while paused.
+    fn apply_nav(&mut self, cmd: NavCommand, line: usize, page: usize) {
+        let viewport = Viewport {
+            offset: self.bottom_line_idx as f64,
+            max_offset: self.filtered_indices.len().saturating_sub(1) as f64,
+            line: line as f64,
+            page: page as f64,
+        };
+        let (offset, follow) = nav(self.follow_tail, cmd, viewport);
+        self.bottom_line_idx = offset as usize;
+        self.follow_tail = follow && !self.paused;
+    }
+
     pub fn scroll_up(&mut self, amount: usize) {
-        if self.follow_tail {
-            self.bottom_line_idx = self.filtered_indices.len().saturating_sub(1);
-        }
-        self.bottom_line_idx = self.bottom_line_idx.saturating_sub(amount);
-        self.follow_tail = false;
+        self.apply_nav(NavCommand::LineUp, amount, 0);
     }
 
     pub fn scroll_down(&mut self, amount: usize) {
-        let max_idx = self.filtered_indices.len().saturating_sub(1);
-        if self.follow_tail {
-            return;
-        }
-        self.bottom_line_idx = (self.bottom_line_idx + amount).min(max_idx);
-        if self.bottom_line_idx >= max_idx {
-            self.follow_tail = true;
+        self.apply_nav(NavCommand::LineDown, amount, 0);
+    }
+
+    /// Move the cursor line up, starting from the bottom line if there is none yet.
+    pub fn select_up(&mut self, amount: usize) {
+        let current = self
+            .selected_idx
+            .unwrap_or_else(|| self.get_bottom_line_idx());
+        self.selected_idx = Some(current.saturating_sub(amount));
+    }
+
+    /// Move the cursor line down, scrolling when it passes the bottom line.
+    pub fn select_down(&mut self, amount: usize) {
+        let current = self
+            .selected_idx
+            .unwrap_or_else(|| self.get_bottom_line_idx());
+        let selected = (current + amount).min(self.filtered_indices.len().saturating_sub(1));
+        self.selected_idx = Some(selected);
+        let bottom = self.get_bottom_line_idx();
+        if selected > bottom {
+            self.bottom_line_idx = bottom;
+            self.follow_tail = false;
+            self.scroll_down(selected - bottom);
         }
     }
 
     pub fn scroll_to_start(&mut self) {
-        self.bottom_line_idx = 0;
-        self.follow_tail = false;
+        self.apply_nav(NavCommand::Top, 0, 0);
     }
 
     pub fn scroll_to_end(&mut self) {
-        self.follow_tail = true;
-        self.bottom_line_idx = self.filtered_indices.len().saturating_sub(1);
+        self.apply_nav(NavCommand::Bottom, 0, 0);
+    }
+
+    /// Freeze the view where it is, or resume following from the tail.
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.scroll_to_end();
+        } else {
+            self.bottom_line_idx = self.get_bottom_line_idx();
+            self.follow_tail = false;
+            self.paused = true;
+        }
+    }
+
+    /// Stop following and hold the view until resumed, like pausing, or jump
+    /// to the tail and follow it.
+    pub fn toggle_follow(&mut self) {
+        if self.follow_tail {
+            self.bottom_line_idx = self.get_bottom_line_idx();
+            self.follow_tail = false;
+            self.paused = true;
+        } else {
+            self.paused = false;
+            self.scroll_to_end();
+        }
     }
 
     pub fn get_bottom_line_idx(&self) -> usize {
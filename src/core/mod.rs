@@ -1,9 +1,24 @@
+pub mod error_log;
 pub mod filter_state;
 pub mod input_state;
+pub mod level;
 pub mod listen_state;
 pub mod log_state;
+pub mod nav;
+pub mod output_template;
+pub mod search_state;
+pub mod throughput;
 
-pub use filter_state::FilterState;
-pub use input_state::{InputFields, InputMode};
+pub use error_log::ErrorLog;
+pub use filter_state::{compile_hide_regex, FilterState};
+pub use input_state::{InputFields, InputHistory, InputMode};
+pub use level::{detect_level, Level};
 pub use listen_state::{ListenAddrEntry, ListenDisplayMode, ListenState};
-pub use log_state::{format_relative_time, get_time_age, LogLine, LogState, TimeAge};
+pub use log_state::{
+    format_absolute_time, format_relative_time, get_time_age, LineKind, LogLine, LogState, TimeAge,
+    TimeFormat,
+};
+pub use nav::{nav, NavCommand, Viewport};
+pub use output_template::OutputTemplate;
+pub use search_state::SearchState;
+pub use throughput::{format_bytes, Throughput};
@@ -0,0 +1,80 @@
+/// Keyboard scrolling shared by the TUI and GUI, so following the tail and
+/// clamping behave the same in both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NavCommand {
+    LineUp,
+    LineDown,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// A vertical scroll position in the frontend's own units (lines or pixels).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub offset: f64,
+    /// Offset at which the last line is in view.
+    pub max_offset: f64,
+    pub line: f64,
+    pub page: f64,
+}
+
+/// The new offset, clamped to `0..=max_offset`, and whether to follow the
+/// tail. Moving up stops following; reaching the bottom resumes it. While
+/// following, the view counts as being at the bottom.
+pub fn nav(follow_tail: bool, cmd: NavCommand, viewport: Viewport) -> (f64, bool) {
+    let max = viewport.max_offset.max(0.0);
+    let offset = if follow_tail {
+        max
+    } else {
+        viewport.offset.clamp(0.0, max)
+    };
+    let offset = match cmd {
+        NavCommand::LineUp => offset - viewport.line,
+        NavCommand::LineDown => offset + viewport.line,
+        NavCommand::PageUp => offset - viewport.page,
+        NavCommand::PageDown => offset + viewport.page,
+        NavCommand::Top => 0.0,
+        NavCommand::Bottom => max,
+    }
+    .clamp(0.0, max);
+    let follow = match cmd {
+        NavCommand::LineUp | NavCommand::PageUp | NavCommand::Top => false,
+        NavCommand::LineDown | NavCommand::PageDown | NavCommand::Bottom => offset >= max,
+    };
+    (offset, follow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nav_clamps_and_follows() {
+        let view = |offset| Viewport {
+            offset,
+            max_offset: 100.0,
+            line: 1.0,
+            page: 30.0,
+        };
+        assert_eq!(nav(false, NavCommand::PageUp, view(10.0)), (0.0, false));
+        assert_eq!(nav(false, NavCommand::PageDown, view(10.0)), (40.0, false));
+        assert_eq!(nav(false, NavCommand::PageDown, view(80.0)), (100.0, true));
+        assert_eq!(nav(false, NavCommand::LineDown, view(99.0)), (100.0, true));
+        assert_eq!(nav(false, NavCommand::Bottom, view(0.0)), (100.0, true));
+        assert_eq!(nav(true, NavCommand::LineDown, view(0.0)), (100.0, true));
+        assert_eq!(nav(true, NavCommand::LineUp, view(0.0)), (99.0, false));
+        assert_eq!(nav(true, NavCommand::Top, view(50.0)), (0.0, false));
+
+        // Everything fits: going down is already the bottom, going up is not.
+        let fits = Viewport {
+            offset: 0.0,
+            max_offset: 0.0,
+            line: 1.0,
+            page: 30.0,
+        };
+        assert_eq!(nav(false, NavCommand::PageDown, fits), (0.0, true));
+        assert_eq!(nav(true, NavCommand::LineUp, fits), (0.0, false));
+    }
+}
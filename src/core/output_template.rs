@@ -0,0 +1,156 @@
+use crate::core::{format_absolute_time, format_relative_time, LogLine, TimeFormat};
+use anyhow::{anyhow, Result};
+
+const FIELD_NAMES: [(&str, TemplateField); 5] = [
+    ("ts", TemplateField::Ts),
+    ("reltime", TemplateField::RelTime),
+    ("line", TemplateField::Line),
+    ("content", TemplateField::Content),
+    ("raw", TemplateField::Raw),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TemplateField {
+    /// Absolute timestamp in the `--time-format` style.
+    Ts,
+    /// Relative time as in the gutter, e.g. `-5m`.
+    RelTime,
+    /// 1-based line number.
+    Line,
+    /// Display content, after the hide regex.
+    Content,
+    /// The line as received.
+    Raw,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(TemplateField),
+}
+
+/// Per-line format for copied lines (`--output-template`), e.g.
+/// `{ts}\t{line}\t{content}`. `{{`/`}}` are literal braces and `\t`, `\n`
+/// and `\\` are escapes, so the template can be passed from a shell as is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputTemplate {
+    segments: Vec<Segment>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("Unclosed '{{' in '{}'", template))?;
+                    let name = &rest[..end];
+                    let field = FIELD_NAMES
+                        .iter()
+                        .find(|(field_name, _)| *field_name == name)
+                        .map(|&(_, field)| field)
+                        .ok_or_else(|| {
+                            anyhow!("Unknown field '{{{}}}', expected ts, reltime, line, content or raw", name)
+                        })?;
+                    chars = rest[end + 1..].chars();
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => {
+                    return Err(anyhow!(
+                        "Unmatched '}}' in '{}' (write '}}}}' for a brace)",
+                        template
+                    ))
+                }
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some(other) => text.push(other),
+                    None => text.push('\\'),
+                },
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Fill in the template for `line`, numbered `number`, showing `content`.
+    pub fn render(
+        &self,
+        line: &LogLine,
+        number: usize,
+        content: &str,
+        time_format: TimeFormat,
+    ) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Field(TemplateField::Ts) => {
+                    out.push_str(&format_absolute_time(line.timestamp, time_format))
+                }
+                Segment::Field(TemplateField::RelTime) => {
+                    out.push_str(&format_relative_time(line.timestamp))
+                }
+                Segment::Field(TemplateField::Line) => out.push_str(&number.to_string()),
+                Segment::Field(TemplateField::Content) => out.push_str(content),
+                Segment::Field(TemplateField::Raw) => out.push_str(&line.content),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::LogState;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_output_template() {
+        let mut log_state = LogState::default();
+        let timestamp = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        let idx = log_state.add_line_at(
+            "token=abc GET /".to_string(),
+            Some(timestamp),
+            Default::default(),
+            None,
+            false,
+        );
+        let line = log_state.line(idx);
+
+        let template = OutputTemplate::parse(r"{ts}\t{line}\t{content}").unwrap();
+        assert_eq!(
+            template.render(line, 7, "GET /", TimeFormat::Clock),
+            "14:07:09\t7\tGET /"
+        );
+        let template = OutputTemplate::parse("{{{raw}}}").unwrap();
+        assert_eq!(
+            template.render(line, 7, "GET /", TimeFormat::Clock),
+            "{token=abc GET /}"
+        );
+
+        for bad in ["{time}", "{content", "a}b", "{}"] {
+            assert!(OutputTemplate::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
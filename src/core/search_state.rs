@@ -0,0 +1,9 @@
+use crate::filter::FilterExpr;
+
+/// Transient `/` search over the filtered lines. Not persisted.
+#[derive(Clone, Default)]
+pub struct SearchState {
+    pub expr: Option<FilterExpr>,
+    /// `(current, total)` matching lines after the last jump, shown as "match X/Y".
+    pub position: Option<(usize, usize)>,
+}
@@ -0,0 +1,127 @@
+use std::time::Instant;
+
+/// Complete seconds the rate is averaged over; it goes blank once they are all idle.
+const WINDOW_SECS: u64 = 3;
+/// One bucket per second, plus the one still filling.
+const BUCKETS: usize = WINDOW_SECS as usize + 1;
+
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    second: u64,
+    lines: u64,
+    bytes: u64,
+}
+
+/// Rolling lines/bytes per second of ingested input.
+#[derive(Clone)]
+pub struct Throughput {
+    start: Instant,
+    buckets: [Bucket; BUCKETS],
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: [Bucket::default(); BUCKETS],
+        }
+    }
+}
+
+impl Throughput {
+    /// Seconds since creation, the clock `record` and `rate` take.
+    pub fn now(&self) -> u64 {
+        self.start.elapsed().as_secs()
+    }
+
+    pub fn record(&mut self, now: u64, bytes: usize) {
+        let bucket = &mut self.buckets[now as usize % BUCKETS];
+        if bucket.second != now {
+            *bucket = Bucket {
+                second: now,
+                lines: 0,
+                bytes: 0,
+            };
+        }
+        bucket.lines += 1;
+        bucket.bytes += bytes as u64;
+    }
+
+    /// Lines and bytes per second over the last complete seconds, `None` if
+    /// nothing arrived in them.
+    pub fn rate(&self, now: u64) -> Option<(f64, f64)> {
+        let window = now.saturating_sub(WINDOW_SECS)..now;
+        let (lines, bytes) = self
+            .buckets
+            .iter()
+            .filter(|bucket| window.contains(&bucket.second))
+            .fold((0, 0), |(lines, bytes), bucket| {
+                (lines + bucket.lines, bytes + bucket.bytes)
+            });
+        (lines > 0).then(|| {
+            (
+                lines as f64 / WINDOW_SECS as f64,
+                bytes as f64 / WINDOW_SECS as f64,
+            )
+        })
+    }
+
+    /// Like `1.2k lines/s, 840 KB/s`, or empty when idle.
+    pub fn summary(&self) -> String {
+        match self.rate(self.now()) {
+            Some((lines, bytes)) => {
+                format!("{} lines/s, {}/s", format_count(lines), format_bytes(bytes))
+            }
+            None => String::new(),
+        }
+    }
+}
+
+fn format_count(count: f64) -> String {
+    if count >= 1_000_000.0 {
+        format!("{:.1}M", count / 1_000_000.0)
+    } else if count >= 1000.0 {
+        format!("{:.1}k", count / 1000.0)
+    } else {
+        format!("{:.0}", count)
+    }
+}
+
+pub fn format_bytes(bytes: f64) -> String {
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{:.0} KB", bytes / 1024.0)
+    } else {
+        format!("{:.0} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_rate() {
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.rate(0), None);
+        for second in 10..13 {
+            for _ in 0..400 {
+                throughput.record(second, 700);
+            }
+        }
+        throughput.record(13, 5);
+        assert_eq!(throughput.rate(13), Some((400.0, 280_000.0)));
+        assert_eq!(
+            throughput.rate(14).map(|(lines, _)| lines),
+            Some(801.0 / 3.0)
+        );
+        assert_eq!(throughput.rate(17), None, "blank after idle seconds");
+
+        // A bucket reused for a later second starts over.
+        throughput.record(17, 1);
+        assert_eq!(throughput.rate(18), Some((1.0 / 3.0, 1.0 / 3.0)));
+        assert_eq!(format_count(1234.0), "1.2k");
+        assert_eq!(format_bytes(840.0 * 1024.0), "840 KB");
+    }
+}
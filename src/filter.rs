@@ -1,21 +1,228 @@
+use crate::core::level::{detect_level, Level};
+use crate::highlight::first_json_object;
 use anyhow::{anyhow, Result};
-use regex::Regex;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use std::cmp::Ordering;
 
 #[derive(Debug, Clone)]
 pub enum FilterExpr {
     Pattern(Regex),
     And(Box<FilterExpr>, Box<FilterExpr>),
     Or(Box<FilterExpr>, Box<FilterExpr>),
+    /// Exactly one side matches.
+    Xor(Box<FilterExpr>, Box<FilterExpr>),
     Not(Box<FilterExpr>),
+    /// `file:NAME` (or `source:NAME`), `host:NAME` or `app:NAME`, matched
+    /// against that field of the line instead of its text.
+    Field(LineField, Regex),
+    /// `json:PATH OP VALUE`, tested against a field of the first JSON object
+    /// in the line. Lines without one, or without the field, never match.
+    JsonField {
+        path: Vec<String>,
+        op: JsonOp,
+        value: String,
+    },
+    /// `line:A-B`, `line:A-`, `line:N` or `line:-N`, tested against the line
+    /// number shown in the gutter.
+    LineRange(LineRange),
+    /// `level:NAME`, `level>=NAME` etc., compared with the level detected in
+    /// the line. Lines without a level never match.
+    Level {
+        op: LevelOp,
+        level: Level,
+    },
+    /// `since:TIME` or `until:TIME` (one bound each, inclusive), compared with
+    /// the line's parsed timestamp. Lines without one never match.
+    TimeRange {
+        since: Option<DateTime<Local>>,
+        until: Option<DateTime<Local>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelOp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Longer operators first, so `>=` isn't read as `>`.
+const LEVEL_OPS: [(&str, LevelOp); 5] = [
+    (":", LevelOp::Eq),
+    (">=", LevelOp::Ge),
+    ("<=", LevelOp::Le),
+    (">", LevelOp::Gt),
+    ("<", LevelOp::Lt),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineRange {
+    /// Inclusive 1-based line numbers, open-ended without an end.
+    Span(usize, Option<usize>),
+    /// The last N lines buffered when the line is checked.
+    Last(usize),
+}
+
+impl LineRange {
+    fn contains(self, line: usize, last_line: usize) -> bool {
+        match self {
+            LineRange::Span(first, end) => line >= first && end.is_none_or(|end| line <= end),
+            LineRange::Last(count) => line + count > last_line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    /// `~=`: the string field contains the value.
+    Contains,
+}
+
+/// Longer operators first, so `!=` isn't read as `=`.
+const JSON_OPS: [(&str, JsonOp); 5] = [
+    ("!=", JsonOp::Ne),
+    ("~=", JsonOp::Contains),
+    ("=", JsonOp::Eq),
+    ("<", JsonOp::Lt),
+    (">", JsonOp::Gt),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineField {
+    /// Source label: the file when viewing several, or the client's address
+    /// when listening.
+    File,
+    /// Syslog hostname.
+    Host,
+    /// Syslog app name.
+    App,
+}
+
+const FIELD_PREFIXES: [(&str, LineField); 4] = [
+    ("file:", LineField::File),
+    ("source:", LineField::File),
+    ("host:", LineField::Host),
+    ("app:", LineField::App),
+];
+
+/// Per-line values that field terms are tested against; a missing field never matches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineFields<'a> {
+    pub file: Option<&'a str>,
+    pub host: Option<&'a str>,
+    pub app: Option<&'a str>,
+    /// 1-based line number, for `line:` terms.
+    pub line: Option<usize>,
+    /// Number of the newest buffered line, for `line:-N`.
+    pub last_line: Option<usize>,
+    /// Timestamp parsed from the line, for `since:`/`until:`. `None` for
+    /// lines only stamped with their arrival time.
+    pub time: Option<DateTime<Local>>,
+}
+
+impl LineFields<'_> {
+    fn get(&self, field: LineField) -> Option<&str> {
+        match field {
+            LineField::File => self.file,
+            LineField::Host => self.host,
+            LineField::App => self.app,
+        }
+    }
 }
 
 impl FilterExpr {
     pub fn matches(&self, text: &str) -> bool {
+        self.matches_line(text, &LineFields::default())
+    }
+
+    /// Like `matches`, with `fields` for the `file:`/`host:`/`app:` terms.
+    pub fn matches_line(&self, text: &str, fields: &LineFields) -> bool {
         match self {
             FilterExpr::Pattern(re) => re.is_match(text),
-            FilterExpr::And(a, b) => a.matches(text) && b.matches(text),
-            FilterExpr::Or(a, b) => a.matches(text) || b.matches(text),
-            FilterExpr::Not(e) => !e.matches(text),
+            FilterExpr::Field(field, re) => {
+                fields.get(*field).is_some_and(|value| re.is_match(value))
+            }
+            FilterExpr::JsonField { path, op, value } => first_json_object(text)
+                .is_some_and(|json| json_field_matches(&json, path, *op, value)),
+            FilterExpr::LineRange(range) => match (fields.line, fields.last_line) {
+                (Some(line), Some(last_line)) => range.contains(line, last_line),
+                _ => false,
+            },
+            FilterExpr::Level { op, level } => detect_level(text).is_some_and(|found| match op {
+                LevelOp::Eq => found == *level,
+                LevelOp::Lt => found < *level,
+                LevelOp::Le => found <= *level,
+                LevelOp::Gt => found > *level,
+                LevelOp::Ge => found >= *level,
+            }),
+            FilterExpr::TimeRange { since, until } => fields.time.is_some_and(|time| {
+                since.is_none_or(|since| time >= since) && until.is_none_or(|until| time <= until)
+            }),
+            FilterExpr::And(a, b) => a.matches_line(text, fields) && b.matches_line(text, fields),
+            FilterExpr::Or(a, b) => a.matches_line(text, fields) || b.matches_line(text, fields),
+            FilterExpr::Xor(a, b) => a.matches_line(text, fields) != b.matches_line(text, fields),
+            FilterExpr::Not(e) => !e.matches_line(text, fields),
+        }
+    }
+
+    /// Whether `self` only narrows `previous`: it has the shape `previous && ...`
+    /// (possibly with further `&&` terms), so every line it matches also matched `previous`.
+    pub fn refines(&self, previous: &FilterExpr) -> bool {
+        match self {
+            FilterExpr::And(left, _) => left.same_as(previous) || left.refines(previous),
+            _ => false,
+        }
+    }
+
+    /// Structural equality on pattern sources. Builder flags such as case
+    /// insensitivity are not compared.
+    pub fn same_as(&self, other: &FilterExpr) -> bool {
+        match (self, other) {
+            (FilterExpr::Pattern(a), FilterExpr::Pattern(b)) => a.as_str() == b.as_str(),
+            (FilterExpr::Field(f1, a), FilterExpr::Field(f2, b)) => {
+                f1 == f2 && a.as_str() == b.as_str()
+            }
+            (
+                FilterExpr::JsonField {
+                    path: p1,
+                    op: o1,
+                    value: v1,
+                },
+                FilterExpr::JsonField {
+                    path: p2,
+                    op: o2,
+                    value: v2,
+                },
+            ) => p1 == p2 && o1 == o2 && v1 == v2,
+            (FilterExpr::LineRange(a), FilterExpr::LineRange(b)) => a == b,
+            (FilterExpr::Level { op: o1, level: l1 }, FilterExpr::Level { op: o2, level: l2 }) => {
+                o1 == o2 && l1 == l2
+            }
+            (
+                FilterExpr::TimeRange {
+                    since: s1,
+                    until: u1,
+                },
+                FilterExpr::TimeRange {
+                    since: s2,
+                    until: u2,
+                },
+            ) => s1 == s2 && u1 == u2,
+            (FilterExpr::And(a1, b1), FilterExpr::And(a2, b2))
+            | (FilterExpr::Or(a1, b1), FilterExpr::Or(a2, b2))
+            | (FilterExpr::Xor(a1, b1), FilterExpr::Xor(a2, b2)) => {
+                a1.same_as(a2) && b1.same_as(b2)
+            }
+            (FilterExpr::Not(a), FilterExpr::Not(b)) => a.same_as(b),
+            _ => false,
         }
     }
 
@@ -34,15 +241,193 @@ impl FilterExpr {
                     matches.push((m.start(), m.end()));
                 }
             }
-            FilterExpr::And(a, b) | FilterExpr::Or(a, b) => {
+            FilterExpr::And(a, b) | FilterExpr::Or(a, b) | FilterExpr::Xor(a, b) => {
                 a.collect_matches(text, matches);
                 b.collect_matches(text, matches);
             }
             FilterExpr::Not(e) => e.collect_matches(text, matches),
+            FilterExpr::Field(..)
+            | FilterExpr::JsonField { .. }
+            | FilterExpr::LineRange(_)
+            | FilterExpr::Level { .. }
+            | FilterExpr::TimeRange { .. } => {}
         }
     }
 }
 
+fn json_field_matches(json: &Value, path: &[String], op: JsonOp, value: &str) -> bool {
+    let field = path.iter().try_fold(json, |node, key| match node {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => node.get(key),
+    });
+    let Some(field) = field else {
+        return false;
+    };
+    match op {
+        JsonOp::Eq => json_scalar_eq(field, value),
+        JsonOp::Ne => !json_scalar_eq(field, value),
+        JsonOp::Contains => field.as_str().is_some_and(|text| text.contains(value)),
+        JsonOp::Lt => json_cmp(field, value) == Some(Ordering::Less),
+        JsonOp::Gt => json_cmp(field, value) == Some(Ordering::Greater),
+    }
+}
+
+fn json_scalar_eq(field: &Value, value: &str) -> bool {
+    match field {
+        Value::String(text) => text == value,
+        Value::Number(n) => n.as_f64().is_some_and(|n| value.parse() == Ok(n)),
+        Value::Bool(b) => value == if *b { "true" } else { "false" },
+        Value::Null => value == "null",
+        Value::Array(_) | Value::Object(_) => false,
+    }
+}
+
+/// Numbers compare numerically, as do strings when both sides are numbers;
+/// other strings compare by text, so ISO timestamps order correctly.
+fn json_cmp(field: &Value, value: &str) -> Option<Ordering> {
+    let number = value.parse::<f64>().ok();
+    match field {
+        Value::Number(n) => n.as_f64()?.partial_cmp(&number?),
+        Value::String(text) => match (text.parse::<f64>(), number) {
+            (Ok(n), Some(number)) => n.partial_cmp(&number),
+            _ => Some(text.as_str().cmp(value)),
+        },
+        _ => None,
+    }
+}
+
+/// Split `PATH OP VALUE` from a `json:` term.
+fn parse_json_term(term: &str) -> Result<FilterExpr> {
+    let (at, op_text, op) = term
+        .char_indices()
+        .find_map(|(at, _)| {
+            JSON_OPS
+                .iter()
+                .find(|(op_text, _)| term[at..].starts_with(op_text))
+                .map(|&(op_text, op)| (at, op_text, op))
+        })
+        .ok_or_else(|| anyhow!("Expected =, !=, <, > or ~= in 'json:{}'", term))?;
+    let path = &term[..at];
+    if path.is_empty() || path.split('.').any(str::is_empty) {
+        return Err(anyhow!("Invalid JSON path in 'json:{}'", term));
+    }
+    Ok(FilterExpr::JsonField {
+        path: path.split('.').map(str::to_string).collect(),
+        op,
+        value: term[at + op_text.len()..].to_string(),
+    })
+}
+
+/// The operator and name after `level`, e.g. `>=warn`. `None` if `term` doesn't
+/// start with an operator, so `levels` or `level=x` stay plain patterns.
+fn split_level_term(term: &str) -> Option<(LevelOp, &str)> {
+    LEVEL_OPS
+        .iter()
+        .find_map(|&(op_text, op)| term.strip_prefix(op_text).map(|name| (op, name)))
+}
+
+fn parse_level_term(term: &str) -> Result<FilterExpr> {
+    let (op, name) =
+        split_level_term(term).ok_or_else(|| anyhow!("Expected an operator in 'level{}'", term))?;
+    let level = Level::from_name(name).ok_or_else(|| {
+        anyhow!(
+            "Unknown level '{}', expected trace, debug, info, warn, error or fatal",
+            name
+        )
+    })?;
+    Ok(FilterExpr::Level { op, level })
+}
+
+/// Parse the `A-B`, `A-`, `N` or `-N` of a `line:` term.
+fn parse_line_range(term: &str) -> Result<FilterExpr> {
+    let invalid = || {
+        anyhow!(
+            "Expected line:A-B, line:A-, line:N or line:-N, got 'line:{}'",
+            term
+        )
+    };
+    let number = |text: &str| {
+        text.parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(invalid)
+    };
+    let range = match term.split_once('-') {
+        Some(("", count)) => LineRange::Last(number(count)?),
+        Some((first, "")) => LineRange::Span(number(first)?, None),
+        Some((first, end)) => {
+            let (first, end) = (number(first)?, number(end)?);
+            if end < first {
+                return Err(anyhow!("Line range 'line:{}' ends before it starts", term));
+            }
+            LineRange::Span(first, Some(end))
+        }
+        None => {
+            let line = number(term)?;
+            LineRange::Span(line, Some(line))
+        }
+    };
+    Ok(FilterExpr::LineRange(range))
+}
+
+/// Parse the value of a `since:`/`until:` term: `-30s`, `-5m`, `-2h` or `-1d`
+/// before `now`, `HH:MM[:SS]` today, `YYYY-MM-DD` at midnight, or
+/// `YYYY-MM-DDTHH:MM[:SS]` with an optional UTC offset.
+fn parse_time_bound(value: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(offset) = value.strip_prefix('-') {
+        let (count, unit) = offset.split_at(offset.find(|c: char| !c.is_ascii_digit())?);
+        let count = count.parse::<i64>().ok()?;
+        let duration = match unit {
+            "s" => Duration::try_seconds(count),
+            "m" => Duration::try_minutes(count),
+            "h" => Duration::try_hours(count),
+            "d" => Duration::try_days(count),
+            _ => None,
+        }?;
+        return now.checked_sub_signed(duration);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    let naive = ["%H:%M:%S", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value, format).ok())
+        .map(|time| now.date_naive().and_time(time))
+        .or_else(|| {
+            ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn parse_time_term(until: bool, value: &str) -> Result<FilterExpr> {
+    let name = if until { "until" } else { "since" };
+    let time = parse_time_bound(value, Local::now()).ok_or_else(|| {
+        anyhow!(
+            "Expected a time like 10:00:00, 2024-05-01T10:00 or -5m in '{}:{}'",
+            name,
+            value
+        )
+    })?;
+    Ok(if until {
+        FilterExpr::TimeRange {
+            since: None,
+            until: Some(time),
+        }
+    } else {
+        FilterExpr::TimeRange {
+            since: Some(time),
+            until: None,
+        }
+    })
+}
+
 fn merge_overlapping(ranges: &mut Vec<(usize, usize)>) {
     if ranges.is_empty() {
         return;
@@ -59,13 +444,32 @@ fn merge_overlapping(ranges: &mut Vec<(usize, usize)>) {
     ranges.truncate(write + 1);
 }
 
+/// How patterns in a filter expression are compiled.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FilterOptions {
+    /// Match case-insensitively. Inline flags in a quoted pattern, e.g.
+    /// `"(?-i)Error"`, still take precedence.
+    pub case_insensitive: bool,
+    /// Match a pattern without uppercase letters case-insensitively, like
+    /// ripgrep. `case_insensitive` wins: with it, every pattern ignores case.
+    pub smart_case: bool,
+    /// Treat each pattern as a plain substring instead of a regex.
+    pub literal: bool,
+    /// Only match whole words, see `whole_word_pattern`.
+    pub whole_word: bool,
+}
+
 pub fn parse_filter(input: &str) -> Result<FilterExpr> {
+    parse_filter_opts(input, FilterOptions::default())
+}
+
+pub fn parse_filter_opts(input: &str, opts: FilterOptions) -> Result<FilterExpr> {
     let input = input.trim();
     if input.is_empty() {
         return Err(anyhow!("Empty filter expression"));
     }
     let tokens = tokenize(input)?;
-    let (expr, pos) = parse_or(&tokens, 0)?;
+    let (expr, pos) = parse_or(&tokens, 0, opts)?;
     if pos != tokens.len() {
         return Err(anyhow!("Unexpected token at position {}", pos));
     }
@@ -78,8 +482,25 @@ enum Token {
     RParen,
     And,
     Or,
+    Xor,
     Not,
     Pattern(String),
+    /// Unquoted `file:NAME`, `source:NAME`, `host:NAME` or `app:NAME`.
+    Field(LineField, String),
+    /// Unquoted `json:PATH OP VALUE`, with any quotes in the value removed.
+    JsonField(String),
+    /// Unquoted `line:RANGE`.
+    LineRange(String),
+    /// Unquoted `level` followed by an operator, holding the rest.
+    Level(String),
+    /// Unquoted `since:TIME` (false) or `until:TIME` (true).
+    TimeBound(bool, String),
+}
+
+/// Whether the input continues with `^^`. A single `^` is a regex anchor.
+fn at_xor(chars: &std::iter::Peekable<std::str::Chars>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next() == Some('^') && ahead.next() == Some('^')
 }
 
 fn tokenize(input: &str) -> Result<Vec<Token>> {
@@ -119,6 +540,11 @@ fn tokenize(input: &str) -> Result<Vec<Token>> {
                 tokens.push(Token::Not);
                 chars.next();
             }
+            '^' if at_xor(&chars) => {
+                tokens.push(Token::Xor);
+                chars.next();
+                chars.next();
+            }
             '"' | '\'' => {
                 let quote = c;
                 chars.next();
@@ -140,13 +566,59 @@ fn tokenize(input: &str) -> Result<Vec<Token>> {
             _ => {
                 let mut pattern = String::new();
                 while let Some(&ch) = chars.peek() {
-                    if ch == '(' || ch == ')' || ch == '&' || ch == '|' || ch == '!' || ch == ' ' {
+                    let json = pattern.starts_with("json:");
+                    if json && ch == '"' {
+                        chars.next();
+                        while let Some(ch) = chars.next_if(|&ch| ch != '"') {
+                            pattern.push(ch);
+                        }
+                        if chars.next().is_none() {
+                            return Err(anyhow!("Unterminated string"));
+                        }
+                        continue;
+                    }
+                    if ch == '('
+                        || ch == ')'
+                        || ch == '&'
+                        || ch == '|'
+                        || (ch == '!' && !json)
+                        || ch == ' '
+                        || at_xor(&chars)
+                    {
                         break;
                     }
                     pattern.push(ch);
                     chars.next();
                 }
-                if !pattern.is_empty() {
+                if let Some(term) = pattern.strip_prefix("json:") {
+                    tokens.push(Token::JsonField(term.to_string()));
+                    continue;
+                }
+                if let Some(term) = pattern.strip_prefix("line:") {
+                    tokens.push(Token::LineRange(term.to_string()));
+                    continue;
+                }
+                if let Some(term) = pattern.strip_prefix("since:") {
+                    tokens.push(Token::TimeBound(false, term.to_string()));
+                    continue;
+                }
+                if let Some(term) = pattern.strip_prefix("until:") {
+                    tokens.push(Token::TimeBound(true, term.to_string()));
+                    continue;
+                }
+                if let Some(term) = pattern
+                    .strip_prefix("level")
+                    .filter(|term| split_level_term(term).is_some())
+                {
+                    tokens.push(Token::Level(term.to_string()));
+                    continue;
+                }
+                let field = FIELD_PREFIXES.iter().find_map(|(prefix, field)| {
+                    pattern.strip_prefix(prefix).map(|name| (*field, name))
+                });
+                if let Some((field, name)) = field {
+                    tokens.push(Token::Field(field, name.to_string()));
+                } else if !pattern.is_empty() {
                     tokens.push(Token::Pattern(pattern));
                 }
             }
@@ -155,57 +627,153 @@ fn tokenize(input: &str) -> Result<Vec<Token>> {
     Ok(tokens)
 }
 
-fn parse_or(tokens: &[Token], pos: usize) -> Result<(FilterExpr, usize)> {
-    let (mut left, mut pos) = parse_and(tokens, pos)?;
+fn parse_or(tokens: &[Token], pos: usize, opts: FilterOptions) -> Result<(FilterExpr, usize)> {
+    let (mut left, mut pos) = parse_xor(tokens, pos, opts)?;
     while pos < tokens.len() && tokens[pos] == Token::Or {
-        let (right, new_pos) = parse_and(tokens, pos + 1)?;
+        let (right, new_pos) = parse_xor(tokens, pos + 1, opts)?;
         left = FilterExpr::Or(Box::new(left), Box::new(right));
         pos = new_pos;
     }
     Ok((left, pos))
 }
 
-fn parse_and(tokens: &[Token], pos: usize) -> Result<(FilterExpr, usize)> {
-    let (mut left, mut pos) = parse_unary(tokens, pos)?;
+/// `^^` binds tighter than `||` and looser than `&&`.
+fn parse_xor(tokens: &[Token], pos: usize, opts: FilterOptions) -> Result<(FilterExpr, usize)> {
+    let (mut left, mut pos) = parse_and(tokens, pos, opts)?;
+    while pos < tokens.len() && tokens[pos] == Token::Xor {
+        let (right, new_pos) = parse_and(tokens, pos + 1, opts)?;
+        left = FilterExpr::Xor(Box::new(left), Box::new(right));
+        pos = new_pos;
+    }
+    Ok((left, pos))
+}
+
+fn parse_and(tokens: &[Token], pos: usize, opts: FilterOptions) -> Result<(FilterExpr, usize)> {
+    let (mut left, mut pos) = parse_unary(tokens, pos, opts)?;
     while pos < tokens.len() && tokens[pos] == Token::And {
-        let (right, new_pos) = parse_unary(tokens, pos + 1)?;
+        let (right, new_pos) = parse_unary(tokens, pos + 1, opts)?;
         left = FilterExpr::And(Box::new(left), Box::new(right));
         pos = new_pos;
     }
     Ok((left, pos))
 }
 
-fn parse_unary(tokens: &[Token], pos: usize) -> Result<(FilterExpr, usize)> {
+fn parse_unary(tokens: &[Token], pos: usize, opts: FilterOptions) -> Result<(FilterExpr, usize)> {
     if pos >= tokens.len() {
         return Err(anyhow!("Unexpected end of expression"));
     }
     if tokens[pos] == Token::Not {
-        let (expr, new_pos) = parse_unary(tokens, pos + 1)?;
+        let (expr, new_pos) = parse_unary(tokens, pos + 1, opts)?;
         return Ok((FilterExpr::Not(Box::new(expr)), new_pos));
     }
-    parse_primary(tokens, pos)
+    parse_primary(tokens, pos, opts)
 }
 
-fn parse_primary(tokens: &[Token], pos: usize) -> Result<(FilterExpr, usize)> {
+fn parse_primary(tokens: &[Token], pos: usize, opts: FilterOptions) -> Result<(FilterExpr, usize)> {
     if pos >= tokens.len() {
         return Err(anyhow!("Unexpected end of expression"));
     }
     match &tokens[pos] {
         Token::LParen => {
-            let (expr, new_pos) = parse_or(tokens, pos + 1)?;
+            let (expr, new_pos) = parse_or(tokens, pos + 1, opts)?;
             if new_pos >= tokens.len() || tokens[new_pos] != Token::RParen {
                 return Err(anyhow!("Missing closing parenthesis"));
             }
             Ok((expr, new_pos + 1))
         }
-        Token::Pattern(p) => {
-            let re = Regex::new(p).map_err(|e| anyhow!("Invalid regex '{}': {}", p, e))?;
-            Ok((FilterExpr::Pattern(re), pos + 1))
-        }
+        Token::Pattern(p) => Ok((FilterExpr::Pattern(compile_pattern(p, opts)?), pos + 1)),
+        Token::Field(field, p) => Ok((
+            FilterExpr::Field(*field, compile_pattern(p, opts)?),
+            pos + 1,
+        )),
+        Token::JsonField(term) => Ok((parse_json_term(term)?, pos + 1)),
+        Token::LineRange(term) => Ok((parse_line_range(term)?, pos + 1)),
+        Token::Level(term) => Ok((parse_level_term(term)?, pos + 1)),
+        Token::TimeBound(until, term) => Ok((parse_time_term(*until, term)?, pos + 1)),
         _ => Err(anyhow!("Unexpected token")),
     }
 }
 
+fn compile_pattern(p: &str, opts: FilterOptions) -> Result<Regex> {
+    let mut pattern = if opts.literal {
+        regex::escape(p)
+    } else {
+        p.to_string()
+    };
+    if opts.whole_word {
+        pattern = whole_word_pattern(p, pattern, opts.literal);
+    }
+    let ignore_case = opts.case_insensitive || (opts.smart_case && !has_uppercase(p, opts.literal));
+    RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| anyhow!("Invalid regex '{}': {}", p, e))
+}
+
+/// Whether the user's text `p` has an uppercase letter, for smart case. In a
+/// regex, escapes such as `\S` or `\p{Lu}` don't count.
+fn has_uppercase(p: &str, literal: bool) -> bool {
+    if literal {
+        return p.chars().any(char::is_uppercase);
+    }
+    let mut chars = p.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('p' | 'P') if chars.as_str().starts_with('{') => {
+                    chars.by_ref().find(|&c| c == '}');
+                }
+                _ => {}
+            },
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Put `\b` around `pattern` on each side where the user's text `p` starts or
+/// ends with a word character, so `err` no longer hits `stderr` while `->` or
+/// `[x]` still match. Regexes with their own anchors or boundaries are kept as is.
+fn whole_word_pattern(p: &str, pattern: String, literal: bool) -> String {
+    if !literal && has_anchor(p) {
+        return pattern;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let starts_word = p.chars().next().is_some_and(is_word);
+    // In a regex, a trailing `\d` or `\s` is a class, not a word character.
+    let mut tail = p.chars().rev();
+    let ends_word = tail.next().is_some_and(is_word) && (literal || tail.next() != Some('\\'));
+    format!(
+        "{}(?:{}){}",
+        if starts_word { r"\b" } else { "" },
+        pattern,
+        if ends_word { r"\b" } else { "" }
+    )
+}
+
+/// Whether regex `p` has `^`, `$`, `\b`, `\B`, `\A` or `\z` outside a character
+/// class, where `[^a]` negates and `\b` is a backspace.
+fn has_anchor(p: &str) -> bool {
+    let mut chars = p.chars();
+    let mut class_depth = 0usize;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars.next();
+                if class_depth == 0 && matches!(escaped, Some('b' | 'B' | 'A' | 'z')) {
+                    return true;
+                }
+            }
+            '[' => class_depth += 1,
+            ']' => class_depth = class_depth.saturating_sub(1),
+            '^' | '$' if class_depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,4 +825,358 @@ mod tests {
         assert!(!filter.matches("error in debug mode"));
         assert!(!filter.matches("debug mode error"));
     }
+
+    #[test]
+    fn test_refines_and_extension() {
+        let old = parse_filter("error").unwrap();
+        assert!(parse_filter("error && db").unwrap().refines(&old));
+        assert!(parse_filter("error && db && !retry").unwrap().refines(&old));
+
+        let old = parse_filter("error || warn").unwrap();
+        assert!(parse_filter("(error || warn) && db").unwrap().refines(&old));
+    }
+
+    #[test]
+    fn test_refines_rejects_non_subsets() {
+        let old = parse_filter("error || warn").unwrap();
+        // Precedence makes this `error || (warn && db)`, which is not `old && ...`.
+        assert!(!parse_filter("error || warn && db").unwrap().refines(&old));
+
+        let old = parse_filter("error").unwrap();
+        assert!(!parse_filter("error || db").unwrap().refines(&old));
+        assert!(!parse_filter("db && error").unwrap().refines(&old));
+        assert!(!parse_filter("errors && db").unwrap().refines(&old));
+        assert!(!parse_filter("error").unwrap().refines(&old));
+    }
+
+    fn ignore_case() -> FilterOptions {
+        FilterOptions {
+            case_insensitive: true,
+            ..FilterOptions::default()
+        }
+    }
+
+    fn literal() -> FilterOptions {
+        FilterOptions {
+            literal: true,
+            ..FilterOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_option() {
+        let filter = parse_filter_opts("error && !debug", ignore_case()).unwrap();
+        assert!(filter.matches("ERROR in production"));
+        assert!(!filter.matches("Error while DEBUG"));
+        assert!(!parse_filter("error").unwrap().matches("ERROR"));
+    }
+
+    #[test]
+    fn test_case_insensitive_with_inline_flags() {
+        let filter = parse_filter(r#""(?i)error""#).unwrap();
+        assert!(filter.matches("ERROR"));
+        let filter = parse_filter_opts(r#""(?i)error""#, ignore_case()).unwrap();
+        assert!(filter.matches("ERROR"));
+        let filter = parse_filter_opts(r#""(?-i)Error""#, ignore_case()).unwrap();
+        assert!(filter.matches("Error"));
+        assert!(!filter.matches("ERROR"));
+    }
+
+    #[test]
+    fn test_smart_case() {
+        let smart = FilterOptions {
+            smart_case: true,
+            ..FilterOptions::default()
+        };
+        let filter = parse_filter_opts("error", smart).unwrap();
+        assert!(filter.matches("ERROR disk full"));
+        assert!(filter.matches("Error disk full"));
+        let filter = parse_filter_opts("Error", smart).unwrap();
+        assert!(filter.matches("Error disk full"));
+        assert!(!filter.matches("ERROR disk full"));
+        assert!(!filter.matches("error disk full"));
+
+        // Each pattern decides for itself; escapes aren't uppercase letters.
+        let filter = parse_filter_opts(r"timeout && \Sfail\p{Lu}", smart).unwrap();
+        assert!(filter.matches("TIMEOUT xFAILX"));
+        let filter = parse_filter_opts("DB && timeout", smart).unwrap();
+        assert!(filter.matches("DB TIMEOUT"));
+        assert!(!filter.matches("db timeout"));
+
+        let explicit = FilterOptions {
+            case_insensitive: true,
+            ..smart
+        };
+        assert!(parse_filter_opts("Error", explicit)
+            .unwrap()
+            .matches("ERROR"));
+    }
+
+    #[test]
+    fn test_literal_metacharacters() {
+        assert!(parse_filter(r#""foo(bar""#).is_err());
+        let filter = parse_filter_opts(r#""foo(bar" && "a.b+""#, literal()).unwrap();
+        assert!(filter.matches("call foo(bar) a.b+c"));
+        assert!(!filter.matches("call foo(bar) axbb"));
+    }
+
+    #[test]
+    fn test_literal_quoted_operators() {
+        let filter = parse_filter_opts(r#""a||b" || !x"#, literal()).unwrap();
+        assert!(filter.matches("x a||b"));
+        assert!(!filter.matches("x a b"));
+        assert_eq!(filter.find_all_matches("1 a||b 2"), vec![(2, 6)]);
+    }
+
+    #[test]
+    fn test_literal_case_insensitive() {
+        let opts = FilterOptions {
+            case_insensitive: true,
+            literal: true,
+            ..FilterOptions::default()
+        };
+        let filter = parse_filter_opts("[error]", opts).unwrap();
+        assert!(filter.matches("[ERROR] boom"));
+        assert!(!filter.matches("e"));
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let opts = FilterOptions {
+            whole_word: true,
+            ..FilterOptions::default()
+        };
+        let filter = parse_filter_opts("err", opts).unwrap();
+        assert!(filter.matches("err: boom"));
+        assert!(!filter.matches("stderr"));
+        assert!(!filter.matches("error"));
+
+        let filter = parse_filter_opts(r#""warn|err""#, opts).unwrap();
+        assert!(filter.matches("a warn"));
+        assert!(!filter.matches("warning"));
+
+        // Symbol edges get no boundary, anchored regexes are left alone.
+        assert!(parse_filter_opts(r#""->""#, opts).unwrap().matches("a->b"));
+        assert!(parse_filter_opts(
+            "[x]",
+            FilterOptions {
+                literal: true,
+                ..opts
+            }
+        )
+        .unwrap()
+        .matches("[x]y"));
+        assert!(parse_filter_opts("^err", opts).unwrap().matches("error"));
+        assert!(parse_filter_opts(r"id=\d", opts).unwrap().matches("id=12"));
+
+        // A negated class is not an anchor, so the word still gets its boundaries.
+        let filter = parse_filter_opts(r#""e[^a]r""#, opts).unwrap();
+        assert!(filter.matches("an exr here"));
+        assert!(!filter.matches("bexr"));
+        assert!(!filter.matches("exrs"));
+        assert!(parse_filter_opts(r#""[$]5""#, opts).unwrap().matches("$5"));
+        assert!(!parse_filter_opts(r#""[$]5""#, opts).unwrap().matches("$50"));
+    }
+
+    #[test]
+    fn test_xor() {
+        let filter = parse_filter("timeout ^^ retry").unwrap();
+        assert!(filter.matches("timeout"));
+        assert!(filter.matches("retry"));
+        assert!(!filter.matches("timeout, retry"));
+        assert!(!filter.matches("ok"));
+        assert_eq!(
+            filter.find_all_matches("timeout, retry"),
+            vec![(0, 7), (9, 14)]
+        );
+    }
+
+    #[test]
+    fn test_xor_precedence_and_grouping() {
+        // a ^^ b && c  ==  a ^^ (b && c);  a || b ^^ c  ==  a || (b ^^ c)
+        let filter = parse_filter("a ^^ b && c").unwrap();
+        assert!(filter.matches("a b"));
+        assert!(!filter.matches("a b c"));
+        let filter = parse_filter("x || b ^^ c").unwrap();
+        assert!(filter.matches("x b c"));
+        assert!(!filter.matches("b c"));
+
+        let filter = parse_filter("(a ^^ b) && !c").unwrap();
+        assert!(filter.matches("a"));
+        assert!(!filter.matches("a c"));
+        assert!(!filter.matches("a b"));
+    }
+
+    #[test]
+    fn test_xor_with_quotes_and_anchors() {
+        let filter = parse_filter(r#""disk full"^^"^WARN""#).unwrap();
+        assert!(filter.matches("error: disk full"));
+        assert!(filter.matches("WARN slow"));
+        assert!(!filter.matches("WARN disk full"));
+
+        let filter = parse_filter("^ERROR^^^WARN").unwrap();
+        assert!(filter.matches("ERROR x"));
+        assert!(filter.matches("WARN x"));
+        assert!(!filter.matches("x ERROR"));
+    }
+
+    #[test]
+    fn test_source_field() {
+        let file = |name| LineFields {
+            file: Some(name),
+            ..LineFields::default()
+        };
+        let filter = parse_filter("file:access && !health").unwrap();
+        assert!(filter.matches_line("GET /login", &file("access")));
+        assert!(!filter.matches_line("GET /health", &file("access")));
+        assert!(!filter.matches_line("GET /login", &file("error")));
+        assert!(!filter.matches("GET /login"));
+
+        let quoted = parse_filter("\"file:access\"").unwrap();
+        assert!(quoted.matches("opened file:access.log"));
+        assert!(parse_filter("file:access")
+            .unwrap()
+            .same_as(&filter_source("access")));
+        assert!(parse_filter("source:access")
+            .unwrap()
+            .same_as(&filter_source("access")));
+        assert!(parse_filter("file:(").is_err());
+    }
+
+    #[test]
+    fn test_syslog_fields() {
+        let fields = LineFields {
+            host: Some("web1"),
+            app: Some("sshd"),
+            ..LineFields::default()
+        };
+        assert!(parse_filter("host:web && app:^sshd$")
+            .unwrap()
+            .matches_line("x", &fields));
+        assert!(!parse_filter("host:db").unwrap().matches_line("x", &fields));
+        assert!(!parse_filter("file:web1")
+            .unwrap()
+            .matches_line("x", &fields));
+        assert!(!parse_filter("app:sshd")
+            .unwrap()
+            .same_as(&parse_filter("host:sshd").unwrap()));
+    }
+
+    #[test]
+    fn test_level_terms() {
+        let matches = |filter: &str, line: &str| parse_filter(filter).unwrap().matches(line);
+        assert!(matches("level:error", "12:00 ERROR db down"));
+        assert!(!matches("level:error", "12:00 FATAL db down"));
+        assert!(matches("level>=warn", "12:00 FATAL db down"));
+        assert!(matches("level>=warn", "warning: low disk"));
+        assert!(!matches("level>=warn", "INFO started"));
+        assert!(matches("level<info && db", "debug db query"));
+        assert!(
+            !matches("level<=fatal", "no level here"),
+            "lines without a level never match"
+        );
+        assert!(matches("!level:debug", "no level here"));
+        assert!(
+            matches("level=error", "msg=x level=error"),
+            "level= stays a pattern"
+        );
+        assert!(parse_filter("level:loud").is_err());
+        assert!(!parse_filter("level>warn")
+            .unwrap()
+            .same_as(&parse_filter("level>=warn").unwrap()));
+    }
+
+    #[test]
+    fn test_line_range() {
+        let at = |line| LineFields {
+            line: Some(line),
+            last_line: Some(5000),
+            ..LineFields::default()
+        };
+        let filter = parse_filter("line:1000-2000 && error").unwrap();
+        assert!(filter.matches_line("error", &at(1000)));
+        assert!(filter.matches_line("error", &at(2000)));
+        assert!(!filter.matches_line("error", &at(2001)));
+        assert!(!filter.matches_line("info", &at(1500)));
+        assert!(!filter.matches("error"), "no line number, no match");
+
+        let last = parse_filter("line:-50").unwrap();
+        assert!(last.matches_line("x", &at(4951)));
+        assert!(!last.matches_line("x", &at(4950)));
+        assert!(parse_filter("line:4000-")
+            .unwrap()
+            .matches_line("x", &at(5000)));
+        assert!(parse_filter("line:7").unwrap().matches_line("x", &at(7)));
+        assert!(!parse_filter("line:7")
+            .unwrap()
+            .same_as(&parse_filter("line:-7").unwrap()));
+        for bad in ["line:", "line:0", "line:5-2", "line:a-b", "line:-"] {
+            assert!(parse_filter(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_time_range() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let at = |h, m| Local.with_ymd_and_hms(2024, 5, 1, h, m, 0).unwrap();
+        assert_eq!(parse_time_bound("-5m", now), Some(at(11, 55)));
+        assert_eq!(parse_time_bound("-2h", now), Some(at(10, 0)));
+        assert_eq!(parse_time_bound("10:30", now), Some(at(10, 30)));
+        assert_eq!(
+            parse_time_bound("2024-05-01T09:15:00", now),
+            Some(at(9, 15))
+        );
+        assert_eq!(parse_time_bound("2024-05-01", now), Some(at(0, 0)));
+        for bad in ["-5", "-5x", "25:00", "yesterday", ""] {
+            assert_eq!(parse_time_bound(bad, now), None, "{}", bad);
+        }
+
+        let filter = FilterExpr::And(
+            Box::new(FilterExpr::TimeRange {
+                since: Some(at(10, 0)),
+                until: None,
+            }),
+            Box::new(FilterExpr::TimeRange {
+                since: None,
+                until: Some(at(11, 30)),
+            }),
+        );
+        let stamped = |time| LineFields {
+            time: Some(time),
+            ..LineFields::default()
+        };
+        assert!(filter.matches_line("x", &stamped(at(10, 0))));
+        assert!(filter.matches_line("x", &stamped(at(11, 30))));
+        assert!(!filter.matches_line("x", &stamped(at(11, 31))));
+        assert!(!filter.matches("x"), "unparsed timestamps never match");
+        assert!(parse_filter("since:10:00:00 && until:-1m").is_ok());
+        assert!(parse_filter("until:noon").is_err());
+    }
+
+    #[test]
+    fn test_json_field() {
+        let line = r#"12:00 req {"level":"error","user":{"id":150,"name":"Ann Lee"},"tags":["a","b"],"ts":"2024-05-01T10:00:00Z"}"#;
+        let matches = |filter: &str| parse_filter(filter).unwrap().matches(line);
+        assert!(matches("json:level=error"));
+        assert!(!matches("json:level!=error"));
+        assert!(matches("json:user.id>100 && json:user.id<200"));
+        assert!(matches("json:user.id=150.0"));
+        assert!(matches(r#"json:user.name~="n L""#));
+        assert!(matches("json:tags.1=b"));
+        assert!(matches("json:ts>2024-04-30"));
+        assert!(!matches("json:user.id~=15"), "~= is for strings");
+        assert!(!matches("json:missing!=x"), "a missing field never matches");
+        assert!(!parse_filter("json:level=error")
+            .unwrap()
+            .matches("level=error"));
+        assert!(matches("!json:level=info"));
+
+        assert!(parse_filter("json:level").is_err());
+        assert!(parse_filter("json:=x").is_err());
+        assert!(parse_filter("json:a..b=x").is_err());
+    }
+
+    fn filter_source(name: &str) -> FilterExpr {
+        FilterExpr::Field(LineField::File, Regex::new(name).unwrap())
+    }
 }
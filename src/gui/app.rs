@@ -1,14 +1,16 @@
-use crate::core::{format_relative_time, get_time_age, ListenState, LogLine, TimeAge};
-use crate::source::{start_source, LogSource, SourceEvent};
-use crate::state::AppState;
+use crate::core::{
+    format_relative_time, get_time_age, LineKind, ListenState, LogLine, NavCommand, TimeAge,
+};
+use crate::source::{start_source, FileSpec, LogSource, SourceEvent};
+use crate::state::{AppState, Presets};
 use async_channel::Receiver;
 use dioxus::html::MountedData;
 use dioxus::prelude::*;
-use std::rc::Rc;
-use std::sync::Arc;
 use fancy_regex::Regex;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::rc::Rc;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use super::components::{ListenPopup, LogLineContent};
@@ -19,24 +21,65 @@ const LINE_HEIGHT: f64 = 20.0;
 const BASE_RENDER_THRESHOLD_MS: f64 = 50.0;
 const MIN_RENDER_THRESHOLD_MS: f64 = 5.0;
 const THRESHOLD_DECAY_FACTOR: f64 = 0.7;
+const RESIZE_DEBOUNCE_MS: u64 = 100;
 
 #[derive(Props, Clone, PartialEq)]
 pub struct GuiAppProps {
-    pub file: Option<PathBuf>,
+    pub file: Option<String>,
     pub port: Option<u16>,
+    /// `--bind`: the one local address to listen on.
+    pub bind: Option<IpAddr>,
+    /// `--line-start` from the command line, already validated; overrides the saved regex.
+    pub line_start: Option<String>,
+    /// `--tail N`: start from the last N lines of the file.
+    pub tail: Option<usize>,
+    /// `--hide`/`--filter`/`--highlight` from the command line.
+    pub presets: Presets,
+    /// Pause in typing before the input fields are checked for errors.
+    pub validate_delay: Duration,
+}
+
+/// Check the input fields once typing has paused for `delay`; each keystroke
+/// restarts the wait, like the resize debounce.
+async fn validate_after_pause(
+    mut validate_generation: Signal<u64>,
+    mut app_state: Signal<GuiAppState>,
+    delay: Duration,
+) {
+    let generation = *validate_generation.read() + 1;
+    validate_generation.set(generation);
+    async_std::task::sleep(delay).await;
+    if *validate_generation.read() == generation {
+        app_state.write().validate_inputs();
+    }
 }
 
 #[component]
 pub fn GuiApp(props: GuiAppProps) -> Element {
-    let mut app_state = use_signal(|| GuiAppState::new());
+    let mut app_state = use_signal(|| GuiAppState::new(&props.presets));
     let mut source_rx: Signal<Option<Receiver<SourceEvent>>> = use_signal(|| None);
     let mut container_element: Signal<Option<Rc<MountedData>>> = use_signal(|| None);
-    let mut listen_state = use_signal(|| ListenState::new(props.port));
+    let mut listen_state = use_signal(|| {
+        let mut state = ListenState::new(props.port);
+        if let Some(addr) = props.bind {
+            state.set_bind_addr(addr);
+        }
+        state
+    });
     let mut pending_scroll_to_bottom = use_signal(|| false);
+    let mut pending_scroll_y: Signal<Option<f64>> = use_signal(|| None);
+    // Pointer y and scroll offset where a scrollbar thumb drag started.
+    let mut scrollbar_drag: Signal<Option<(f64, f64)>> = use_signal(|| None);
+    let mut resize_generation = use_signal(|| 0u64);
+    let validate_generation = use_signal(|| 0u64);
+    let validate_delay = props.validate_delay;
 
     use_effect({
         let file = props.file.clone();
         let port = props.port;
+        let bind = props.bind;
+        let line_start = props.line_start.clone();
+        let tail = props.tail;
         move || {
             let (sync_tx, sync_rx) = mpsc::channel::<SourceEvent>();
             let (async_tx, async_rx) = async_channel::unbounded::<SourceEvent>();
@@ -50,18 +93,26 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
             });
 
             let source = if let Some(port) = port {
-                LogSource::Network(port)
-            } else if let Some(ref path) = file {
-                LogSource::File(path.clone())
+                LogSource::Network(port, bind, None)
+            } else if let Some(ref spec) = file {
+                match FileSpec::parse(spec) {
+                    Ok(spec) => LogSource::File(FileSpec { tail, ..spec }),
+                    Err(e) => {
+                        app_state.write().status_message =
+                            Some(format!("Failed to start source: {}", e));
+                        return;
+                    }
+                }
             } else {
                 LogSource::Stdin
             };
 
             let state = AppState::load();
-            let line_start_regex = if state.line_start_regex.trim().is_empty() {
+            let line_start_text = line_start.clone().unwrap_or(state.line_start_regex);
+            let line_start_regex = if line_start_text.trim().is_empty() {
                 None
             } else {
-                match Regex::new(&state.line_start_regex) {
+                match Regex::new(&line_start_text) {
                     Ok(re) => Some(Arc::new(re)),
                     Err(_) => None,
                 }
@@ -94,34 +145,48 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                 let wait_duration = threshold.saturating_sub(last_time.elapsed());
 
                 match async_std::future::timeout(wait_duration, rx.recv()).await {
-                    Ok(Ok(event)) => {
-                        match event {
-                            SourceEvent::Line(content) => {
-                                pending_lines.push(content);
-                                current_threshold_ms = (current_threshold_ms * THRESHOLD_DECAY_FACTOR)
-                                    .max(MIN_RENDER_THRESHOLD_MS);
-                            }
-                            SourceEvent::SystemLine(content) => {
+                    Ok(Ok(event)) => match event {
+                        SourceEvent::Line(content)
+                        | SourceEvent::StderrLine(content)
+                        | SourceEvent::TaggedLine(_, content)
+                        | SourceEvent::PeerLine(_, content) => {
+                            pending_lines.push(content);
+                            current_threshold_ms = (current_threshold_ms * THRESHOLD_DECAY_FACTOR)
+                                .max(MIN_RENDER_THRESHOLD_MS);
+                        }
+                        SourceEvent::SystemLine(content) => {
+                            let mut state = app_state.write();
+                            state.add_system_line(content);
+                            state.version += 1;
+                        }
+                        SourceEvent::Error(e) => {
+                            app_state
+                                .write()
+                                .source_errors
+                                .push(chrono::Local::now(), e);
+                        }
+                        SourceEvent::Connected(peer) => {
+                            listen_state.write().on_connected(peer.clone());
+                            let mut state = app_state.write();
+                            state.is_connected = true;
+                            state.status_message = Some(format!("Connected: {}", peer));
+                        }
+                        SourceEvent::Disconnected(peer) => {
+                            listen_state.write().on_disconnected(&peer);
+                            let mut state = app_state.write();
+                            state.is_connected = false;
+                            state.status_message = Some(format!("Disconnected: {}", peer));
+                        }
+                        SourceEvent::ReplaceLast(content) => match pending_lines.last_mut() {
+                            Some(last) => *last = content,
+                            None => {
                                 let mut state = app_state.write();
-                                state.add_line_with_update(content, false);
+                                state.replace_last_line(content);
                                 state.version += 1;
                             }
-                            SourceEvent::Error(e) => {
-                                app_state.write().status_message = Some(format!("Error: {}", e));
-                            }
-                            SourceEvent::Connected(peer) => {
-                                listen_state.write().has_connection = true;
-                                let mut state = app_state.write();
-                                state.is_connected = true;
-                                state.status_message = Some(format!("Connected: {}", peer));
-                            }
-                            SourceEvent::Disconnected(peer) => {
-                                let mut state = app_state.write();
-                                state.is_connected = false;
-                                state.status_message = Some(format!("Disconnected: {}", peer));
-                            }
-                        }
-                    }
+                        },
+                        SourceEvent::CaughtUp | SourceEvent::Eof => {}
+                    },
                     Ok(Err(_)) => break,
                     Err(_) => {
                         let lines_to_add: Vec<String> = pending_lines.drain(..).collect();
@@ -143,45 +208,89 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                 }
             } else {
                 match rx.recv().await {
-                    Ok(event) => {
-                        match event {
-                            SourceEvent::Line(content) => {
-                                pending_lines.push(content);
-                                last_data_time = Some(Instant::now());
-                                current_threshold_ms = (current_threshold_ms * THRESHOLD_DECAY_FACTOR)
-                                    .max(MIN_RENDER_THRESHOLD_MS);
-                            }
-                            SourceEvent::SystemLine(content) => {
+                    Ok(event) => match event {
+                        SourceEvent::Line(content)
+                        | SourceEvent::StderrLine(content)
+                        | SourceEvent::TaggedLine(_, content)
+                        | SourceEvent::PeerLine(_, content) => {
+                            pending_lines.push(content);
+                            last_data_time = Some(Instant::now());
+                            current_threshold_ms = (current_threshold_ms * THRESHOLD_DECAY_FACTOR)
+                                .max(MIN_RENDER_THRESHOLD_MS);
+                        }
+                        SourceEvent::SystemLine(content) => {
+                            let mut state = app_state.write();
+                            state.add_system_line(content);
+                            state.version += 1;
+                        }
+                        SourceEvent::Error(e) => {
+                            app_state
+                                .write()
+                                .source_errors
+                                .push(chrono::Local::now(), e);
+                        }
+                        SourceEvent::Connected(peer) => {
+                            listen_state.write().on_connected(peer.clone());
+                            let mut state = app_state.write();
+                            state.is_connected = true;
+                            state.status_message = Some(format!("Connected: {}", peer));
+                        }
+                        SourceEvent::Disconnected(peer) => {
+                            listen_state.write().on_disconnected(&peer);
+                            let mut state = app_state.write();
+                            state.is_connected = false;
+                            state.status_message = Some(format!("Disconnected: {}", peer));
+                        }
+                        SourceEvent::ReplaceLast(content) => match pending_lines.last_mut() {
+                            Some(last) => *last = content,
+                            None => {
                                 let mut state = app_state.write();
-                                state.add_line_with_update(content, false);
+                                state.replace_last_line(content);
                                 state.version += 1;
                             }
-                            SourceEvent::Error(e) => {
-                                app_state.write().status_message = Some(format!("Error: {}", e));
-                            }
-                            SourceEvent::Connected(peer) => {
-                                listen_state.write().has_connection = true;
-                                let mut state = app_state.write();
-                                state.is_connected = true;
-                                state.status_message = Some(format!("Connected: {}", peer));
-                            }
-                            SourceEvent::Disconnected(peer) => {
-                                let mut state = app_state.write();
-                                state.is_connected = false;
-                                state.status_message = Some(format!("Disconnected: {}", peer));
-                            }
-                        }
-                    }
+                        },
+                        SourceEvent::CaughtUp | SourceEvent::Eof => {}
+                    },
                     Err(_) => break,
                 }
             }
         }
     });
 
+    let desktop = dioxus::desktop::use_window();
+    use_hook({
+        let desktop = desktop.clone();
+        move || super::window::keep_on_screen(&desktop)
+    });
+    // Polled rather than hooked to move/resize events; saving at most once a
+    // second while dragging is plenty.
+    use_future(move || {
+        let desktop = desktop.clone();
+        async move {
+            let mut saved = super::window::saved_geometry();
+            loop {
+                async_std::task::sleep(Duration::from_secs(1)).await;
+                let geometry = super::window::current_geometry(&desktop);
+                if let Some(geometry) = geometry.filter(|geometry| Some(*geometry) != saved) {
+                    super::window::save_geometry(geometry);
+                    saved = Some(geometry);
+                }
+            }
+        }
+    });
+
     use_future(move || async move {
         loop {
             async_std::task::sleep(Duration::from_secs(1)).await;
-            if app_state.read().show_time {
+            // Also tick while lines arrive, so the throughput fades out once they stop.
+            let tick = {
+                let state = app_state.read();
+                state.show_time
+                    || state
+                        .last_update_time
+                        .is_some_and(|time| (chrono::Local::now() - time).num_seconds() <= 5)
+            };
+            if tick {
                 app_state.write().version += 1;
             }
         }
@@ -197,6 +306,13 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                     let _ = el.scroll(coords, ScrollBehavior::Instant).await;
                 }
             }
+            let scroll_y = pending_scroll_y.write().take();
+            if let Some(scroll_y) = scroll_y {
+                if let Some(ref el) = *container_element.read() {
+                    let coords = dioxus::html::geometry::PixelsVector2D::new(0.0, scroll_y);
+                    let _ = el.scroll(coords, ScrollBehavior::Instant).await;
+                }
+            }
             async_std::task::sleep(Duration::from_millis(16)).await;
         }
     });
@@ -209,24 +325,50 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
     let container_height = state.container_height;
     let follow_tail = state.follow_tail;
     let show_time = state.show_time;
+    let show_line_numbers = state.show_line_numbers;
     let wrap_lines = state.wrap_lines;
+    let layout_epoch = state.layout_epoch;
+    let case_insensitive = state.case_insensitive;
+    let smart_case = state.smart_case;
+    let literal = state.literal;
+    let whole_word = state.whole_word;
+    let filter_after_hide = state.filter_state.filter_after_hide;
     let hide_text = state.hide_text.clone();
     let filter_text = state.filter_text.clone();
     let highlight_text = state.highlight_text.clone();
     let hide_error = state.hide_error.clone();
     let filter_error = state.filter_error.clone();
+    let highlight_error = state.highlight_error.clone();
     let line_start_text = state.line_start_text.clone();
     let line_start_error = state.line_start_error.clone();
     let status_message = state.status_message.clone();
     let is_connected = state.is_connected;
     let highlight_expr = state.filter_state.highlight_expr.clone();
     let total_height = state.total_height();
-    let (start_idx, end_idx) = state.find_visible_range(scroll_y, container_height + LINE_HEIGHT * 3.0);
+    let scrollbar_thumb = state.scrollbar_thumb();
+    let (start_idx, end_idx) =
+        state.find_visible_range(scroll_y, container_height + LINE_HEIGHT * 3.0);
     let version = state.version;
     let last_update_time = state.last_update_time;
+    let throughput = state.throughput.summary();
+    let error_count = state.source_errors.unseen();
+    let errors: Vec<String> = if state.show_errors {
+        state
+            .source_errors
+            .entries()
+            .iter()
+            .map(|(time, message)| format!("{} {}", time.format("%H:%M:%S"), message))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let show_errors = state.show_errors;
     drop(state);
 
-    let (visible_lines, runtime_hide_error): (Vec<(usize, usize, f64, LogLine, String)>, Option<String>) = {
+    let (visible_lines, runtime_hide_error): (
+        Vec<(usize, usize, f64, LogLine, String)>,
+        Option<String>,
+    ) = {
         let state = app_state.read();
         let mut error: Option<String> = None;
         let lines: Vec<_> = (start_idx..end_idx)
@@ -269,7 +411,14 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         class: if hide_error.is_some() { "error" } else { "" },
                         placeholder: "regex to hide...",
                         value: "{hide_text}",
-                        oninput: move |e| app_state.write().hide_text = e.value(),
+                        oninput: move |e| {
+                            {
+                                let mut state = app_state.write();
+                                state.hide_text = e.value();
+                                state.shadowed.hide = None;
+                            }
+                            validate_after_pause(validate_generation, app_state, validate_delay)
+                        },
                         onkeydown: move |e| {
                             if e.key() == Key::Enter {
                                 app_state.write().apply_hide();
@@ -284,7 +433,14 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         class: if filter_error.is_some() { "error" } else { "" },
                         placeholder: "filter expression...",
                         value: "{filter_text}",
-                        oninput: move |e| app_state.write().filter_text = e.value(),
+                        oninput: move |e| {
+                            {
+                                let mut state = app_state.write();
+                                state.filter_text = e.value();
+                                state.shadowed.filter = None;
+                            }
+                            validate_after_pause(validate_generation, app_state, validate_delay)
+                        },
                         onkeydown: move |e| {
                             if e.key() == Key::Enter {
                                 app_state.write().apply_filter();
@@ -296,9 +452,17 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                     label { "Highlight:" }
                     input {
                         r#type: "text",
+                        class: if highlight_error.is_some() { "error" } else { "" },
                         placeholder: "highlight expression...",
                         value: "{highlight_text}",
-                        oninput: move |e| app_state.write().highlight_text = e.value(),
+                        oninput: move |e| {
+                            {
+                                let mut state = app_state.write();
+                                state.highlight_text = e.value();
+                                state.shadowed.highlight = None;
+                            }
+                            validate_after_pause(validate_generation, app_state, validate_delay)
+                        },
                         onkeydown: move |e| {
                             if e.key() == Key::Enter {
                                 app_state.write().apply_highlight();
@@ -313,7 +477,10 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         class: if line_start_error.is_some() { "error" } else { "" },
                         placeholder: "regex for log line start...",
                         value: "{line_start_text}",
-                        oninput: move |e| app_state.write().line_start_text = e.value(),
+                        oninput: move |e| {
+                            app_state.write().line_start_text = e.value();
+                            validate_after_pause(validate_generation, app_state, validate_delay)
+                        },
                         onkeydown: move |e| {
                             if e.key() == Key::Enter {
                                 app_state.write().apply_line_start();
@@ -331,6 +498,14 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         },
                         "Time"
                     }
+                    button {
+                        class: if show_line_numbers { "active" } else { "" },
+                        title: "Line numbers",
+                        onclick: move |_| {
+                            app_state.write().toggle_line_numbers();
+                        },
+                        "#"
+                    }
                     button {
                         class: if wrap_lines { "active" } else { "" },
                         onclick: move |_| {
@@ -343,6 +518,42 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         },
                         "Wrap"
                     }
+                    button {
+                        class: if case_insensitive { "active" } else { "" },
+                        onclick: move |_| {
+                            app_state.write().toggle_case_insensitive();
+                        },
+                        "Aa"
+                    }
+                    button {
+                        class: if smart_case { "active" } else { "" },
+                        title: "Ignore case unless a pattern has uppercase",
+                        onclick: move |_| {
+                            app_state.write().toggle_smart_case();
+                        },
+                        "Smart case"
+                    }
+                    button {
+                        class: if literal { "active" } else { "" },
+                        onclick: move |_| {
+                            app_state.write().toggle_literal();
+                        },
+                        "Literal"
+                    }
+                    button {
+                        class: if whole_word { "active" } else { "" },
+                        onclick: move |_| {
+                            app_state.write().toggle_whole_word();
+                        },
+                        "Word"
+                    }
+                    button {
+                        class: if filter_after_hide { "active" } else { "" },
+                        onclick: move |_| {
+                            app_state.write().toggle_filter_after_hide();
+                        },
+                        "After hide"
+                    }
                     button {
                         class: if follow_tail { "active" } else { "" },
                         onclick: move |_| {
@@ -367,6 +578,19 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
 
             div { class: "log-wrapper",
                 div { class: "log-main",
+                    onmousemove: move |e| {
+                        let Some((start_y, start_scroll)) = *scrollbar_drag.read() else {
+                            return;
+                        };
+                        if e.held_buttons().is_empty() {
+                            scrollbar_drag.set(None);
+                            return;
+                        }
+                        let mut s = app_state.write();
+                        s.drag_scrollbar(start_scroll, e.client_coordinates().y - start_y);
+                        pending_scroll_y.set(Some(s.scroll_y));
+                    },
+                    onmouseup: move |_| scrollbar_drag.set(None),
                     div {
                         class: if wrap_lines { "log-container wrap-mode" } else { "log-container nowrap-mode" },
                         tabindex: "0",
@@ -382,14 +606,17 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                             container_element.set(Some(e.data()));
                         },
                         onresize: move |_| async move {
-                            if let Some(ref el) = *container_element.read() {
+                            // Wait for a drag-resize to settle so wrapped lines are re-measured once.
+                            let generation = *resize_generation.read() + 1;
+                            resize_generation.set(generation);
+                            async_std::task::sleep(Duration::from_millis(RESIZE_DEBOUNCE_MS)).await;
+                            if *resize_generation.read() != generation {
+                                return;
+                            }
+                            let element = container_element.read().clone();
+                            if let Some(el) = element {
                                 if let Ok(rect) = el.get_client_rect().await {
-                                    let mut s = app_state.write();
-                                    s.container_height = rect.size.height;
-                                    s.container_width = rect.size.width;
-                                    s.clamp_scroll();
-                                    s.clamp_scroll_x();
-                                    s.version += 1;
+                                    app_state.write().resize_container(rect.size.width, rect.size.height);
                                 }
                             }
                         },
@@ -412,14 +639,8 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                         onkeydown: move |e| {
                             let mut s = app_state.write();
                             match e.key() {
-                                Key::ArrowUp => {
-                                    s.scroll_y -= LINE_HEIGHT;
-                                    s.follow_tail = false;
-                                }
-                                Key::ArrowDown => {
-                                    s.scroll_y += LINE_HEIGHT;
-                                    s.follow_tail = s.is_at_bottom();
-                                }
+                                Key::ArrowUp => s.nav(NavCommand::LineUp),
+                                Key::ArrowDown => s.nav(NavCommand::LineDown),
                                 Key::ArrowLeft => {
                                     if !s.wrap_lines {
                                         s.scroll_x -= 40.0;
@@ -432,23 +653,15 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                                         s.clamp_scroll_x();
                                     }
                                 }
-                                Key::PageUp => {
-                                    s.scroll_y -= s.container_height;
-                                    s.follow_tail = false;
-                                }
-                                Key::PageDown => {
-                                    s.scroll_y += s.container_height;
-                                    s.follow_tail = s.is_at_bottom();
-                                }
+                                Key::PageUp => s.nav(NavCommand::PageUp),
+                                Key::PageDown => s.nav(NavCommand::PageDown),
                                 Key::Home => {
-                                    s.scroll_y = 0.0;
+                                    s.nav(NavCommand::Top);
                                     s.scroll_x = 0.0;
-                                    s.follow_tail = false;
-                                }
-                                Key::End => {
-                                    s.scroll_to_bottom();
-                                    s.follow_tail = true;
                                 }
+                                Key::End => s.nav(NavCommand::Bottom),
+                                Key::Character(ref c) if c == "g" => s.nav(NavCommand::Top),
+                                Key::Character(ref c) if c == "G" => s.nav(NavCommand::Bottom),
                                 _ => return,
                             }
                             s.clamp_scroll();
@@ -460,13 +673,14 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                             style: "height: {total_height}px; position: relative;",
                             for (filter_idx, line_idx, offset, line, content) in visible_lines {
                                 div {
-                                    class: "log-line",
-                                    key: "{line_idx}-{wrap_lines}",
+                                    class: if line.kind == LineKind::System { "log-line system" } else { "log-line" },
+                                    key: "{line_idx}-{wrap_lines}-{layout_epoch}",
                                     style: if wrap_lines {
                                         format!("position: absolute; top: {offset}px; left: 0; right: 0;")
                                     } else {
                                         format!("position: absolute; top: {offset}px; left: -{scroll_x}px; right: 0;")
                                     },
+                                    // Measures the whole row, including the hanging indent of wrapped content.
                                     onmounted: {
                                         let filter_idx = filter_idx;
                                         move |e| async move {
@@ -488,7 +702,9 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                                             rsx! { span { class: "{age_class}", "{format_relative_time(line.timestamp)}" } }
                                         }
                                     }
-                                    span { class: "line-num", "{line_idx + 1}" }
+                                    if show_line_numbers {
+                                        span { class: "line-num", "{line_idx + 1}" }
+                                    }
                                     LogLineContent {
                                         content: content,
                                         highlight_text: highlight_text.clone(),
@@ -498,6 +714,25 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                             }
                         }
                     }
+                    if let Some((thumb_top, thumb_height)) = scrollbar_thumb {
+                        div {
+                            class: "scrollbar",
+                            onmousedown: move |e| {
+                                let mut s = app_state.write();
+                                s.page_scrollbar(e.element_coordinates().y);
+                                pending_scroll_y.set(Some(s.scroll_y));
+                            },
+                            div {
+                                class: "scrollbar-thumb",
+                                style: "top: {thumb_top}px; height: {thumb_height}px;",
+                                onmousedown: move |e| {
+                                    e.stop_propagation();
+                                    let scroll_y = app_state.read().scroll_y;
+                                    scrollbar_drag.set(Some((e.client_coordinates().y, scroll_y)));
+                                },
+                            }
+                        }
+                    }
                 }
             }
 
@@ -507,6 +742,24 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                     "{filtered_count} / {total_lines} lines"
                     if follow_tail { " • Following" }
                 }
+                if error_count > 0 {
+                    span {
+                        class: "status-errors",
+                        title: "Show source errors",
+                        onclick: move |_| {
+                            let mut s = app_state.write();
+                            s.source_errors.mark_seen();
+                            s.show_errors = true;
+                        },
+                        if error_count == 1 { "⚠ 1 error" } else { "⚠ {error_count} errors" }
+                    }
+                }
+                if let Some(connection) = listen_state.read().connection_status() {
+                    span { class: "status-info", "{connection}" }
+                }
+                if !throughput.is_empty() {
+                    span { class: "status-info", "{throughput}" }
+                }
                 span { class: "status-info",
                     if let Some(ref msg) = status_message {
                         "{msg}"
@@ -516,6 +769,35 @@ pub fn GuiApp(props: GuiAppProps) -> Element {
                 }
             }
 
+            if show_errors {
+                div { class: "popup-overlay",
+                    onclick: move |_| app_state.write().show_errors = false,
+                    div { class: "popup error-list",
+                        onclick: move |e| e.stop_propagation(),
+                        div { class: "popup-header", "Source errors" }
+                        div { class: "popup-interfaces",
+                            for error in errors.iter() {
+                                div { class: "popup-error", "{error}" }
+                            }
+                        }
+                        div { class: "toolbar-actions",
+                            button {
+                                onclick: move |_| {
+                                    let mut s = app_state.write();
+                                    s.source_errors.clear();
+                                    s.show_errors = false;
+                                },
+                                "Clear"
+                            }
+                            button {
+                                onclick: move |_| app_state.write().show_errors = false,
+                                "Close"
+                            }
+                        }
+                    }
+                }
+            }
+
             if listen_state.read().show_popup() {
                 ListenPopup { listen_state }
             }
@@ -1,9 +1,14 @@
+use super::state::highlight_content;
 use crate::core::{ListenDisplayMode, ListenState};
 use crate::filter::FilterExpr;
-use super::state::highlight_content;
 use dioxus::prelude::*;
 
-fn format_addr_display(ip: &std::net::IpAddr, port: u16, is_v6: bool, mode: ListenDisplayMode) -> String {
+fn format_addr_display(
+    ip: &std::net::IpAddr,
+    port: u16,
+    is_v6: bool,
+    mode: ListenDisplayMode,
+) -> String {
     match mode {
         ListenDisplayMode::AddrPort => {
             if is_v6 {
@@ -61,7 +66,12 @@ fn get_copy_text_from_interfaces(state: &ListenState) -> Option<String> {
         for addr_info in &iface.addresses {
             if addr_idx == state.selected_idx {
                 let is_v6 = addr_info.ip.is_ipv6();
-                return Some(format_addr_display(&addr_info.ip, port, is_v6, state.display_mode));
+                return Some(format_addr_display(
+                    &addr_info.ip,
+                    port,
+                    is_v6,
+                    state.display_mode,
+                ));
             }
             addr_idx += 1;
         }
@@ -69,27 +79,20 @@ fn get_copy_text_from_interfaces(state: &ListenState) -> Option<String> {
     None
 }
 
-#[cfg(target_os = "macos")]
 fn copy_to_clipboard(text: &str) {
-    let _ = std::process::Command::new("pbcopy")
-        .stdin(std::process::Stdio::piped())
-        .spawn()
-        .and_then(|mut child| {
-            use std::io::Write;
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(text.as_bytes())?;
-            }
-            child.wait()
-        });
+    if let Err(e) = crate::clipboard::copy_to_clipboard(text) {
+        tracing::warn!("Copy failed: {}", e);
+    }
 }
 
-#[cfg(not(target_os = "macos"))]
-fn copy_to_clipboard(_text: &str) {}
-
 #[component]
 pub fn ListenPopup(listen_state: Signal<ListenState>) -> Element {
     let state = listen_state.read();
     let port = state.port.unwrap_or(0);
+    let bind_note = state
+        .bind_addr
+        .map(|addr| format!(" at {}", addr))
+        .unwrap_or_default();
     let interfaces = state.network_interfaces.clone();
     let display_mode = state.display_mode;
     let selected_idx = state.selected_idx;
@@ -129,6 +132,7 @@ pub fn ListenPopup(listen_state: Signal<ListenState>) -> Element {
                 div { class: "popup-header",
                     span { "Listening on port " }
                     span { class: "popup-port", "{port}" }
+                    span { "{bind_note}" }
                 }
                 div { class: "popup-mode",
                     span { class: "popup-label", "Mode (Tab): " }
@@ -2,36 +2,74 @@ pub mod app;
 mod components;
 mod state;
 mod style;
+mod window;
 
 use anyhow::Result;
-use dioxus::desktop::{Config, WindowBuilder};
+use dioxus::desktop::{Config, LogicalPosition, LogicalSize, WindowBuilder};
 use dioxus::prelude::*;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::time::Duration;
 
+use crate::state::Presets;
 use app::GuiApp;
 
-static INIT_FILE: std::sync::OnceLock<Option<PathBuf>> = std::sync::OnceLock::new();
+static INIT_FILE: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
 static INIT_PORT: std::sync::OnceLock<Option<u16>> = std::sync::OnceLock::new();
+static INIT_BIND: std::sync::OnceLock<Option<IpAddr>> = std::sync::OnceLock::new();
+static INIT_LINE_START: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+static INIT_TAIL: std::sync::OnceLock<Option<usize>> = std::sync::OnceLock::new();
+static INIT_PRESETS: std::sync::OnceLock<Presets> = std::sync::OnceLock::new();
+static INIT_VALIDATE_DELAY: std::sync::OnceLock<Duration> = std::sync::OnceLock::new();
 
-pub fn run_with_args(file: Option<PathBuf>, port: Option<u16>) -> Result<()> {
+pub fn run_with_args(
+    file: Option<String>,
+    port: Option<u16>,
+    bind: Option<IpAddr>,
+    line_start: Option<String>,
+    tail: Option<usize>,
+    presets: Presets,
+    validate_delay: Duration,
+) -> Result<()> {
     INIT_FILE.set(file).ok();
     INIT_PORT.set(port).ok();
+    INIT_BIND.set(bind).ok();
+    INIT_LINE_START.set(line_start).ok();
+    INIT_TAIL.set(tail).ok();
+    INIT_PRESETS.set(presets).ok();
+    INIT_VALIDATE_DELAY.set(validate_delay).ok();
 
-    let window = WindowBuilder::new().with_always_on_top(false);
+    let mut window = WindowBuilder::new().with_always_on_top(false);
+    if let Some(geometry) = window::saved_geometry() {
+        window = window
+            .with_inner_size(LogicalSize::new(geometry.width, geometry.height))
+            .with_position(LogicalPosition::new(geometry.x, geometry.y));
+    }
     let config = Config::default().with_window(window);
 
-    LaunchBuilder::desktop().with_cfg(config).launch(app_with_args);
+    LaunchBuilder::desktop()
+        .with_cfg(config)
+        .launch(app_with_args);
     Ok(())
 }
 
 fn app_with_args() -> Element {
     let file = INIT_FILE.get().cloned().flatten();
     let port = INIT_PORT.get().cloned().flatten();
+    let bind = INIT_BIND.get().cloned().flatten();
+    let line_start = INIT_LINE_START.get().cloned().flatten();
+    let tail = INIT_TAIL.get().cloned().flatten();
+    let presets = INIT_PRESETS.get().cloned().unwrap_or_default();
+    let validate_delay = INIT_VALIDATE_DELAY.get().copied().unwrap_or_default();
 
     rsx! {
         GuiApp {
             file: file,
             port: port,
+            bind: bind,
+            line_start: line_start,
+            tail: tail,
+            presets: presets,
+            validate_delay: validate_delay,
         }
     }
 }
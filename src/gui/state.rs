@@ -1,16 +1,41 @@
-use crate::core::{FilterState, LogLine};
-use crate::filter::{parse_filter, FilterExpr};
-use crate::highlight::{apply_highlights, highlight_line, HighlightStyle};
-use crate::state::AppState;
+use crate::core::{
+    compile_hide_regex, nav, ErrorLog, FilterState, LineKind, LogLine, NavCommand, Throughput,
+    Viewport,
+};
+use crate::filter::{parse_filter_opts, FilterExpr, FilterOptions, LineFields};
+use crate::highlight::{apply_highlights, highlight_line, HeuristicRules, HighlightStyle};
+use crate::state::{AppState, Presets};
 use fancy_regex::Regex;
 
 const LINE_HEIGHT: f64 = 20.0;
+const SCROLLBAR_MIN_THUMB: f64 = 30.0;
+/// Estimated width of the `.line-num` column.
+const LINE_NUM_WIDTH: f64 = 62.0;
 
-pub fn highlight_content(content: &str, highlight_expr: &Option<FilterExpr>) -> Vec<(String, HighlightStyle)> {
-    let spans = highlight_line(content, highlight_expr.as_ref(), true, true);
+pub fn highlight_content(
+    content: &str,
+    highlight_expr: &Option<FilterExpr>,
+) -> Vec<(String, HighlightStyle)> {
+    let spans = highlight_line(
+        content,
+        highlight_expr.as_ref(),
+        None,
+        HeuristicRules::ALL,
+        true,
+        false,
+    );
     apply_highlights(content, &spans)
 }
 
+/// An empty field is never an error.
+fn field_error(text: &str, check: impl FnOnce(&str) -> Option<String>) -> Option<String> {
+    if text.trim().is_empty() {
+        None
+    } else {
+        check(text)
+    }
+}
+
 #[derive(Clone)]
 pub struct GuiAppState {
     pub lines: Vec<LogLine>,
@@ -18,13 +43,19 @@ pub struct GuiAppState {
     pub filter_state: FilterState,
     pub follow_tail: bool,
     pub show_time: bool,
+    pub show_line_numbers: bool,
     pub wrap_lines: bool,
+    pub case_insensitive: bool,
+    pub smart_case: bool,
+    pub literal: bool,
+    pub whole_word: bool,
     pub hide_text: String,
     pub filter_text: String,
     pub highlight_text: String,
     pub line_start_text: String,
     pub hide_error: Option<String>,
     pub filter_error: Option<String>,
+    pub highlight_error: Option<String>,
     pub line_start_error: Option<String>,
     pub status_message: Option<String>,
     pub is_connected: bool,
@@ -36,25 +67,45 @@ pub struct GuiAppState {
     pub version: u64,
     pub line_heights: Vec<f64>,
     pub line_offsets: Vec<f64>,
+    /// Bumped whenever measured heights are dropped; part of each line's key so
+    /// the lines remount and `onmounted` measures them again.
+    pub layout_epoch: u64,
     pub last_update_time: Option<chrono::DateTime<chrono::Local>>,
+    pub throughput: Throughput,
+    /// Errors from the source, shown as a badge in the status bar.
+    pub source_errors: ErrorLog,
+    pub show_errors: bool,
+    /// Saved expressions replaced by command-line presets, written back in
+    /// their place until the user edits that field.
+    pub shadowed: Presets,
 }
 
 impl GuiAppState {
-    pub fn new() -> Self {
-        let state = AppState::load();
+    pub fn new(presets: &Presets) -> Self {
+        let mut state = AppState::load();
+        let shadowed = presets.apply_to(&mut state);
         let mut s = Self {
             lines: Vec::new(),
             filtered_indices: Vec::new(),
-            filter_state: FilterState::default(),
+            filter_state: FilterState {
+                filter_after_hide: state.filter_after_hide,
+                ..FilterState::default()
+            },
             follow_tail: true,
             show_time: true,
+            show_line_numbers: state.show_line_numbers,
             wrap_lines: state.wrap_lines,
+            case_insensitive: state.case_insensitive,
+            smart_case: state.smart_case,
+            literal: state.literal,
+            whole_word: state.whole_word,
             hide_text: state.hide_input.clone(),
             filter_text: state.filter_input.clone(),
             highlight_text: state.highlight_input.clone(),
             line_start_text: state.line_start_regex.clone(),
             hide_error: None,
             filter_error: None,
+            highlight_error: None,
             line_start_error: None,
             status_message: None,
             is_connected: false,
@@ -66,20 +117,25 @@ impl GuiAppState {
             version: 0,
             line_heights: Vec::new(),
             line_offsets: Vec::new(),
+            layout_epoch: 0,
             last_update_time: None,
+            throughput: Throughput::default(),
+            source_errors: ErrorLog::default(),
+            show_errors: false,
+            shadowed,
         };
         if !s.hide_text.trim().is_empty() {
-            if let Ok(re) = Regex::new(&s.hide_text) {
+            if let Ok(re) = compile_hide_regex(&s.hide_text, s.case_insensitive) {
                 s.filter_state.hide_regex = Some(re);
             }
         }
         if !s.filter_text.trim().is_empty() {
-            if let Ok(expr) = parse_filter(&s.filter_text) {
+            if let Ok(expr) = parse_filter_opts(&s.filter_text, s.filter_options()) {
                 s.filter_state.filter_expr = Some(expr);
             }
         }
         if !s.highlight_text.trim().is_empty() {
-            if let Ok(expr) = parse_filter(&s.highlight_text) {
+            if let Ok(expr) = parse_filter_opts(&s.highlight_text, s.filter_options()) {
                 s.filter_state.highlight_expr = Some(expr);
             }
         }
@@ -90,15 +146,29 @@ impl GuiAppState {
         self.filter_state.apply_hide(&line.content)
     }
 
-    fn matches_filter(&self, line: &LogLine) -> bool {
-        let content = self.get_display_content(line).unwrap_or_else(|_| line.content.clone());
-        self.filter_state.matches_filter(&content)
+    /// `idx` is the line's position in `lines`, which may not hold it yet.
+    fn matches_filter(&self, idx: usize, line: &LogLine) -> bool {
+        if line.kind == LineKind::System {
+            return true;
+        }
+        let fields = LineFields {
+            line: Some(idx + 1),
+            last_line: Some(self.lines.len().max(idx + 1)),
+            ..LineFields::default()
+        };
+        if !self.filter_state.filter_after_hide {
+            return self.filter_state.matches_filter(&line.content, &fields);
+        }
+        let content = self
+            .get_display_content(line)
+            .unwrap_or_else(|_| line.content.clone());
+        self.filter_state.matches_filter(&content, &fields)
     }
 
     fn rebuild_filtered_indices(&mut self) {
         self.filtered_indices.clear();
         for (i, line) in self.lines.iter().enumerate() {
-            if self.matches_filter(line) {
+            if self.matches_filter(i, line) {
                 self.filtered_indices.push(i);
             }
         }
@@ -107,12 +177,38 @@ impl GuiAppState {
         self.version += 1;
     }
 
+    fn refine_filtered_indices(&mut self) {
+        let previous = std::mem::take(&mut self.filtered_indices);
+        self.filtered_indices = previous
+            .into_iter()
+            .filter(|&i| self.matches_filter(i, &self.lines[i]))
+            .collect();
+        self.reset_line_heights();
+        self.clamp_scroll();
+        self.version += 1;
+    }
+
     fn reset_line_heights(&mut self) {
         let count = self.filtered_indices.len();
         self.line_heights = vec![LINE_HEIGHT; count];
+        self.layout_epoch += 1;
         self.rebuild_offsets();
     }
 
+    /// Record a new container size. Wrapped lines change height with the width,
+    /// so their measurements are dropped and re-taken.
+    pub fn resize_container(&mut self, width: f64, height: f64) {
+        let width_changed = (self.container_width - width).abs() > 0.5;
+        self.container_height = height;
+        self.container_width = width;
+        if self.wrap_lines && width_changed {
+            self.reset_line_heights();
+        }
+        self.clamp_scroll();
+        self.clamp_scroll_x();
+        self.version += 1;
+    }
+
     fn rebuild_offsets(&mut self) {
         self.line_offsets.clear();
         let mut offset = 0.0;
@@ -128,7 +224,9 @@ impl GuiAppState {
     }
 
     pub fn set_line_height(&mut self, filtered_idx: usize, height: f64) {
-        if filtered_idx < self.line_heights.len() && (self.line_heights[filtered_idx] - height).abs() > 0.5 {
+        if filtered_idx < self.line_heights.len()
+            && (self.line_heights[filtered_idx] - height).abs() > 0.5
+        {
             self.line_heights[filtered_idx] = height;
             self.rebuild_offsets();
             self.version += 1;
@@ -136,9 +234,15 @@ impl GuiAppState {
     }
 
     pub fn find_visible_range(&self, scroll_y: f64, viewport_height: f64) -> (usize, usize) {
-        let start = self.line_offsets.partition_point(|&o| o <= scroll_y).saturating_sub(1);
+        let start = self
+            .line_offsets
+            .partition_point(|&o| o <= scroll_y)
+            .saturating_sub(1);
         let end_scroll = scroll_y + viewport_height;
-        let end = self.line_offsets.partition_point(|&o| o < end_scroll).min(self.filtered_indices.len());
+        let end = self
+            .line_offsets
+            .partition_point(|&o| o < end_scroll)
+            .min(self.filtered_indices.len());
         (start, end)
     }
 
@@ -148,11 +252,30 @@ impl GuiAppState {
 
     fn save_state(&self) {
         let state = AppState {
-            hide_input: self.hide_text.clone(),
-            filter_input: self.filter_text.clone(),
-            highlight_input: self.highlight_text.clone(),
+            hide_input: self
+                .shadowed
+                .hide
+                .clone()
+                .unwrap_or_else(|| self.hide_text.clone()),
+            filter_input: self
+                .shadowed
+                .filter
+                .clone()
+                .unwrap_or_else(|| self.filter_text.clone()),
+            highlight_input: self
+                .shadowed
+                .highlight
+                .clone()
+                .unwrap_or_else(|| self.highlight_text.clone()),
             wrap_lines: self.wrap_lines,
+            show_line_numbers: self.show_line_numbers,
+            case_insensitive: self.case_insensitive,
+            smart_case: self.smart_case,
+            literal: self.literal,
+            whole_word: self.whole_word,
+            filter_after_hide: self.filter_state.filter_after_hide,
             line_start_regex: self.line_start_text.clone(),
+            ..AppState::load()
         };
         state.save();
     }
@@ -171,28 +294,38 @@ impl GuiAppState {
             self.filter_state.hide_regex = None;
             self.hide_error = None;
         } else {
-            match Regex::new(&self.hide_text) {
+            match compile_hide_regex(&self.hide_text, self.case_insensitive) {
                 Ok(re) => {
                     self.filter_state.hide_regex = Some(re);
                     self.hide_error = None;
                 }
                 Err(e) => {
-                    self.hide_error = Some(e.to_string());
+                    self.hide_error = Some(e);
                     return;
                 }
             }
         }
-        self.rebuild_filtered_indices();
+        if self.filter_state.filter_after_hide {
+            self.rebuild_filtered_indices();
+        } else {
+            self.version += 1;
+        }
         self.save_state();
     }
 
     pub fn apply_filter(&mut self) {
+        let mut refine = false;
         if self.filter_text.trim().is_empty() {
             self.filter_state.filter_expr = None;
             self.filter_error = None;
         } else {
-            match parse_filter(&self.filter_text) {
+            match parse_filter_opts(&self.filter_text, self.filter_options()) {
                 Ok(expr) => {
+                    refine = self
+                        .filter_state
+                        .filter_expr
+                        .as_ref()
+                        .is_some_and(|previous| expr.refines(previous));
                     self.filter_state.filter_expr = Some(expr);
                     self.filter_error = None;
                 }
@@ -202,22 +335,110 @@ impl GuiAppState {
                 }
             }
         }
-        self.rebuild_filtered_indices();
+        if refine {
+            self.refine_filtered_indices();
+        } else {
+            self.rebuild_filtered_indices();
+        }
         self.save_state();
     }
 
     pub fn apply_highlight(&mut self) {
         if self.highlight_text.trim().is_empty() {
             self.filter_state.highlight_expr = None;
+            self.highlight_error = None;
         } else {
-            if let Ok(expr) = parse_filter(&self.highlight_text) {
-                self.filter_state.highlight_expr = Some(expr);
+            match parse_filter_opts(&self.highlight_text, self.filter_options()) {
+                Ok(expr) => {
+                    self.filter_state.highlight_expr = Some(expr);
+                    self.highlight_error = None;
+                }
+                Err(e) => self.highlight_error = Some(e.to_string()),
             }
         }
         self.version += 1;
         self.save_state();
     }
 
+    /// Set each field's error from its current text without applying it.
+    pub fn validate_inputs(&mut self) {
+        let options = self.filter_options();
+        let case_insensitive = self.case_insensitive;
+        let expr_error = |text: &str| {
+            parse_filter_opts(text, options)
+                .err()
+                .map(|e| e.to_string())
+        };
+        self.hide_error = field_error(&self.hide_text, |text| {
+            compile_hide_regex(text, case_insensitive).err()
+        });
+        self.filter_error = field_error(&self.filter_text, expr_error);
+        self.highlight_error = field_error(&self.highlight_text, expr_error);
+        self.line_start_error = field_error(&self.line_start_text, |text| {
+            Regex::new(text).err().map(|e| e.to_string())
+        });
+    }
+
+    pub fn toggle_case_insensitive(&mut self) {
+        self.case_insensitive = !self.case_insensitive;
+        self.apply_hide();
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    pub fn toggle_smart_case(&mut self) {
+        self.smart_case = !self.smart_case;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    pub fn toggle_literal(&mut self) {
+        self.literal = !self.literal;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    /// Show or hide the line-number column. Every width estimate includes it,
+    /// so the widest one shifts by the same amount.
+    pub fn toggle_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
+        if self.max_content_width > 0.0 {
+            let shift = if self.show_line_numbers {
+                LINE_NUM_WIDTH
+            } else {
+                -LINE_NUM_WIDTH
+            };
+            self.max_content_width += shift;
+        }
+        if self.wrap_lines {
+            self.reset_line_heights();
+        }
+        self.clamp_scroll_x();
+        self.save_state();
+        self.version += 1;
+    }
+
+    pub fn toggle_filter_after_hide(&mut self) {
+        self.filter_state.filter_after_hide = !self.filter_state.filter_after_hide;
+        self.rebuild_filtered_indices();
+        self.save_state();
+    }
+
+    pub fn toggle_whole_word(&mut self) {
+        self.whole_word = !self.whole_word;
+        self.apply_filter();
+        self.apply_highlight();
+    }
+
+    fn filter_options(&self) -> FilterOptions {
+        FilterOptions {
+            case_insensitive: self.case_insensitive,
+            smart_case: self.smart_case,
+            literal: self.literal,
+            whole_word: self.whole_word,
+        }
+    }
+
     pub fn apply_line_start(&mut self) {
         if self.line_start_text.trim().is_empty() {
             self.line_start_error = None;
@@ -237,20 +458,51 @@ impl GuiAppState {
     }
 
     pub fn add_line(&mut self, content: String) {
-        self.add_line_with_update(content, true);
+        self.push_line(content, LineKind::Output, true);
     }
 
-    pub fn add_line_with_update(&mut self, content: String, update_time: bool) {
+    pub fn add_system_line(&mut self, content: String) {
+        self.push_line(content, LineKind::System, false);
+    }
+
+    /// Put `content` in place of the newest line, for a progress bar redrawn
+    /// after a `\r`. A notice in between means there is nothing to redraw.
+    pub fn replace_last_line(&mut self, content: String) {
+        if self
+            .lines
+            .last()
+            .is_some_and(|line| line.kind != LineKind::System)
+        {
+            let idx = self.lines.len() - 1;
+            self.lines.pop();
+            if self.filtered_indices.last() == Some(&idx) {
+                self.filtered_indices.pop();
+                self.line_heights.pop();
+                self.line_offsets.pop();
+            }
+        }
+        self.push_line(content, LineKind::Output, true);
+    }
+
+    fn push_line(&mut self, content: String, kind: LineKind, update_time: bool) {
         let now = chrono::Local::now();
+        if kind != LineKind::System {
+            let second = self.throughput.now();
+            self.throughput.record(second, content.len());
+        }
         let line = LogLine {
             content: content
                 .trim_end_matches('\n')
                 .trim_end_matches('\r')
                 .to_string(),
             timestamp: now,
+            timestamp_parsed: false,
+            kind,
+            source: None,
+            syslog: None,
         };
         let idx = self.lines.len();
-        let matches = self.matches_filter(&line);
+        let matches = self.matches_filter(idx, &line);
         let estimated_width = self.estimate_line_width(&line);
         if estimated_width > self.max_content_width {
             self.max_content_width = estimated_width;
@@ -271,10 +523,16 @@ impl GuiAppState {
     }
 
     fn estimate_line_width(&self, line: &LogLine) -> f64 {
-        let content = self.get_display_content(line).unwrap_or_else(|_| line.content.clone());
+        let content = self
+            .get_display_content(line)
+            .unwrap_or_else(|_| line.content.clone());
         let char_width = 7.2;
         let timestamp_width = if self.show_time { 32.0 } else { 0.0 };
-        let line_num_width = 62.0;
+        let line_num_width = if self.show_line_numbers {
+            LINE_NUM_WIDTH
+        } else {
+            0.0
+        };
         let padding = 24.0;
         timestamp_width + line_num_width + (content.len() as f64 * char_width) + padding
     }
@@ -308,4 +566,58 @@ impl GuiAppState {
     pub fn is_at_bottom(&self) -> bool {
         self.scroll_y >= self.max_scroll() - 1.0
     }
+
+    /// Scroll for a navigation key, the same way the TUI does.
+    pub fn nav(&mut self, cmd: NavCommand) {
+        let viewport = Viewport {
+            offset: self.scroll_y,
+            max_offset: self.max_scroll(),
+            line: LINE_HEIGHT,
+            page: self.container_height,
+        };
+        (self.scroll_y, self.follow_tail) = nav(self.follow_tail, cmd, viewport);
+    }
+
+    /// Top and height of the scrollbar thumb, `None` when everything fits.
+    pub fn scrollbar_thumb(&self) -> Option<(f64, f64)> {
+        let max_scroll = self.max_scroll();
+        if max_scroll <= 0.0 {
+            return None;
+        }
+        let track = self.container_height;
+        let height = (track * track / (max_scroll + track))
+            .max(SCROLLBAR_MIN_THUMB)
+            .min(track);
+        let top = self.scroll_y.clamp(0.0, max_scroll) / max_scroll * (track - height);
+        Some((top, height))
+    }
+
+    /// Scroll to where the thumb is `delta` pixels from its position at `start_scroll`.
+    pub fn drag_scrollbar(&mut self, start_scroll: f64, delta: f64) {
+        let Some((_, height)) = self.scrollbar_thumb() else {
+            return;
+        };
+        let travel = self.container_height - height;
+        if travel > 0.0 {
+            self.scroll_y = start_scroll + delta * self.max_scroll() / travel;
+        }
+        self.clamp_scroll();
+        self.follow_tail = self.is_at_bottom();
+        self.version += 1;
+    }
+
+    /// Page towards a click on the scrollbar track outside the thumb.
+    pub fn page_scrollbar(&mut self, click_y: f64) {
+        let Some((top, _)) = self.scrollbar_thumb() else {
+            return;
+        };
+        if click_y < top {
+            self.scroll_y -= self.container_height;
+        } else {
+            self.scroll_y += self.container_height;
+        }
+        self.clamp_scroll();
+        self.follow_tail = self.is_at_bottom();
+        self.version += 1;
+    }
 }
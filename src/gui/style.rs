@@ -118,6 +118,30 @@ html, body {
     overscroll-behavior: contain;
 }
 
+/* The vertical bar is drawn by `.scrollbar`; the native horizontal one stays for nowrap mode. */
+.log-container::-webkit-scrollbar {
+    width: 0;
+    height: 14px;
+}
+
+.log-container::-webkit-scrollbar-track {
+    background: light-dark(#f0f0f0, #1e1e1e);
+    border-top: 1px solid light-dark(#d4d4d4, #3c3c3c);
+}
+
+.log-container::-webkit-scrollbar-thumb {
+    background: light-dark(#c4c4c4, #5a5a5a);
+    border: 2px solid transparent;
+    background-clip: padding-box;
+    border-radius: 7px;
+    min-width: 30px;
+}
+
+.log-container::-webkit-scrollbar-thumb:hover {
+    background: light-dark(#a0a0a0, #787878);
+    background-clip: padding-box;
+}
+
 .nowrap-mode.log-container {
     overflow-x: auto;
 }
@@ -156,6 +180,11 @@ html, body {
     min-height: 20px;
 }
 
+.log-line.system {
+    opacity: 0.6;
+    font-style: italic;
+}
+
 .log-line:hover {
     background: light-dark(#f0f0f0, #2a2d2e);
 }
@@ -209,6 +238,10 @@ html, body {
 .wrap-mode .content {
     white-space: pre-wrap;
     word-break: break-all;
+    flex: 1;
+    min-width: 0;
+    padding-left: 2ch;
+    text-indent: -2ch;
 }
 
 .hl-error {
@@ -238,6 +271,19 @@ html, body {
     color: light-dark(#6f42c1, #d2a8ff);
 }
 
+.hl-ip {
+    color: light-dark(#0b7285, #66d9e8);
+}
+
+.hl-url {
+    color: light-dark(#0550ae, #a5d6ff);
+    text-decoration: underline;
+}
+
+.hl-uuid {
+    color: light-dark(#a626a4, #f0a3ff);
+}
+
 .hl-custom {
     background: light-dark(#ffff00, #ffcc00);
     color: light-dark(#000000, #000000);
@@ -246,6 +292,12 @@ html, body {
     font-weight: bold;
 }
 
+.hl-search {
+    background: light-dark(#000000, #ffffff);
+    color: light-dark(#ffffff, #000000);
+    font-weight: bold;
+}
+
 .hl-json-key {
     color: light-dark(#17a2b8, #58a6ff);
 }
@@ -266,7 +318,16 @@ html, body {
     color: light-dark(#dc3545, #f85149);
 }
 
+.hl-logfmt-key {
+    color: light-dark(#0366d6, #79c0ff);
+}
+
+.hl-logfmt-value {
+    color: light-dark(#28a745, #3fb950);
+}
+
 .scrollbar {
+    flex-shrink: 0;
     width: 14px;
     background: light-dark(#f0f0f0, #1e1e1e);
     border-left: 1px solid light-dark(#d4d4d4, #3c3c3c);
@@ -282,27 +343,8 @@ html, body {
     min-height: 30px;
 }
 
-.scrollbar-thumb:hover {
-    background: light-dark(#a0a0a0, #787878);
-}
-
-.scrollbar-h {
-    height: 14px;
-    background: light-dark(#f0f0f0, #1e1e1e);
-    border-top: 1px solid light-dark(#d4d4d4, #3c3c3c);
-    position: relative;
-}
-
-.scrollbar-thumb-h {
-    position: absolute;
-    height: 10px;
-    top: 2px;
-    background: light-dark(#c4c4c4, #5a5a5a);
-    border-radius: 5px;
-    min-width: 30px;
-}
-
-.scrollbar-thumb-h:hover {
+.scrollbar-thumb:hover,
+.scrollbar-thumb:active {
     background: light-dark(#a0a0a0, #787878);
 }
 
@@ -323,6 +365,18 @@ html, body {
     opacity: 0.9;
 }
 
+.status-errors {
+    background: #c42b1c;
+    border-radius: 3px;
+    padding: 0 6px;
+    font-weight: bold;
+    cursor: pointer;
+}
+
+.popup.error-list {
+    max-width: 80vw;
+}
+
 .status-msg {
     opacity: 0.8;
 }
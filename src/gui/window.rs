@@ -0,0 +1,69 @@
+use crate::state::{AppState, WindowGeometry};
+use dioxus::desktop::{DesktopContext, LogicalPosition};
+
+/// Smaller saved sizes are ignored, in case the window was collapsed.
+const MIN_WIDTH: f64 = 200.0;
+const MIN_HEIGHT: f64 = 150.0;
+/// How much of the window must be on some monitor to leave it where it was.
+const VISIBLE_MARGIN: f64 = 100.0;
+
+/// The saved geometry, if it is big enough to restore.
+pub fn saved_geometry() -> Option<WindowGeometry> {
+    AppState::load()
+        .window
+        .filter(|geometry| geometry.width >= MIN_WIDTH && geometry.height >= MIN_HEIGHT)
+}
+
+/// Current geometry, or `None` while minimized or maximized so those states
+/// don't replace the size to restore.
+pub fn current_geometry(window: &DesktopContext) -> Option<WindowGeometry> {
+    if window.is_minimized() || window.is_maximized() {
+        return None;
+    }
+    let scale = window.scale_factor();
+    let position = window.outer_position().ok()?.to_logical::<f64>(scale);
+    let size = window.inner_size().to_logical::<f64>(scale);
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+pub fn save_geometry(geometry: WindowGeometry) {
+    AppState {
+        window: Some(geometry),
+        ..AppState::load()
+    }
+    .save();
+}
+
+/// Center the window on the primary monitor if the restored position left it
+/// off every monitor, e.g. after one was unplugged.
+pub fn keep_on_screen(window: &DesktopContext) {
+    let Some(geometry) = current_geometry(window) else {
+        return;
+    };
+    let visible = window.available_monitors().any(|monitor| {
+        let scale = monitor.scale_factor();
+        let origin = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+        geometry.x + geometry.width > origin.x + VISIBLE_MARGIN
+            && geometry.x < origin.x + size.width - VISIBLE_MARGIN
+            && geometry.y >= origin.y
+            && geometry.y < origin.y + size.height - VISIBLE_MARGIN
+    });
+    if visible {
+        return;
+    }
+    if let Some(monitor) = window.primary_monitor() {
+        let scale = monitor.scale_factor();
+        let origin = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+        window.set_outer_position(LogicalPosition::new(
+            origin.x + ((size.width - geometry.width) / 2.0).max(0.0),
+            origin.y + ((size.height - geometry.height) / 2.0).max(0.0),
+        ));
+    }
+}
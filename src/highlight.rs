@@ -1,9 +1,14 @@
+use crate::core::level::{self, level_pattern, Level};
 use crate::filter::FilterExpr;
+use anyhow::{anyhow, Result};
+use ratatui::style::{Color, Style};
 use regex::Regex;
 use serde_json::Value;
-use std::sync::LazyLock;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum HighlightStyle {
     None,
     Error,
@@ -12,12 +17,20 @@ pub enum HighlightStyle {
     Debug,
     Bracket,
     Timestamp,
+    IpAddress,
+    Url,
+    Uuid,
     CustomHighlight,
+    SearchMatch,
     JsonKey,
     JsonString,
     JsonNumber,
     JsonBool,
     JsonNull,
+    LogfmtKey,
+    LogfmtValue,
+    /// Index into the user-defined rules from the config file.
+    UserRule(usize),
 }
 
 impl HighlightStyle {
@@ -30,74 +43,505 @@ impl HighlightStyle {
             HighlightStyle::Debug => "hl-debug",
             HighlightStyle::Bracket => "hl-bracket",
             HighlightStyle::Timestamp => "hl-timestamp",
+            HighlightStyle::IpAddress => "hl-ip",
+            HighlightStyle::Url => "hl-url",
+            HighlightStyle::Uuid => "hl-uuid",
             HighlightStyle::CustomHighlight => "hl-custom",
+            HighlightStyle::SearchMatch => "hl-search",
             HighlightStyle::JsonKey => "hl-json-key",
             HighlightStyle::JsonString => "hl-json-string",
             HighlightStyle::JsonNumber => "hl-json-number",
             HighlightStyle::JsonBool => "hl-json-bool",
             HighlightStyle::JsonNull => "hl-json-null",
+            HighlightStyle::LogfmtKey => "hl-logfmt-key",
+            HighlightStyle::LogfmtValue => "hl-logfmt-value",
+            HighlightStyle::UserRule(_) => "",
         }
     }
 
-    pub fn to_ratatui_style(&self) -> ratatui::style::Style {
-        use ratatui::style::{Color, Modifier, Style};
+    /// Name used for this style in the config file's `theme` table.
+    fn theme_key(&self) -> Option<&'static str> {
+        match self {
+            HighlightStyle::Error => Some("error"),
+            HighlightStyle::Warning => Some("warn"),
+            HighlightStyle::Info => Some("info"),
+            HighlightStyle::Debug => Some("debug"),
+            HighlightStyle::Bracket => Some("bracket"),
+            HighlightStyle::Timestamp => Some("timestamp"),
+            HighlightStyle::IpAddress => Some("ip"),
+            HighlightStyle::Url => Some("url"),
+            HighlightStyle::Uuid => Some("uuid"),
+            HighlightStyle::CustomHighlight => Some("custom"),
+            HighlightStyle::JsonKey => Some("json_key"),
+            HighlightStyle::JsonString => Some("json_string"),
+            HighlightStyle::JsonNumber => Some("json_number"),
+            HighlightStyle::JsonBool => Some("json_bool"),
+            HighlightStyle::JsonNull => Some("json_null"),
+            HighlightStyle::LogfmtKey => Some("logfmt_key"),
+            HighlightStyle::LogfmtValue => Some("logfmt_value"),
+            HighlightStyle::None | HighlightStyle::SearchMatch | HighlightStyle::UserRule(_) => {
+                None
+            }
+        }
+    }
+
+    /// Style under the installed `HighlightConfig`. Code drawing many runs
+    /// should hold on to a `HighlightConfig` and use its `style_for` instead.
+    pub fn to_ratatui_style(&self) -> Style {
+        current_config().style_for(*self)
+    }
+
+    fn default_ratatui_style(&self) -> Style {
+        use ratatui::style::Modifier;
         match self {
             HighlightStyle::None => Style::default(),
             HighlightStyle::Error => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            HighlightStyle::Warning => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-            HighlightStyle::Info => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+            HighlightStyle::Warning => Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            HighlightStyle::Info => Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
             HighlightStyle::Debug => Style::default().fg(Color::Cyan),
             HighlightStyle::Bracket => Style::default().fg(Color::Blue),
             HighlightStyle::Timestamp => Style::default().fg(Color::Magenta),
-            HighlightStyle::CustomHighlight => Style::default().bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD),
+            HighlightStyle::IpAddress => Style::default().fg(Color::LightCyan),
+            HighlightStyle::Url => Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::UNDERLINED),
+            HighlightStyle::Uuid => Style::default().fg(Color::LightMagenta),
+            HighlightStyle::CustomHighlight => Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            HighlightStyle::SearchMatch => {
+                Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+            }
             HighlightStyle::JsonKey => Style::default().fg(Color::Cyan),
             HighlightStyle::JsonString => Style::default().fg(Color::Green),
             HighlightStyle::JsonNumber => Style::default().fg(Color::Yellow),
             HighlightStyle::JsonBool => Style::default().fg(Color::Magenta),
             HighlightStyle::JsonNull => Style::default().fg(Color::Red),
+            HighlightStyle::LogfmtKey => Style::default().fg(Color::Blue),
+            HighlightStyle::LogfmtValue => Style::default().fg(Color::Green),
+            HighlightStyle::UserRule(_) => Style::default(),
+        }
+    }
+}
+
+const THEMED_STYLES: [HighlightStyle; 17] = [
+    HighlightStyle::Error,
+    HighlightStyle::Warning,
+    HighlightStyle::Info,
+    HighlightStyle::Debug,
+    HighlightStyle::Bracket,
+    HighlightStyle::Timestamp,
+    HighlightStyle::IpAddress,
+    HighlightStyle::Url,
+    HighlightStyle::Uuid,
+    HighlightStyle::CustomHighlight,
+    HighlightStyle::JsonKey,
+    HighlightStyle::JsonString,
+    HighlightStyle::JsonNumber,
+    HighlightStyle::JsonBool,
+    HighlightStyle::JsonNull,
+    HighlightStyle::LogfmtKey,
+    HighlightStyle::LogfmtValue,
+];
+
+/// Color overrides for the built-in highlight styles. The color replaces the
+/// foreground, or the background for the custom highlight.
+#[derive(Default)]
+pub struct HighlightTheme {
+    colors: HashMap<HighlightStyle, Color>,
+    /// Swap the colors that wash out on a light terminal background for darker ones.
+    light: bool,
+}
+
+/// Darker foregrounds for the default colors that are hard to read on a light background.
+fn light_background_color(style: HighlightStyle) -> Option<Color> {
+    match style {
+        HighlightStyle::Warning | HighlightStyle::JsonNumber => Some(Color::Rgb(175, 95, 0)),
+        HighlightStyle::Debug | HighlightStyle::JsonKey => Some(Color::Rgb(0, 120, 140)),
+        HighlightStyle::IpAddress => Some(Color::Rgb(0, 100, 160)),
+        HighlightStyle::Url => Some(Color::Blue),
+        HighlightStyle::Uuid => Some(Color::Magenta),
+        _ => None,
+    }
+}
+
+impl HighlightTheme {
+    /// Build from `name -> color` pairs. Colors may be names (`red`,
+    /// `light-blue`), 256-color indices (`208`) or hex (`#b58900`).
+    pub fn from_names(names: &HashMap<String, String>) -> Result<Self> {
+        let mut colors = HashMap::new();
+        for (name, value) in names {
+            let style = THEMED_STYLES
+                .iter()
+                .find(|style| style.theme_key() == Some(name.as_str()))
+                .ok_or_else(|| anyhow!("Unknown theme entry '{}'", name))?;
+            let color = value
+                .parse::<Color>()
+                .map_err(|_| anyhow!("Invalid color '{}' for theme entry '{}'", value, name))?;
+            colors.insert(*style, color);
+        }
+        Ok(Self {
+            colors,
+            light: false,
+        })
+    }
+
+    /// Use the palette for a light terminal background (`--theme`). Colors from
+    /// the config file still win.
+    pub fn for_background(self, light: bool) -> Self {
+        Self { light, ..self }
+    }
+
+    pub fn style_for(&self, style: HighlightStyle) -> Style {
+        let mut base = style.default_ratatui_style();
+        if let Some(color) = light_background_color(style).filter(|_| self.light) {
+            base = base.fg(color);
+        }
+        match self.colors.get(&style) {
+            Some(&color) if style == HighlightStyle::CustomHighlight => base.bg(color),
+            Some(&color) => base.fg(color),
+            None => base,
         }
     }
 }
 
 #[derive(Clone)]
-struct HeuristicRule {
+pub struct HighlightRule {
     regex: Regex,
     style: HighlightStyle,
+    priority: u8,
 }
 
-static HEURISTIC_RULES: LazyLock<Vec<HeuristicRule>> = LazyLock::new(|| {
+/// Heuristic rules in the order they are listed and stored in `HeuristicRules`.
+pub const HEURISTIC_RULE_NAMES: [&str; 10] = [
+    "error",
+    "warn",
+    "info",
+    "debug",
+    "bracket",
+    "timestamp-long",
+    "timestamp-short",
+    "ip",
+    "url",
+    "uuid",
+];
+
+/// Which heuristic rules color lines, one bit per entry of `HEURISTIC_RULE_NAMES`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeuristicRules(u16);
+
+impl HeuristicRules {
+    pub const ALL: Self = Self((1 << HEURISTIC_RULE_NAMES.len()) - 1);
+    pub const NONE: Self = Self(0);
+
+    /// All rules except the named ones; unknown names are ignored.
+    pub fn without(disabled: &[String]) -> Self {
+        let mut rules = Self::ALL;
+        for (idx, name) in HEURISTIC_RULE_NAMES.iter().enumerate() {
+            if disabled.iter().any(|d| d == name) {
+                rules.0 &= !(1 << idx);
+            }
+        }
+        rules
+    }
+
+    /// Names of the rules that are off, for saving.
+    pub fn disabled(self) -> Vec<String> {
+        HEURISTIC_RULE_NAMES
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| !self.is_enabled(idx))
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
+    pub fn is_enabled(self, idx: usize) -> bool {
+        self.0 & (1 << idx) != 0
+    }
+
+    pub fn toggle(&mut self, idx: usize) {
+        self.0 ^= 1 << idx;
+    }
+}
+
+static RULES: OnceLock<Vec<HighlightRule>> = OnceLock::new();
+
+fn rules() -> &'static [HighlightRule] {
+    RULES.get_or_init(builtin_rules)
+}
+
+/// Priority of heuristic rules, below user rules, JSON and highlight expressions.
+const HEURISTIC_PRIORITY: u8 = 10;
+/// IP, URL and UUID rules sit just above the other heuristics so digits inside
+/// them are not recolored as timestamps.
+const TOKEN_PRIORITY: u8 = 12;
+/// Above heuristics, below user rules so those still show inside values.
+const LOGFMT_PRIORITY: u8 = 15;
+pub const DEFAULT_CUSTOM_RULE_PRIORITY: u8 = 20;
+
+/// A regex -> color rule from the config file.
+#[derive(Clone)]
+pub struct CustomRule {
+    regex: Regex,
+    style: Style,
+    priority: u8,
+}
+
+impl CustomRule {
+    /// Priorities must stay below the highlight expression (100) so user rules
+    /// never hide it or search matches; JSON values are at 50.
+    pub fn new(pattern: &str, color: &str, bold: bool, priority: u8) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| anyhow!("Invalid rule pattern '{}': {}", pattern, e))?;
+        let color = color
+            .parse::<Color>()
+            .map_err(|_| anyhow!("Invalid color '{}' for rule '{}'", color, pattern))?;
+        if !(1..100).contains(&priority) {
+            return Err(anyhow!(
+                "Priority {} for rule '{}' must be between 1 and 99",
+                priority,
+                pattern
+            ));
+        }
+        let mut style = Style::default().fg(color);
+        if bold {
+            style = style.add_modifier(ratatui::style::Modifier::BOLD);
+        }
+        Ok(Self {
+            regex,
+            style,
+            priority,
+        })
+    }
+}
+
+/// Chars of a line that are highlighted by default; see `HighlightConfig::max_chars`.
+pub const DEFAULT_MAX_HIGHLIGHT_CHARS: usize = 10_000;
+
+/// What the config file sets up highlighting with.
+pub struct HighlightConfig {
+    pub theme: HighlightTheme,
+    pub custom_rules: Vec<CustomRule>,
+    /// Chars of a line the highlighters look at, 0 for all of them.
+    pub max_chars: usize,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            theme: HighlightTheme::default(),
+            custom_rules: Vec::new(),
+            max_chars: DEFAULT_MAX_HIGHLIGHT_CHARS,
+        }
+    }
+}
+
+impl HighlightConfig {
+    pub fn style_for(&self, style: HighlightStyle) -> Style {
+        match style {
+            HighlightStyle::UserRule(idx) => self
+                .custom_rules
+                .get(idx)
+                .map(|rule| rule.style)
+                .unwrap_or_default(),
+            _ => self.theme.style_for(style),
+        }
+    }
+
+    /// Search matches cover the whole line, but the other highlighters only look at
+    /// its first `max_chars` chars, so huge lines like big JSON payloads stay cheap
+    /// to draw.
+    pub fn highlight_line(
+        &self,
+        text: &str,
+        custom_filter: Option<&FilterExpr>,
+        search: Option<&FilterExpr>,
+        heuristics: HeuristicRules,
+        json_enabled: bool,
+        logfmt_enabled: bool,
+    ) -> Vec<Span> {
+        let mut spans = Vec::new();
+
+        if let Some(search) = search {
+            for (start, end) in search.find_all_matches(text) {
+                spans.push(Span {
+                    start,
+                    end,
+                    style: HighlightStyle::SearchMatch,
+                    priority: 200,
+                });
+            }
+        }
+
+        let limit = if self.max_chars == 0 {
+            usize::MAX
+        } else {
+            self.max_chars
+        };
+        let text = text
+            .char_indices()
+            .nth(limit)
+            .map_or(text, |(end, _)| &text[..end]);
+
+        if let Some(filter) = custom_filter {
+            let matches = filter.find_all_matches(text);
+            for (start, end) in matches {
+                spans.push(Span {
+                    start,
+                    end,
+                    style: HighlightStyle::CustomHighlight,
+                    priority: 100,
+                });
+            }
+        }
+
+        if json_enabled {
+            if let Some(json_spans) = highlight_json(text) {
+                spans.extend(json_spans);
+            }
+        }
+
+        if logfmt_enabled {
+            if let Some(logfmt_spans) = highlight_logfmt(text) {
+                spans.extend(logfmt_spans);
+            }
+        }
+
+        push_custom_rule_spans(text, &self.custom_rules, &mut spans);
+
+        for (idx, rule) in rules().iter().enumerate() {
+            if heuristics.is_enabled(idx) {
+                for m in rule.regex.find_iter(text) {
+                    spans.push(Span {
+                        start: m.start(),
+                        end: m.end(),
+                        style: rule.style,
+                        priority: rule.priority,
+                    });
+                }
+            }
+        }
+
+        spans.sort_by(|a, b| a.start.cmp(&b.start).then(b.priority.cmp(&a.priority)));
+        spans
+    }
+
+    pub fn apply_highlights_ratatui(&self, text: &str, spans: &[Span]) -> Vec<(String, Style)> {
+        apply_highlights(text, spans)
+            .into_iter()
+            .map(|(s, style)| (s, self.style_for(style)))
+            .collect()
+    }
+}
+
+static CONFIG: OnceLock<RwLock<Arc<HighlightConfig>>> = OnceLock::new();
+
+/// Install the highlight setup from the config file, replacing the current one.
+pub fn set_config(config: HighlightConfig) {
+    *config_lock().write().unwrap() = Arc::new(config);
+}
+
+/// The installed highlight setup, the default one until `set_config` is called.
+pub fn current_config() -> Arc<HighlightConfig> {
+    config_lock().read().unwrap().clone()
+}
+
+fn config_lock() -> &'static RwLock<Arc<HighlightConfig>> {
+    CONFIG.get_or_init(RwLock::default)
+}
+
+/// In the order of `HEURISTIC_RULE_NAMES`.
+fn builtin_rules() -> Vec<HighlightRule> {
     vec![
-        HeuristicRule {
-            regex: Regex::new(r"(?i)\b(error|err|fatal|fail(ed)?|panic)\b").unwrap(),
+        HighlightRule {
+            regex: Regex::new(&level_pattern(&[Level::Error, Level::Fatal])).unwrap(),
             style: HighlightStyle::Error,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
-            regex: Regex::new(r"(?i)\b(warn(ing)?)\b").unwrap(),
+        HighlightRule {
+            regex: Regex::new(&level_pattern(&[Level::Warn])).unwrap(),
             style: HighlightStyle::Warning,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
-            regex: Regex::new(r"(?i)\b(info)\b").unwrap(),
+        HighlightRule {
+            regex: Regex::new(&level_pattern(&[Level::Info])).unwrap(),
             style: HighlightStyle::Info,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
-            regex: Regex::new(r"(?i)\b(debug|trace)\b").unwrap(),
+        HighlightRule {
+            regex: Regex::new(&level_pattern(&[Level::Trace, Level::Debug])).unwrap(),
             style: HighlightStyle::Debug,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
+        HighlightRule {
             regex: Regex::new(r"\[[^\]]+\]").unwrap(),
             style: HighlightStyle::Bracket,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
+        HighlightRule {
             regex: Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}").unwrap(),
             style: HighlightStyle::Timestamp,
+            priority: HEURISTIC_PRIORITY,
         },
-        HeuristicRule {
+        HighlightRule {
             regex: Regex::new(r"\d{2}:\d{2}:\d{2}").unwrap(),
             style: HighlightStyle::Timestamp,
+            priority: HEURISTIC_PRIORITY,
+        },
+        HighlightRule {
+            // IPv4 with an optional port; IPv6 in full or with one `::`.
+            regex: Regex::new(concat!(
+                r"(?i)\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)(?::\d{1,5})?\b",
+                r"|\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b",
+                r"|\b(?:[0-9a-f]{1,4}:){1,6}:[0-9a-f]{1,4}(?::[0-9a-f]{1,4}){0,5}\b",
+            ))
+            .unwrap(),
+            style: HighlightStyle::IpAddress,
+            priority: TOKEN_PRIORITY,
+        },
+        HighlightRule {
+            // Trailing punctuation is left out so `see http://x/y.` stops at `y`.
+            regex: Regex::new(r#"\bhttps?://[^\s"'<>()\[\]{}]*[^\s"'<>()\[\]{}.,;:!?]"#).unwrap(),
+            style: HighlightStyle::Url,
+            priority: TOKEN_PRIORITY,
+        },
+        HighlightRule {
+            regex: Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap(),
+            style: HighlightStyle::Uuid,
+            priority: TOKEN_PRIORITY,
         },
     ]
-});
+}
+
+/// The highlight style of the line's level, see `core::detect_level`.
+pub fn detect_level(text: &str) -> Option<HighlightStyle> {
+    level::detect_level(text).map(Level::style)
+}
+
+/// Whether any part of `text` hits the heuristic error rule.
+pub fn is_error_line(text: &str) -> bool {
+    rules()
+        .iter()
+        .any(|rule| rule.style == HighlightStyle::Error && rule.regex.is_match(text))
+}
 
+pub fn level_letter(level: Option<HighlightStyle>) -> char {
+    match level {
+        Some(HighlightStyle::Error) => 'E',
+        Some(HighlightStyle::Warning) => 'W',
+        Some(HighlightStyle::Info) => 'I',
+        Some(HighlightStyle::Debug) => 'D',
+        _ => ' ',
+    }
+}
+
+/// A style over the bytes `start..end`, as regex matches report them. Where
+/// spans overlap, the one with the higher `priority` wins.
 #[derive(Clone)]
 pub struct Span {
     pub start: usize,
@@ -106,49 +550,42 @@ pub struct Span {
     pub priority: u8,
 }
 
+/// Highlight with the installed `HighlightConfig`; see `HighlightConfig::highlight_line`.
 pub fn highlight_line(
     text: &str,
     custom_filter: Option<&FilterExpr>,
-    heuristic_enabled: bool,
+    search: Option<&FilterExpr>,
+    heuristics: HeuristicRules,
     json_enabled: bool,
+    logfmt_enabled: bool,
 ) -> Vec<Span> {
-    let mut spans = Vec::new();
+    current_config().highlight_line(
+        text,
+        custom_filter,
+        search,
+        heuristics,
+        json_enabled,
+        logfmt_enabled,
+    )
+}
 
-    if let Some(filter) = custom_filter {
-        let matches = filter.find_all_matches(text);
-        for (start, end) in matches {
+fn push_custom_rule_spans(text: &str, rules: &[CustomRule], spans: &mut Vec<Span>) {
+    for (idx, rule) in rules.iter().enumerate() {
+        for m in rule.regex.find_iter(text) {
             spans.push(Span {
-                start,
-                end,
-                style: HighlightStyle::CustomHighlight,
-                priority: 100,
+                start: m.start(),
+                end: m.end(),
+                style: HighlightStyle::UserRule(idx),
+                priority: rule.priority,
             });
         }
     }
+}
 
-    if json_enabled {
-        if let Some(json_spans) = highlight_json(text) {
-            spans.extend(json_spans);
-        }
-    }
-
-    if heuristic_enabled {
-        for rule in HEURISTIC_RULES.iter() {
-            for m in rule.regex.find_iter(text) {
-                spans.push(Span {
-                    start: m.start(),
-                    end: m.end(),
-                    style: rule.style,
-                    priority: 10,
-                });
-            }
-        }
-    }
-
-    spans.sort_by(|a, b| {
-        a.start.cmp(&b.start).then(b.priority.cmp(&a.priority))
-    });
-    spans
+thread_local! {
+    /// Per-byte style and priority for `apply_highlights`, kept between calls
+    /// so long lines don't allocate a fresh buffer each time.
+    static STYLE_AT: RefCell<Vec<(HighlightStyle, u8)>> = const { RefCell::new(Vec::new()) };
 }
 
 pub fn apply_highlights(text: &str, spans: &[Span]) -> Vec<(String, HighlightStyle)> {
@@ -156,50 +593,38 @@ pub fn apply_highlights(text: &str, spans: &[Span]) -> Vec<(String, HighlightSty
         return vec![(text.to_string(), HighlightStyle::None)];
     }
 
-    let mut style_at: Vec<(HighlightStyle, u8)> = vec![(HighlightStyle::None, 0); text.len()];
-    
-    for span in spans {
-        let start = char_to_byte_pos(text, span.start);
-        let end = char_to_byte_pos(text, span.end).min(text.len());
-        
-        for i in start..end {
-            if span.priority >= style_at[i].1 {
-                style_at[i] = (span.style, span.priority);
+    STYLE_AT.with_borrow_mut(|style_at| {
+        style_at.clear();
+        style_at.resize(text.len(), (HighlightStyle::None, 0));
+
+        for span in spans {
+            let start = span.start.min(text.len());
+            let end = span.end.min(text.len());
+
+            for slot in &mut style_at[start..end.max(start)] {
+                if span.priority >= slot.1 {
+                    *slot = (span.style, span.priority);
+                }
             }
         }
-    }
 
-    let mut result = Vec::new();
-    let mut pos = 0;
-    
-    while pos < text.len() {
-        let current_style = style_at[pos].0;
-        let mut end = pos + 1;
-        
-        while end < text.len() && style_at[end].0 == current_style {
-            end += 1;
-        }
-        
-        result.push((text[pos..end].to_string(), current_style));
-        pos = end;
-    }
+        let mut result = Vec::new();
+        let mut pos = 0;
 
-    result
-}
+        while pos < text.len() {
+            let current_style = style_at[pos].0;
+            let mut end = pos + 1;
 
-pub fn apply_highlights_ratatui(text: &str, spans: &[Span]) -> Vec<(String, ratatui::style::Style)> {
-    apply_highlights(text, spans)
-        .into_iter()
-        .map(|(s, style)| (s, style.to_ratatui_style()))
-        .collect()
-}
+            while end < text.len() && style_at[end].0 == current_style {
+                end += 1;
+            }
 
-fn char_to_byte_pos(text: &str, char_pos: usize) -> usize {
-    text.char_indices()
-        .nth(char_pos)
-        .map(|(i, _)| i)
-        .unwrap_or(text.len())
-        .min(text.len())
+            result.push((text[pos..end].to_string(), current_style));
+            pos = end;
+        }
+
+        result
+    })
 }
 
 fn highlight_json(text: &str) -> Option<Vec<Span>> {
@@ -207,7 +632,7 @@ fn highlight_json(text: &str) -> Option<Vec<Span>> {
     if json_objects.is_empty() {
         return None;
     }
-    
+
     let mut spans = Vec::new();
     for (json_start, value, json_end) in json_objects {
         let json_str = &text[json_start..json_start + json_end];
@@ -216,17 +641,122 @@ fn highlight_json(text: &str) -> Option<Vec<Span>> {
     Some(spans)
 }
 
+/// Spans for `key=value` pairs, where values are bare words or double-quoted
+/// strings that may contain spaces, `=` and `\"`.
+pub fn highlight_logfmt(text: &str) -> Option<Vec<Span>> {
+    let (offsets, chars): (Vec<usize>, Vec<char>) = text.char_indices().unzip();
+    let byte_pos = |char_pos: usize| offsets.get(char_pos).copied().unwrap_or(text.len());
+    let is_key_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '@');
+    let mut spans = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        if chars[pos].is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let key_start = pos;
+        while pos < chars.len() && is_key_char(chars[pos]) {
+            pos += 1;
+        }
+        if pos == key_start || chars.get(pos) != Some(&'=') {
+            // Not a pair: skip the rest of this word.
+            while pos < chars.len() && !chars[pos].is_whitespace() {
+                pos += 1;
+            }
+            continue;
+        }
+        let key_end = pos;
+        pos += 1;
+        let value_start = pos;
+        if chars.get(pos) == Some(&'"') {
+            pos += 1;
+            while pos < chars.len() && chars[pos] != '"' {
+                pos += if chars[pos] == '\\' { 2 } else { 1 };
+            }
+            pos = (pos + 1).min(chars.len());
+        } else {
+            while pos < chars.len() && !chars[pos].is_whitespace() {
+                pos += 1;
+            }
+        }
+        spans.push(Span {
+            start: byte_pos(key_start),
+            end: byte_pos(key_end),
+            style: HighlightStyle::LogfmtKey,
+            priority: LOGFMT_PRIORITY,
+        });
+        if pos > value_start {
+            spans.push(Span {
+                start: byte_pos(value_start),
+                end: byte_pos(pos),
+                style: HighlightStyle::LogfmtValue,
+                priority: LOGFMT_PRIORITY,
+            });
+        }
+    }
+
+    (!spans.is_empty()).then_some(spans)
+}
+
+/// Each JSON value in `text` pretty-printed with 2-space indents, one after
+/// another with a blank row between, split into colored rows.
+pub fn pretty_json_rows(text: &str) -> Vec<Vec<(String, HighlightStyle)>> {
+    let mut rows = Vec::new();
+    for (_, value, _) in find_all_json(text) {
+        let Ok(pretty) = serde_json::to_string_pretty(&value) else {
+            continue;
+        };
+        if !rows.is_empty() {
+            rows.push(Vec::new());
+        }
+        let mut spans = Vec::new();
+        highlight_json_value(&pretty, &value, 0, &mut spans);
+        let mut row = Vec::new();
+        for (run, style) in apply_highlights(&pretty, &spans) {
+            for (i, part) in run.split('\n').enumerate() {
+                if i > 0 {
+                    rows.push(std::mem::take(&mut row));
+                }
+                if !part.is_empty() {
+                    row.push((part.to_string(), style));
+                }
+            }
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+pub fn contains_json(text: &str) -> bool {
+    !find_all_json(text).is_empty()
+}
+
+/// The first JSON object embedded in `text`.
+pub fn first_json_object(text: &str) -> Option<Value> {
+    let mut search_start = 0;
+    while let Some(pos) = text[search_start..].find('{') {
+        let abs_pos = search_start + pos;
+        let mut stream = serde_json::Deserializer::from_str(&text[abs_pos..]).into_iter::<Value>();
+        if let Some(Ok(value @ Value::Object(_))) = stream.next() {
+            return Some(value);
+        }
+        search_start = abs_pos + 1;
+    }
+    None
+}
+
 fn find_all_json(text: &str) -> Vec<(usize, Value, usize)> {
     let mut results = Vec::new();
     let mut search_start = 0;
-    
+
     while let Some(pos) = text[search_start..].find(|c| c == '{' || c == '[') {
         let abs_pos = search_start + pos;
         let json_str = &text[abs_pos..];
-        
+
         let bytes = json_str.as_bytes();
         let mut stream = serde_json::Deserializer::from_slice(bytes).into_iter::<Value>();
-        
+
         if let Some(Ok(value)) = stream.next() {
             let end = stream.byte_offset();
             if end > 1 {
@@ -240,92 +770,369 @@ fn find_all_json(text: &str) -> Vec<(usize, Value, usize)> {
     results
 }
 
+/// Spans for the tokens of `value`, which was parsed from `text`. The value is
+/// walked in document order (serde_json's `preserve_order`) with a cursor into
+/// `text`, so each token is found once, at its real offset.
 fn highlight_json_value(text: &str, value: &Value, base_offset: usize, spans: &mut Vec<Span>) {
-    match value {
+    let mut cursor = 0;
+    highlight_json_token(text.as_bytes(), value, &mut cursor, base_offset, spans);
+}
+
+fn highlight_json_token(
+    bytes: &[u8],
+    value: &Value,
+    cursor: &mut usize,
+    base_offset: usize,
+    spans: &mut Vec<Span>,
+) {
+    skip_json_separators(bytes, cursor);
+    let start = *cursor;
+    let style = match value {
         Value::Object(map) => {
-            for (key, val) in map {
-                if let Some(key_pos) = find_json_key(text, key) {
-                    spans.push(Span {
-                        start: base_offset + key_pos,
-                        end: base_offset + key_pos + key.len() + 2,
-                        style: HighlightStyle::JsonKey,
-                        priority: 50,
-                    });
-                }
-                highlight_json_value(text, val, base_offset, spans);
+            *cursor += 1;
+            for val in map.values() {
+                skip_json_separators(bytes, cursor);
+                let key_start = *cursor;
+                *cursor = json_string_end(bytes, key_start);
+                spans.push(json_span(
+                    key_start,
+                    *cursor,
+                    base_offset,
+                    HighlightStyle::JsonKey,
+                ));
+                highlight_json_token(bytes, val, cursor, base_offset, spans);
             }
+            skip_json_separators(bytes, cursor);
+            *cursor += 1;
+            return;
         }
         Value::Array(arr) => {
+            *cursor += 1;
             for val in arr {
-                highlight_json_value(text, val, base_offset, spans);
+                highlight_json_token(bytes, val, cursor, base_offset, spans);
             }
+            skip_json_separators(bytes, cursor);
+            *cursor += 1;
+            return;
         }
-        Value::String(s) => {
-            if let Some(pos) = find_json_string(text, s) {
-                spans.push(Span {
-                    start: base_offset + pos,
-                    end: base_offset + pos + s.len() + 2,
-                    style: HighlightStyle::JsonString,
-                    priority: 50,
-                });
-            }
+        Value::String(_) => {
+            *cursor = json_string_end(bytes, start);
+            HighlightStyle::JsonString
         }
-        Value::Number(n) => {
-            let n_str = n.to_string();
-            if let Some(pos) = text.find(&n_str) {
-                spans.push(Span {
-                    start: base_offset + pos,
-                    end: base_offset + pos + n_str.len(),
-                    style: HighlightStyle::JsonNumber,
-                    priority: 50,
-                });
+        Value::Number(_) => {
+            while *cursor < bytes.len()
+                && matches!(
+                    bytes[*cursor],
+                    b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'
+                )
+            {
+                *cursor += 1;
             }
+            HighlightStyle::JsonNumber
         }
         Value::Bool(b) => {
-            let b_str = if *b { "true" } else { "false" };
-            if let Some(pos) = text.find(b_str) {
-                spans.push(Span {
-                    start: base_offset + pos,
-                    end: base_offset + pos + b_str.len(),
-                    style: HighlightStyle::JsonBool,
-                    priority: 50,
-                });
-            }
+            *cursor += if *b { 4 } else { 5 };
+            HighlightStyle::JsonBool
         }
         Value::Null => {
-            if let Some(pos) = text.find("null") {
-                spans.push(Span {
-                    start: base_offset + pos,
-                    end: base_offset + pos + 4,
-                    style: HighlightStyle::JsonNull,
-                    priority: 50,
-                });
-            }
+            *cursor += 4;
+            HighlightStyle::JsonNull
         }
+    };
+    *cursor = (*cursor).min(bytes.len());
+    spans.push(json_span(start, *cursor, base_offset, style));
+}
+
+fn json_span(start: usize, end: usize, base_offset: usize, style: HighlightStyle) -> Span {
+    Span {
+        start: base_offset + start,
+        end: base_offset + end,
+        style,
+        priority: 50,
     }
 }
 
-fn find_json_key(text: &str, key: &str) -> Option<usize> {
-    let pattern = format!("\"{}\"", key);
-    let pos = text.find(&pattern)?;
-    let after = &text[pos + pattern.len()..];
-    if after.trim_start().starts_with(':') {
-        Some(pos)
-    } else {
-        None
+/// Move past whitespace, `,` and `:` between JSON tokens.
+fn skip_json_separators(bytes: &[u8], cursor: &mut usize) {
+    while *cursor < bytes.len()
+        && matches!(bytes[*cursor], b' ' | b'\t' | b'\n' | b'\r' | b',' | b':')
+    {
+        *cursor += 1;
     }
 }
 
-fn find_json_string(text: &str, s: &str) -> Option<usize> {
-    let pattern = format!("\"{}\"", s);
-    let mut search_start = 0;
-    while let Some(pos) = text[search_start..].find(&pattern) {
-        let abs_pos = search_start + pos;
-        let after = &text[abs_pos + pattern.len()..];
-        if !after.trim_start().starts_with(':') {
-            return Some(abs_pos);
+/// End of the quoted string starting at `start`, just past its closing quote.
+fn json_string_end(bytes: &[u8], start: usize) -> usize {
+    let mut pos = start + 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 2,
+            b'"' => return pos + 1,
+            _ => pos += 1,
         }
-        search_start = abs_pos + 1;
     }
-    None
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_theme_overrides_colors() {
+        let theme = HighlightTheme::from_names(&names(&[
+            ("warn", "#b58900"),
+            ("json_key", "208"),
+            ("custom", "light-blue"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            theme.style_for(HighlightStyle::Warning).fg,
+            Some(Color::Rgb(0xb5, 0x89, 0x00))
+        );
+        assert_eq!(
+            theme.style_for(HighlightStyle::JsonKey).fg,
+            Some(Color::Indexed(208))
+        );
+        assert_eq!(
+            theme.style_for(HighlightStyle::CustomHighlight).bg,
+            Some(Color::LightBlue)
+        );
+        assert_eq!(
+            theme.style_for(HighlightStyle::Error),
+            HighlightStyle::Error.default_ratatui_style()
+        );
+    }
+
+    #[test]
+    fn test_light_background_palette() {
+        let theme = HighlightTheme::from_names(&names(&[("debug", "blue")]))
+            .unwrap()
+            .for_background(true);
+        assert_eq!(
+            theme.style_for(HighlightStyle::Warning).fg,
+            Some(Color::Rgb(175, 95, 0))
+        );
+        assert_eq!(
+            theme.style_for(HighlightStyle::Debug).fg,
+            Some(Color::Blue),
+            "config colors win"
+        );
+        assert_eq!(
+            theme.style_for(HighlightStyle::Error),
+            HighlightStyle::Error.default_ratatui_style()
+        );
+    }
+
+    #[test]
+    fn test_theme_rejects_unknown_entries() {
+        assert!(HighlightTheme::from_names(&names(&[("warning", "red")])).is_err());
+        assert!(HighlightTheme::from_names(&names(&[("warn", "not-a-color")])).is_err());
+    }
+
+    #[test]
+    fn test_custom_rules_rank_between_heuristics_and_highlights() {
+        let rules = vec![
+            CustomRule::new(r"GET|POST", "cyan", false, DEFAULT_CUSTOM_RULE_PRIORITY).unwrap(),
+            CustomRule::new(r"trace=\w+", "#ff00ff", true, 60).unwrap(),
+        ];
+        let text = "GET /x ERROR trace=abc";
+        let mut spans = Vec::new();
+        push_custom_rule_spans(text, &rules, &mut spans);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            (spans[0].start, spans[0].end, spans[0].style),
+            (0, 3, HighlightStyle::UserRule(0))
+        );
+        assert_eq!(spans[1].priority, 60);
+
+        // The highlight expression still wins over an overlapping user rule.
+        spans.push(Span {
+            start: 0,
+            end: 1,
+            style: HighlightStyle::CustomHighlight,
+            priority: 100,
+        });
+        let parts = apply_highlights(text, &spans);
+        assert_eq!(parts[0], ("G".to_string(), HighlightStyle::CustomHighlight));
+        assert_eq!(parts[1], ("ET".to_string(), HighlightStyle::UserRule(0)));
+    }
+
+    #[test]
+    fn test_long_lines_are_highlighted_up_to_the_limit() {
+        let padding = "x".repeat(DEFAULT_MAX_HIGHLIGHT_CHARS);
+        let text = format!("ERROR {} ERROR", padding);
+        let search = crate::filter::parse_filter("ERROR").unwrap();
+        let styles = |search: Option<&FilterExpr>| -> Vec<HighlightStyle> {
+            let spans = highlight_line(&text, None, search, HeuristicRules::ALL, true, false);
+            apply_highlights(&text, &spans)
+                .into_iter()
+                .filter(|(part, _)| part == "ERROR")
+                .map(|(_, style)| style)
+                .collect()
+        };
+        assert_eq!(styles(None), vec![HighlightStyle::Error]);
+        assert_eq!(
+            styles(Some(&search)),
+            vec![HighlightStyle::SearchMatch, HighlightStyle::SearchMatch]
+        );
+    }
+
+    #[test]
+    fn test_custom_rule_validation() {
+        assert!(CustomRule::new("(unclosed", "red", false, 20).is_err());
+        assert!(CustomRule::new("ok", "nope", false, 20).is_err());
+        assert!(CustomRule::new("ok", "red", false, 100).is_err());
+        let rule = CustomRule::new("ok", "208", true, 20).unwrap();
+        assert_eq!(rule.style.fg, Some(Color::Indexed(208)));
+    }
+
+    #[test]
+    fn test_ip_url_uuid_heuristics() {
+        let text = "12:00:01 GET https://example.com/a?b=1. from 10.0.0.12:8080 and 2001:0db8:0000:0000:0000:ff00:0042:8329 \
+                    fe80::1 id=123e4567-e89b-12d3-a456-426614174000 v1.2.3 std::fs 999.1.1.1";
+        let spans = highlight_line(text, None, None, HeuristicRules::ALL, false, false);
+        let parts: Vec<_> = apply_highlights(text, &spans)
+            .into_iter()
+            .filter(|(_, style)| *style != HighlightStyle::None)
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("12:00:01".to_string(), HighlightStyle::Timestamp),
+                ("https://example.com/a?b=1".to_string(), HighlightStyle::Url),
+                ("10.0.0.12:8080".to_string(), HighlightStyle::IpAddress),
+                (
+                    "2001:0db8:0000:0000:0000:ff00:0042:8329".to_string(),
+                    HighlightStyle::IpAddress
+                ),
+                ("fe80::1".to_string(), HighlightStyle::IpAddress),
+                (
+                    "123e4567-e89b-12d3-a456-426614174000".to_string(),
+                    HighlightStyle::Uuid
+                ),
+            ]
+        );
+
+        let without_urls = HeuristicRules::without(&["url".to_string()]);
+        let spans = highlight_line("see http://x", None, None, without_urls, false, false);
+        assert!(spans.is_empty());
+    }
+
+    fn logfmt_parts(text: &str) -> Vec<(String, HighlightStyle)> {
+        apply_highlights(text, &highlight_logfmt(text).unwrap_or_default())
+            .into_iter()
+            .filter(|(_, style)| *style != HighlightStyle::None)
+            .collect()
+    }
+
+    #[test]
+    fn test_logfmt_pairs() {
+        let parts = logfmt_parts(r#"level=error msg="thing failed: a=b \"x\"" dur=12ms ünï=ok"#);
+        let expected = [
+            ("level", HighlightStyle::LogfmtKey),
+            ("error", HighlightStyle::LogfmtValue),
+            ("msg", HighlightStyle::LogfmtKey),
+            (r#""thing failed: a=b \"x\"""#, HighlightStyle::LogfmtValue),
+            ("dur", HighlightStyle::LogfmtKey),
+            ("12ms", HighlightStyle::LogfmtValue),
+            ("ünï", HighlightStyle::LogfmtKey),
+            ("ok", HighlightStyle::LogfmtValue),
+        ];
+        let expected: Vec<(String, HighlightStyle)> = expected
+            .iter()
+            .map(|(text, style)| (text.to_string(), *style))
+            .collect();
+        assert_eq!(parts, expected);
+    }
+
+    #[test]
+    fn test_spans_are_byte_offsets_on_non_ascii_text() {
+        let expr = crate::filter::parse_filter("timeout").unwrap();
+        let text = "ERROR größe überschritten: timeout {\"ß\":1}";
+        let spans = highlight_line(text, Some(&expr), None, HeuristicRules::ALL, true, false);
+        let runs: Vec<(String, HighlightStyle)> = apply_highlights(text, &spans)
+            .into_iter()
+            .filter(|(_, style)| *style != HighlightStyle::None)
+            .collect();
+        let expected = [
+            ("ERROR", HighlightStyle::Error),
+            ("timeout", HighlightStyle::CustomHighlight),
+            ("\"ß\"", HighlightStyle::JsonKey),
+            ("1", HighlightStyle::JsonNumber),
+        ];
+        let expected: Vec<(String, HighlightStyle)> = expected
+            .iter()
+            .map(|(text, style)| (text.to_string(), *style))
+            .collect();
+        assert_eq!(runs, expected);
+    }
+
+    #[test]
+    fn test_logfmt_ignores_plain_text() {
+        assert!(highlight_logfmt("GET /index.html 200 =x").is_none());
+        assert!(highlight_logfmt("unterminated=\"open value").is_some());
+    }
+
+    #[test]
+    fn test_json_repeated_values_are_positioned() {
+        let text = r#"x {"z":1,"b":1,"t":"true","u":true,"s":"a\"b","n":[null,-2.5e3]}"#;
+        let spans = highlight_json(text).unwrap();
+        let found: Vec<(&str, HighlightStyle)> = spans
+            .iter()
+            .map(|span| (&text[span.start..span.end], span.style))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("\"z\"", HighlightStyle::JsonKey),
+                ("1", HighlightStyle::JsonNumber),
+                ("\"b\"", HighlightStyle::JsonKey),
+                ("1", HighlightStyle::JsonNumber),
+                ("\"t\"", HighlightStyle::JsonKey),
+                ("\"true\"", HighlightStyle::JsonString),
+                ("\"u\"", HighlightStyle::JsonKey),
+                ("true", HighlightStyle::JsonBool),
+                ("\"s\"", HighlightStyle::JsonKey),
+                (r#""a\"b""#, HighlightStyle::JsonString),
+                ("\"n\"", HighlightStyle::JsonKey),
+                ("null", HighlightStyle::JsonNull),
+                ("-2.5e3", HighlightStyle::JsonNumber),
+            ]
+        );
+        assert!(spans.windows(2).all(|pair| pair[0].end <= pair[1].start));
+    }
+
+    #[test]
+    fn test_pretty_json_rows() {
+        let rows = pretty_json_rows(r#"req {"user":{"id":7}} resp [true]"#);
+        let text: Vec<String> = rows
+            .iter()
+            .map(|row| row.iter().map(|(part, _)| part.as_str()).collect())
+            .collect();
+        assert_eq!(
+            text,
+            vec![
+                "{",
+                "  \"user\": {",
+                "    \"id\": 7",
+                "  }",
+                "}",
+                "",
+                "[",
+                "  true",
+                "]"
+            ]
+        );
+        assert!(rows[1].contains(&("\"user\"".to_string(), HighlightStyle::JsonKey)));
+        assert!(rows[2].contains(&("7".to_string(), HighlightStyle::JsonNumber)));
+        assert!(pretty_json_rows("no json here").is_empty());
+    }
 }
@@ -15,12 +15,29 @@ impl TextInput {
         }
     }
 
+    /// Control characters such as `\n` or `\t` become spaces so the field stays
+    /// on one line and the cursor column matches the text.
     pub fn insert_char(&mut self, c: char) {
+        let c = if c.is_control() { ' ' } else { c };
         let byte_idx = self.char_to_byte_index(self.cursor);
         self.text.insert(byte_idx, c);
         self.cursor += 1;
     }
 
+    /// Insert pasted text at the cursor. Line breaks are flattened to single
+    /// spaces and a trailing one is dropped.
+    pub fn insert_str(&mut self, s: &str) {
+        let flat: String = s
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", "\n")
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let byte_idx = self.char_to_byte_index(self.cursor);
+        self.text.insert_str(byte_idx, &flat);
+        self.cursor += flat.chars().count();
+    }
+
     pub fn delete_char_before_cursor(&mut self) {
         if self.cursor > 0 {
             let byte_idx = self.char_to_byte_index(self.cursor - 1);
@@ -48,6 +65,35 @@ impl TextInput {
         }
     }
 
+    /// Move to the start of the word before the cursor. Words are runs of
+    /// letters, digits and `_`; anything else separates them.
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_start_before_cursor();
+    }
+
+    /// Move to the end of the word after the cursor.
+    pub fn move_word_right(&mut self) {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut idx = self.cursor;
+        while idx < chars.len() && !is_word_char(chars[idx]) {
+            idx += 1;
+        }
+        while idx < chars.len() && is_word_char(chars[idx]) {
+            idx += 1;
+        }
+        self.cursor = idx;
+    }
+
+    /// Delete back to where `move_word_left` would go.
+    pub fn delete_word_before(&mut self) {
+        let start = self.word_start_before_cursor();
+        self.delete_before_cursor_from(start);
+    }
+
+    pub fn delete_to_start(&mut self) {
+        self.delete_before_cursor_from(0);
+    }
+
     pub fn move_cursor_to_start(&mut self) {
         self.cursor = 0;
     }
@@ -72,6 +118,24 @@ impl TextInput {
         self.error.is_some()
     }
 
+    fn word_start_before_cursor(&self) -> usize {
+        let chars: Vec<char> = self.text.chars().take(self.cursor).collect();
+        let mut idx = chars.len();
+        while idx > 0 && !is_word_char(chars[idx - 1]) {
+            idx -= 1;
+        }
+        while idx > 0 && is_word_char(chars[idx - 1]) {
+            idx -= 1;
+        }
+        idx
+    }
+
+    fn delete_before_cursor_from(&mut self, start: usize) {
+        let range = self.char_to_byte_index(start)..self.char_to_byte_index(self.cursor);
+        self.text.replace_range(range, "");
+        self.cursor = start;
+    }
+
     fn char_to_byte_index(&self, char_idx: usize) -> usize {
         self.text
             .char_indices()
@@ -80,3 +144,68 @@ impl TextInput {
             .unwrap_or(self.text.len())
     }
 }
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_flattens_newlines() {
+        let mut input = TextInput::new("ab".to_string());
+        input.move_cursor_left();
+        input.insert_char('\n');
+        assert_eq!(input.text, "a b");
+        assert_eq!(input.cursor, 2);
+
+        input.insert_str("x\r\ny\tz\n");
+        assert_eq!(input.text, "a x y zb");
+        assert_eq!(input.cursor, 7);
+    }
+
+    #[test]
+    fn test_insert_str_multibyte() {
+        let mut input = TextInput::new("日本".to_string());
+        input.move_cursor_left();
+        input.insert_str("é\n語");
+        assert_eq!(input.text, "日é 語本");
+        assert_eq!(input.cursor, 4);
+        input.delete_char_before_cursor();
+        assert_eq!(input.text, "日é 本");
+        input.insert_char('ü');
+        assert_eq!(input.text, "日é ü本");
+    }
+
+    #[test]
+    fn test_word_motion_multibyte() {
+        let mut input = TextInput::new("grüße && !日本_語".to_string());
+        input.move_word_left();
+        assert_eq!(input.cursor, 10);
+        input.move_word_left();
+        assert_eq!(input.cursor, 0);
+        input.move_word_right();
+        assert_eq!(input.cursor, 5);
+        input.move_word_right();
+        assert_eq!(input.cursor, 14);
+        input.move_word_right();
+        assert_eq!(input.cursor, 14);
+
+        input.delete_word_before();
+        assert_eq!(input.text, "grüße && !");
+        assert_eq!(input.cursor, 10);
+        input.delete_word_before();
+        assert_eq!(input.text, "");
+        assert_eq!(input.cursor, 0);
+        input.delete_word_before();
+        assert_eq!(input.text, "");
+
+        let mut input = TextInput::new("héllo wörld".to_string());
+        input.cursor = 7;
+        input.delete_to_start();
+        assert_eq!(input.text, "örld");
+        assert_eq!(input.cursor, 0);
+    }
+}
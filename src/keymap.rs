@@ -0,0 +1,497 @@
+use crate::core::NavCommand;
+use crossterm::event::KeyCode;
+
+/// A key with or without Ctrl or Alt held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+const fn key(c: char) -> Key {
+    special(KeyCode::Char(c))
+}
+
+const fn ctrl(c: char) -> Key {
+    ctrl_special(KeyCode::Char(c))
+}
+
+const fn alt(c: char) -> Key {
+    Key {
+        code: KeyCode::Char(c),
+        ctrl: false,
+        alt: true,
+    }
+}
+
+const fn special(code: KeyCode) -> Key {
+    Key {
+        code,
+        ctrl: false,
+        alt: false,
+    }
+}
+
+const fn ctrl_special(code: KeyCode) -> Key {
+    Key {
+        code,
+        ctrl: true,
+        alt: false,
+    }
+}
+
+impl Key {
+    /// How the key is written in help, e.g. `Ctrl-d`, `Alt-b`, `PgUp` or `Space`.
+    pub fn label(self) -> String {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        if self.ctrl {
+            format!("Ctrl-{}", name)
+        } else if self.alt {
+            format!("Alt-{}", name)
+        } else {
+            name
+        }
+    }
+}
+
+pub struct Binding<A: 'static> {
+    pub keys: &'static [Key],
+    pub action: A,
+    pub help: &'static str,
+}
+
+impl<A> Binding<A> {
+    /// All of the binding's keys, e.g. `g Home`.
+    pub fn keys_label(&self) -> String {
+        self.keys
+            .iter()
+            .map(|key| key.label())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+const fn bind<A>(keys: &'static [Key], action: A, help: &'static str) -> Binding<A> {
+    Binding { keys, action, help }
+}
+
+pub struct Section<A: 'static> {
+    pub title: &'static str,
+    pub bindings: &'static [Binding<A>],
+}
+
+/// What a key does in normal mode. `handle_normal_mode` dispatches on these,
+/// so the help lists exactly the keys that work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Nav(NavCommand),
+    HalfPageUp,
+    HalfPageDown,
+    PanLeft,
+    PanRight,
+    SelectUp,
+    SelectDown,
+    VisualMode,
+    TogglePause,
+    ToggleFollow,
+    EditSearch,
+    SearchNext,
+    SearchPrev,
+    ClearSearch,
+    GotoLine,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+    EditHide,
+    EditFilter,
+    EditHighlight,
+    EditLineStart,
+    EditExtract,
+    InvertFilter,
+    CycleSource,
+    ToggleFilterAfterHide,
+    LessContext,
+    MoreContext,
+    ToggleCaseInsensitive,
+    ToggleSmartCase,
+    ToggleLiteral,
+    ToggleWholeWord,
+    ToggleTime,
+    ToggleCompactGutter,
+    ToggleLineNumbers,
+    ToggleWrap,
+    ToggleDedup,
+    ToggleAlignColumns,
+    ToggleJson,
+    ToggleLogfmt,
+    ToggleHeuristic,
+    HeuristicRules,
+    ToggleAnsi,
+    OpenJson,
+    CopyLine,
+    CopyVisible,
+    CopyMatches,
+    OpenProfiles,
+    SaveProfile,
+    Clear,
+    ClearAbove,
+    ClearBelow,
+    ReloadState,
+    ShowErrors,
+    Diagnostics,
+    Help,
+    Quit,
+}
+
+pub const NORMAL_KEYMAP: &[Section<Action>] = &[
+    Section {
+        title: "Scrolling",
+        bindings: &[
+            bind(
+                &[special(KeyCode::Up), ctrl('y')],
+                Action::Nav(NavCommand::LineUp),
+                "Scroll up a line",
+            ),
+            bind(
+                &[special(KeyCode::Down), ctrl('e')],
+                Action::Nav(NavCommand::LineDown),
+                "Scroll down a line",
+            ),
+            bind(&[ctrl('u')], Action::HalfPageUp, "Scroll up half a page"),
+            bind(
+                &[ctrl('d')],
+                Action::HalfPageDown,
+                "Scroll down half a page",
+            ),
+            bind(
+                &[special(KeyCode::PageUp)],
+                Action::Nav(NavCommand::PageUp),
+                "Scroll up a page",
+            ),
+            bind(
+                &[special(KeyCode::PageDown)],
+                Action::Nav(NavCommand::PageDown),
+                "Scroll down a page",
+            ),
+            bind(
+                &[key('g'), special(KeyCode::Home)],
+                Action::Nav(NavCommand::Top),
+                "Jump to the first line",
+            ),
+            bind(
+                &[key('G'), special(KeyCode::End)],
+                Action::Nav(NavCommand::Bottom),
+                "Jump to the last line",
+            ),
+            bind(&[special(KeyCode::Left)], Action::PanLeft, "Pan left"),
+            bind(&[special(KeyCode::Right)], Action::PanRight, "Pan right"),
+            bind(&[key('k')], Action::SelectUp, "Move the cursor line up"),
+            bind(&[key('j')], Action::SelectDown, "Move the cursor line down"),
+            bind(
+                &[key('V')],
+                Action::VisualMode,
+                "Select lines from the cursor line (y copies, Esc cancels)",
+            ),
+            bind(&[key(' ')], Action::TogglePause, "Pause or resume"),
+            bind(
+                &[key('F')],
+                Action::ToggleFollow,
+                "Stop or resume following new lines",
+            ),
+        ],
+    },
+    Section {
+        title: "Search and bookmarks",
+        bindings: &[
+            bind(&[key('/')], Action::EditSearch, "Search"),
+            bind(&[key('n')], Action::SearchNext, "Next match"),
+            bind(&[key('N')], Action::SearchPrev, "Previous match"),
+            bind(
+                &[special(KeyCode::Esc)],
+                Action::ClearSearch,
+                "Clear the search and the cursor line",
+            ),
+            bind(&[key(':')], Action::GotoLine, "Go to line"),
+            bind(
+                &[key('m')],
+                Action::ToggleBookmark,
+                "Bookmark the cursor line",
+            ),
+            bind(&[key('\'')], Action::NextBookmark, "Next bookmark"),
+            bind(&[key('`')], Action::PrevBookmark, "Previous bookmark"),
+        ],
+    },
+    Section {
+        title: "Filtering",
+        bindings: &[
+            bind(&[key('d')], Action::EditHide, "Edit the hide regex"),
+            bind(&[key('f')], Action::EditFilter, "Edit the filter"),
+            bind(
+                &[key('h')],
+                Action::EditHighlight,
+                "Edit the highlight expression",
+            ),
+            bind(
+                &[key('s')],
+                Action::EditLineStart,
+                "Edit the line start regex",
+            ),
+            bind(&[key('e')], Action::EditExtract, "Edit the extract regex"),
+            bind(&[key('v')], Action::InvertFilter, "Invert the filter"),
+            bind(
+                &[special(KeyCode::Tab)],
+                Action::CycleSource,
+                "Show only the next file or client",
+            ),
+            bind(
+                &[key('r')],
+                Action::ToggleFilterAfterHide,
+                "Filter after hiding",
+            ),
+            bind(
+                &[key('[')],
+                Action::LessContext,
+                "Fewer context lines around matches",
+            ),
+            bind(
+                &[key(']')],
+                Action::MoreContext,
+                "More context lines around matches",
+            ),
+            bind(&[key('i')], Action::ToggleCaseInsensitive, "Ignore case"),
+            bind(
+                &[key('I')],
+                Action::ToggleSmartCase,
+                "Smart case: ignore case unless a pattern has uppercase",
+            ),
+            bind(&[key('l')], Action::ToggleLiteral, "Literal patterns"),
+            bind(&[key('b')], Action::ToggleWholeWord, "Whole words only"),
+        ],
+    },
+    Section {
+        title: "Display",
+        bindings: &[
+            bind(&[key('t')], Action::ToggleTime, "Relative times"),
+            bind(
+                &[key('T')],
+                Action::ToggleCompactGutter,
+                "Compact time and level gutter",
+            ),
+            bind(&[key('#')], Action::ToggleLineNumbers, "Line numbers"),
+            bind(&[key('w')], Action::ToggleWrap, "Wrap lines"),
+            bind(&[key('D')], Action::ToggleDedup, "Collapse repeated lines"),
+            bind(&[key('C')], Action::ToggleAlignColumns, "Align columns"),
+            bind(&[key('J')], Action::ToggleJson, "JSON highlighting"),
+            bind(&[key('K')], Action::ToggleLogfmt, "logfmt highlighting"),
+            bind(
+                &[key('S')],
+                Action::ToggleHeuristic,
+                "Heuristic highlighting",
+            ),
+            bind(
+                &[key('H')],
+                Action::HeuristicRules,
+                "Choose heuristic rules",
+            ),
+            bind(&[key('a')], Action::ToggleAnsi, "ANSI colors"),
+            bind(&[key('o')], Action::OpenJson, "Show the cursor line's JSON"),
+        ],
+    },
+    Section {
+        title: "Copy, profiles and buffer",
+        bindings: &[
+            bind(&[key('y')], Action::CopyLine, "Copy the cursor line"),
+            bind(&[key('Y')], Action::CopyVisible, "Copy the visible lines"),
+            bind(
+                &[key('x')],
+                Action::CopyMatches,
+                "Copy distinct highlight matches",
+            ),
+            bind(&[key('p')], Action::OpenProfiles, "Pick a profile"),
+            bind(
+                &[key('P')],
+                Action::SaveProfile,
+                "Save the setup as a profile",
+            ),
+            bind(&[key('c')], Action::Clear, "Clear all lines"),
+            bind(
+                &[key('<')],
+                Action::ClearAbove,
+                "Clear above the cursor line",
+            ),
+            bind(
+                &[key('>')],
+                Action::ClearBelow,
+                "Clear below the cursor line",
+            ),
+            bind(
+                &[special(KeyCode::F(5))],
+                Action::ReloadState,
+                "Reload saved settings and config",
+            ),
+            bind(
+                &[key('!')],
+                Action::ShowErrors,
+                "Source errors (c in the list clears them)",
+            ),
+            bind(
+                &[ctrl('g')],
+                Action::Diagnostics,
+                "Source, buffer and state file info",
+            ),
+            bind(&[key('?')], Action::Help, "This help"),
+            bind(&[key('q')], Action::Quit, "Quit"),
+        ],
+    },
+];
+
+/// What a key does while editing an input field; other characters are typed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditAction {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Start,
+    End,
+    Backspace,
+    Delete,
+    DeleteWordBefore,
+    DeleteToStart,
+    Apply,
+    Cancel,
+    /// Hide, filter and highlight fields only.
+    HistoryPrev,
+    HistoryNext,
+}
+
+pub const EDIT_KEYS: &[Binding<EditAction>] = &[
+    bind(&[special(KeyCode::Enter)], EditAction::Apply, "Apply"),
+    bind(&[special(KeyCode::Esc)], EditAction::Cancel, "Cancel"),
+    bind(&[special(KeyCode::Left)], EditAction::Left, "Cursor left"),
+    bind(
+        &[special(KeyCode::Right)],
+        EditAction::Right,
+        "Cursor right",
+    ),
+    bind(
+        &[ctrl_special(KeyCode::Left), alt('b')],
+        EditAction::WordLeft,
+        "Word left",
+    ),
+    bind(
+        &[ctrl_special(KeyCode::Right), alt('f')],
+        EditAction::WordRight,
+        "Word right",
+    ),
+    bind(&[special(KeyCode::Home)], EditAction::Start, "Start"),
+    bind(&[special(KeyCode::End)], EditAction::End, "End"),
+    bind(
+        &[special(KeyCode::Backspace)],
+        EditAction::Backspace,
+        "Delete left",
+    ),
+    bind(
+        &[special(KeyCode::Delete)],
+        EditAction::Delete,
+        "Delete right",
+    ),
+    bind(
+        &[ctrl('w')],
+        EditAction::DeleteWordBefore,
+        "Delete word left",
+    ),
+    bind(&[ctrl('u')], EditAction::DeleteToStart, "Delete to start"),
+    bind(
+        &[special(KeyCode::Up)],
+        EditAction::HistoryPrev,
+        "Previous applied expression",
+    ),
+    bind(
+        &[special(KeyCode::Down)],
+        EditAction::HistoryNext,
+        "Next applied expression",
+    ),
+];
+
+fn find<A: Copy>(bindings: &[Binding<A>], key: Key) -> Option<A> {
+    bindings
+        .iter()
+        .find(|binding| binding.keys.contains(&key))
+        .map(|binding| binding.action)
+}
+
+pub fn normal_action(code: KeyCode, ctrl: bool) -> Option<Action> {
+    let key = Key {
+        code,
+        ctrl,
+        alt: false,
+    };
+    NORMAL_KEYMAP
+        .iter()
+        .find_map(|section| find(section.bindings, key))
+}
+
+/// Modifiers that aren't part of a binding are ignored, so e.g. Ctrl-Enter
+/// still applies.
+pub fn edit_action(code: KeyCode, ctrl: bool, alt: bool) -> Option<EditAction> {
+    find(EDIT_KEYS, Key { code, ctrl, alt }).or_else(|| find(EDIT_KEYS, special(code)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_are_bound_once() {
+        let keys: Vec<Key> = NORMAL_KEYMAP
+            .iter()
+            .flat_map(|section| section.bindings)
+            .flat_map(|binding| binding.keys.iter().copied())
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(
+                !keys[i + 1..].contains(key),
+                "{} is bound twice",
+                key.label()
+            );
+        }
+        assert_eq!(
+            normal_action(KeyCode::Char('d'), true),
+            Some(Action::HalfPageDown)
+        );
+        assert_eq!(
+            normal_action(KeyCode::Char('d'), false),
+            Some(Action::EditHide)
+        );
+        assert_eq!(normal_action(KeyCode::Char('Z'), false), None);
+        assert_eq!(edit_action(KeyCode::Char('q'), false, false), None);
+        assert_eq!(
+            edit_action(KeyCode::Char('b'), false, true),
+            Some(EditAction::WordLeft)
+        );
+        assert_eq!(
+            edit_action(KeyCode::Left, true, false),
+            Some(EditAction::WordLeft)
+        );
+        assert_eq!(
+            edit_action(KeyCode::Enter, true, false),
+            Some(EditAction::Apply)
+        );
+        assert_eq!(alt('f').label(), "Alt-f");
+        assert_eq!(NORMAL_KEYMAP[0].bindings[6].keys_label(), "g Home");
+        assert_eq!(ctrl('u').label(), "Ctrl-u");
+    }
+}
@@ -0,0 +1,57 @@
+//! The filtering and highlighting engine behind the `logviewer` binary, for
+//! embedding in other programs.
+//!
+//! - [`parse_filter`] turns a filter expression (`error && !debug`, regexes,
+//!   `"quoted text"`) into a [`FilterExpr`]; [`FilterExpr::matches`] tests a line.
+//! - [`FilterState`] bundles the hide regex, filter and highlight expressions.
+//! - [`LogState`] buffers lines with absolute line numbers and tracks which of
+//!   them pass the filter.
+//! - [`highlight_line`] finds the styled [`Span`]s of a line and
+//!   [`apply_highlights`] cuts the line into `(text, style)` runs.
+//!
+//! # Spans
+//!
+//! A [`Span`] covers the bytes `start..end` of the line, the offsets regex
+//! matches report, with one [`HighlightStyle`]. Spans may overlap; where they
+//! do, each character takes the style of the span with the highest `priority`,
+//! and the later span in the list on a tie. Built-in priorities, highest first:
+//!
+//! | Priority | Source |
+//! |----------|--------|
+//! | 200 | `/` search matches |
+//! | 100 | The highlight expression |
+//! | 50 | JSON keys and values |
+//! | 20 | User rules from the config file (default, configurable per rule) |
+//! | 15 | logfmt keys and values |
+//! | 12 | Heuristic IP address, URL and UUID rules |
+//! | 10 | Other heuristic rules (levels, brackets, timestamps) |
+//!
+//! ```
+//! use logviewer::{apply_highlights, highlight_line, parse_filter, HeuristicRules, HighlightStyle};
+//!
+//! let expr = parse_filter("timeout").unwrap();
+//! let line = "ERROR request timeout";
+//! assert!(expr.matches(line));
+//! let spans = highlight_line(line, Some(&expr), None, HeuristicRules::ALL, false, false);
+//! let runs = apply_highlights(line, &spans);
+//! assert_eq!(runs[0], ("ERROR".to_string(), HighlightStyle::Error));
+//! assert_eq!(runs[2], ("timeout".to_string(), HighlightStyle::CustomHighlight));
+//! ```
+
+pub mod core;
+pub mod filter;
+pub mod highlight;
+pub mod syslog;
+pub mod timeparse;
+
+// Shared with the binary; not part of the embedding API.
+#[doc(hidden)]
+pub mod input;
+#[doc(hidden)]
+pub mod netinfo;
+#[doc(hidden)]
+pub mod state;
+
+pub use crate::core::{FilterState, LineKind, LogLine, LogState};
+pub use filter::{parse_filter, parse_filter_opts, FilterExpr, FilterOptions};
+pub use highlight::{apply_highlights, highlight_line, HeuristicRules, HighlightStyle, Span};
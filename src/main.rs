@@ -1,45 +1,55 @@
+mod ansi;
 mod app;
+mod clipboard;
+mod config;
 mod constants;
-mod core;
-mod filter;
 #[cfg(feature = "gui")]
 mod gui;
-mod highlight;
-mod input;
-mod netinfo;
+mod keymap;
 mod source;
-mod state;
 mod tui;
 
-use anyhow::Result;
+use logviewer::{core, filter, highlight, input, state, syslog, timeparse};
+
+use anyhow::{anyhow, Result};
 use app::App;
 use clap::Parser;
-use constants::POLL_INTERVAL_MS;
-use core::InputMode;
+use constants::{
+    DEFAULT_TAB_WIDTH, H_SCROLL_STEP, POLL_INTERVAL_MAX_MS, POLL_INTERVAL_MS, SOURCE_LABEL_MAX,
+};
+use core::{InputMode, NavCommand, OutputTemplate, TimeFormat};
 use crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
-        MouseEventKind,
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use fancy_regex::Regex;
+use filter::FilterOptions;
+use input::TextInput;
+use keymap::Action;
 use ratatui::{backend::CrosstermBackend, Terminal};
-use source::{start_source, LogSource, SourceEvent};
-use state::AppState;
+use source::{start_source, FileSpec, LogSource, SourceEvent};
+use state::{AppState, Presets};
 use std::io;
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tui::redraw::RedrawThrottle;
+use tui::theme::{Theme, ThemeMode};
 
 #[derive(Parser)]
-#[command(name = "logviewer")]
+#[command(name = "logviewer", version)]
 #[command(about = "Interactive log viewer with filtering and highlighting")]
 struct Cli {
-    #[arg(help = "Log file to view (reads from stdin if not provided)")]
-    file: Option<PathBuf>,
+    #[arg(
+        help = "Log files to view (reads from stdin if not provided). Several files are interleaved and labeled. Use FILE:REGEX to give a file its own line start regex"
+    )]
+    file: Vec<String>,
 
     #[arg(
         short = 'l',
@@ -48,6 +58,195 @@ struct Cli {
     )]
     port: Option<u16>,
 
+    #[arg(
+        long,
+        value_name = "ADDR",
+        requires = "port",
+        help = "Accept --listen connections only on this local address, like 127.0.0.1, instead of all interfaces"
+    )]
+    bind: Option<IpAddr>,
+
+    #[arg(long, value_name = "PATH", requires_all = ["port", "tls_key"], help = "Accept TLS on the --listen port with this PEM certificate chain")]
+    tls_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "tls_cert",
+        help = "PEM private key for --tls-cert"
+    )]
+    tls_key: Option<PathBuf>,
+
+    #[cfg(unix)]
+    #[arg(
+        long = "listen-unix",
+        value_name = "PATH",
+        conflicts_with_all = ["port", "file", "command"],
+        help = "Listen on a Unix domain socket for incoming logs"
+    )]
+    listen_unix: Option<PathBuf>,
+
+    #[arg(
+        long = "no-auto-json",
+        help = "Don't enable JSON highlighting automatically when the input looks like JSON"
+    )]
+    no_auto_json: bool,
+
+    #[arg(
+        long = "line-start",
+        value_name = "REGEX",
+        help = "Regex matching the first line of an entry; other lines are joined onto the previous entry"
+    )]
+    line_start: Option<String>,
+
+    #[arg(
+        long = "state-file",
+        value_name = "PATH",
+        help = "Read and write settings here instead of the config directory"
+    )]
+    state_file: Option<PathBuf>,
+
+    #[arg(
+        long = "no-state",
+        conflicts_with = "state_file",
+        help = "Don't load or save settings"
+    )]
+    no_state: bool,
+
+    #[arg(
+        long = "max-lines",
+        value_name = "N",
+        help = "Keep at most N lines in memory, dropping the oldest (TUI)"
+    )]
+    max_lines: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "file",
+        help = "Start from the last N lines of the file instead of reading it all"
+    )]
+    tail: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "CHAR",
+        help = "Align columns split on this character instead of whitespace (C toggles alignment)"
+    )]
+    delimiter: Option<char>,
+
+    #[arg(
+        long = "tab-width",
+        value_name = "N",
+        default_value_t = DEFAULT_TAB_WIDTH,
+        value_parser = clap::value_parser!(u8).range(1..),
+        help = "Draw tabs as spaces up to the next multiple of N columns (TUI)"
+    )]
+    tab_width: u8,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "Show N unmatched lines before and after each filter match, dimmed ([/] adjust)"
+    )]
+    context: usize,
+
+    #[arg(
+        long,
+        requires = "file",
+        help = "If the file doesn't exist yet, wait for it to be created (single file)"
+    )]
+    wait: bool,
+
+    #[arg(
+        long,
+        help = "Parse lines as syslog frames (RFC5424/RFC3164) and show only the message"
+    )]
+    syslog: bool,
+
+    #[arg(long, help = "Don't jump back to where the file was left last time")]
+    fresh: bool,
+
+    #[arg(long, help = "Quit on q without asking for confirmation")]
+    no_confirm: bool,
+
+    #[arg(
+        long,
+        help = "Treat filter and highlight patterns as plain text instead of regexes"
+    )]
+    literal: bool,
+
+    #[arg(long, help = "Draw without colors (also when NO_COLOR is set)")]
+    no_color: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ThemeMode::Auto,
+        help = "Colors for a light or dark terminal; auto reads COLORFGBG and assumes dark without it"
+    )]
+    theme: ThemeMode,
+
+    #[arg(
+        long = "output-template",
+        value_name = "TEMPLATE",
+        help = "Format of copied lines, e.g. '{ts}\\t{line}\\t{content}' (fields: ts reltime line content raw)"
+    )]
+    output_template: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        help = "Start with this filter expression instead of the saved one"
+    )]
+    filter: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        help = "Start with this highlight expression instead of the saved one"
+    )]
+    highlight: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Start with this hide regex instead of the saved one"
+    )]
+    hide: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = clipboard::ClipboardMode::Auto,
+        help = "Clipboard to copy to; auto uses OSC 52 over SSH"
+    )]
+    clipboard: clipboard::ClipboardMode,
+
+    #[arg(
+        long = "timestamp-format",
+        value_name = "STRFTIME",
+        help = "Format of the timestamp at the start of each line (default: RFC3339, ISO and syslog)"
+    )]
+    timestamp_format: Option<String>,
+
+    #[arg(
+        long = "time-format",
+        value_enum,
+        default_value_t = TimeFormat::Clock,
+        help = "Absolute time style for the compact gutter and copied lines"
+    )]
+    time_format: TimeFormat,
+
+    #[arg(
+        last = true,
+        value_name = "COMMAND",
+        conflicts_with_all = ["file", "port"],
+        help = "Run a command and view its stdout and stderr, e.g. `logviewer -- kubectl logs -f pod`"
+    )]
+    command: Vec<String>,
+
     #[cfg(feature = "gui")]
     #[arg(long = "tui", help = "Use TUI instead of GUI")]
     tui: bool,
@@ -55,29 +254,181 @@ struct Cli {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    if cli.no_state {
+        state::set_state_path(None);
+    } else if let Some(path) = &cli.state_file {
+        state::set_state_path(Some(path.clone()));
+    }
+    let line_start = cli
+        .line_start
+        .as_deref()
+        .map(compile_line_start)
+        .transpose()?;
+    if let Some(template) = &cli.output_template {
+        OutputTemplate::parse(template).map_err(|e| anyhow!("Invalid --output-template: {}", e))?;
+    }
+    let presets = Presets {
+        hide: cli.hide.clone(),
+        filter: cli.filter.clone(),
+        highlight: cli.highlight.clone(),
+    };
+    let saved = AppState::load();
+    let opts = FilterOptions {
+        case_insensitive: saved.case_insensitive,
+        smart_case: saved.smart_case,
+        literal: saved.literal || cli.literal,
+        whole_word: saved.whole_word,
+    };
+    app::validate_presets(&presets, opts).map_err(|e| anyhow!("Invalid --{}", e))?;
+    clipboard::set_mode(cli.clipboard);
+    let config = config::Config::load()?;
+    highlight::set_config(config.highlight_config(cli.theme.is_light())?);
 
     #[cfg(feature = "gui")]
-    if !cli.tui {
-        return gui::run_with_args(cli.file, cli.port);
+    if !needs_tui(&cli) {
+        return gui::run_with_args(
+            cli.file.first().cloned(),
+            cli.port,
+            cli.bind,
+            cli.line_start,
+            cli.tail,
+            presets,
+            config.validate_delay(),
+        );
     }
 
-    run_tui(cli)
+    run_tui(cli, line_start, presets, config.validate_delay())
+}
+
+/// Whether `cli` asks for something only the TUI supports.
+#[cfg(feature = "gui")]
+fn needs_tui(cli: &Cli) -> bool {
+    #[cfg(unix)]
+    if cli.listen_unix.is_some() {
+        return true;
+    }
+    cli.tui
+        || !cli.command.is_empty()
+        || cli.file.len() > 1
+        || cli.tls_cert.is_some()
+        || cli.wait
+        || cli.syslog
+}
+
+/// File name without its last extension, cut to a short column.
+fn source_label(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| {
+            stem.to_string_lossy()
+                .chars()
+                .take(SOURCE_LABEL_MAX)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
-fn run_tui(cli: Cli) -> Result<()> {
+/// What `run_tui` picks to read from, in its order, for the diagnostics popup.
+fn describe_source(cli: &Cli) -> String {
+    #[cfg(unix)]
+    if let Some(path) = &cli.listen_unix {
+        return format!("unix socket {}", path.display());
+    }
+    if !cli.command.is_empty() {
+        format!("command `{}`", cli.command.join(" "))
+    } else if cli.port.is_some() {
+        if cli.tls_cert.is_some() {
+            "TCP with TLS"
+        } else {
+            "TCP"
+        }
+        .to_string()
+    } else {
+        match cli.file.as_slice() {
+            [] => "stdin".to_string(),
+            [file] => format!("file {}", file),
+            files => format!("{} files: {}", files.len(), files.join(", ")),
+        }
+    }
+}
+
+fn compile_line_start(pattern: &str) -> Result<Arc<Regex>> {
+    Regex::new(pattern)
+        .map(Arc::new)
+        .map_err(|e| anyhow!("Invalid --line-start regex: {}", e))
+}
+
+fn run_tui(
+    cli: Cli,
+    line_start: Option<Arc<Regex>>,
+    presets: Presets,
+    validate_delay: Duration,
+) -> Result<()> {
     let (tx, rx) = mpsc::channel::<SourceEvent>();
 
-    let source = if let Some(port) = cli.port {
-        eprintln!("Listening on port {}...", port);
-        LogSource::Network(port)
-    } else if let Some(path) = cli.file {
-        LogSource::File(path)
+    let mut specs = cli
+        .file
+        .iter()
+        .map(|spec| {
+            FileSpec::parse(spec).map(|spec| FileSpec {
+                tail: cli.tail,
+                ..spec
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let source_labels: Vec<String> = if specs.len() > 1 {
+        specs.iter().map(|spec| source_label(&spec.path)).collect()
+    } else {
+        Vec::new()
+    };
+
+    let source = if !cli.command.is_empty() {
+        LogSource::Command(cli.command.clone())
+    } else if let Some(port) = cli.port {
+        let tls = match (&cli.tls_cert, &cli.tls_key) {
+            (Some(cert), Some(key)) => Some(source::load_tls_config(cert, key)?),
+            _ => None,
+        };
+        let tls_note = if tls.is_some() { " (TLS)" } else { "" };
+        match cli.bind {
+            Some(addr) => eprintln!(
+                "Listening on {}{}...",
+                SocketAddr::new(addr, port),
+                tls_note
+            ),
+            None => eprintln!("Listening on port {}{}...", port, tls_note),
+        }
+        LogSource::Network(port, cli.bind, tls)
+    } else if specs.len() > 1 {
+        LogSource::Files(
+            specs,
+            timeparse::TimestampParser::new(cli.timestamp_format.clone()),
+        )
+    } else if let Some(spec) = specs.pop() {
+        LogSource::File(FileSpec {
+            wait: cli.wait,
+            ..spec
+        })
     } else {
         LogSource::Stdin
     };
+    #[cfg(unix)]
+    let source = match &cli.listen_unix {
+        Some(path) => LogSource::UnixSocket(path.clone()),
+        None => source,
+    };
+
+    // Positions are absolute line numbers, which mean nothing when only the tail was read.
+    let position_key = match &source {
+        LogSource::File(spec) if spec.tail.is_none() => std::fs::canonicalize(&spec.path)
+            .ok()
+            .map(|path| path.display().to_string()),
+        _ => None,
+    };
 
     let state = AppState::load();
-    let line_start_regex = if state.line_start_regex.trim().is_empty() {
+    let line_start_regex = if line_start.is_some() {
+        line_start
+    } else if state.line_start_regex.trim().is_empty() {
         None
     } else {
         match Regex::new(&state.line_start_regex) {
@@ -93,19 +444,34 @@ fn run_tui(cli: Cli) -> Result<()> {
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_app(&mut terminal, rx, cli.port);
+    let result = run_app(
+        &mut terminal,
+        rx,
+        &cli,
+        source_labels,
+        &presets,
+        position_key,
+        validate_delay,
+    );
 
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
+    source::cleanup();
 
     if let Err(e) = result {
         eprintln!("Error: {}", e);
@@ -117,18 +483,81 @@ fn run_tui(cli: Cli) -> Result<()> {
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     rx: mpsc::Receiver<SourceEvent>,
-    listen_port: Option<u16>,
+    cli: &Cli,
+    source_labels: Vec<String>,
+    presets: &Presets,
+    position_key: Option<String>,
+    validate_delay: Duration,
 ) -> Result<()> {
-    let mut app = App::new(rx, listen_port);
+    let mut app = App::new(rx, cli.port, presets);
+    if let Some(addr) = cli.bind {
+        app.listen_state.set_bind_addr(addr);
+    }
+    app.validate_delay = validate_delay;
+    #[cfg(unix)]
+    {
+        app.listen_state.socket_path = cli
+            .listen_unix
+            .as_ref()
+            .map(|path| path.display().to_string());
+    }
+    app.auto_json = !cli.no_auto_json;
+    app.timestamp_parser = timeparse::TimestampParser::new(cli.timestamp_format.clone());
+    app.time_format = cli.time_format;
+    app.syslog = cli.syslog;
+    app.column_delimiter = cli.delimiter;
+    app.align_columns = cli.delimiter.is_some();
+    app.context = cli.context;
+    app.tab_width = cli.tab_width.into();
+    // Checked in `main`.
+    app.output_template = cli
+        .output_template
+        .as_deref()
+        .and_then(|template| OutputTemplate::parse(template).ok());
+    app.theme = Theme::new(cli.theme.is_light());
+    app.no_color =
+        cli.no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    app.source_labels = source_labels;
+    app.source_description = describe_source(cli);
+    app.log_state.max_lines = cli.max_lines;
+    app.restore_position = position_key.is_some() && !cli.fresh;
+    app.confirm_quit = !cli.no_confirm;
+    app.position_key = position_key;
+    if cli.literal && !app.literal {
+        app.toggle_literal();
+    }
 
+    // Backs off while nothing happens and snaps back on any source data or input.
+    let mut poll_interval = POLL_INTERVAL_MS;
+    let mut redraw = RedrawThrottle::default();
     loop {
-        app.poll_source();
-
-        let visible_height = terminal.size()?.height.saturating_sub(9) as usize;
-
-        terminal.draw(|f| tui::draw(f, &mut app))?;
+        if app.poll_source() {
+            poll_interval = POLL_INTERVAL_MS;
+            redraw.on_data();
+        }
+        if app.validation_due_in() == Some(Duration::ZERO) {
+            app.validate_pending_input();
+            redraw.on_input();
+        }
 
-        if event::poll(Duration::from_millis(POLL_INTERVAL_MS))? {
+        if redraw.due_in(Instant::now()).is_zero() {
+            terminal.draw(|f| tui::draw(f, &mut app))?;
+            redraw.drawn(Instant::now());
+        }
+        let visible_height = app.log_view_height();
+
+        let timeout = Duration::from_millis(poll_interval).min(redraw.due_in(Instant::now()));
+        let timeout = app
+            .validation_due_in()
+            .map_or(timeout, |due| due.min(timeout));
+        let has_event = event::poll(timeout)?;
+        poll_interval = if has_event {
+            POLL_INTERVAL_MS
+        } else {
+            (poll_interval * 2).min(POLL_INTERVAL_MAX_MS)
+        };
+        if has_event {
+            redraw.on_input();
             let ev = event::read()?;
 
             if let Event::Mouse(mouse) = &ev {
@@ -136,31 +565,88 @@ fn run_app(
                     && app.listen_state.show_popup()
                 {
                     if let Some(text) = app.listen_state.handle_click(mouse.column, mouse.row) {
-                        copy_to_clipboard(&text);
-                        app.status_message = Some(format!("Copied: {}", text));
+                        copy_and_report(&mut app, &text, format!("Copied: {}", text));
+                    }
+                }
+                let popup_open = app.listen_state.show_popup()
+                    || app.profile_popup.is_some()
+                    || app.heuristic_popup.is_some()
+                    || app.text_popup.is_some()
+                    || app.help_scroll.is_some()
+                    || app.error_scroll.is_some();
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left) && !popup_open {
+                    app.minimap_click(mouse.column, mouse.row);
+                }
+                match mouse.kind {
+                    MouseEventKind::ScrollUp if !popup_open => {
+                        app.mouse_scroll(mouse.column, mouse.row, true)
+                    }
+                    MouseEventKind::ScrollDown if !popup_open => {
+                        app.mouse_scroll(mouse.column, mouse.row, false)
                     }
+                    _ => {}
                 }
             }
 
+            if let Event::Paste(text) = &ev {
+                app.handle_paste(text);
+            }
+
             if let Event::Key(key) = ev {
                 app.status_message = None;
 
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    return quit(&mut app);
+                }
+
                 if app.show_quit_confirm {
-                    handle_quit_confirm(&mut app, key.code)?;
+                    if handle_quit_confirm(&mut app, key.code) {
+                        return quit(&mut app);
+                    }
                     continue;
                 }
 
                 if app.listen_state.show_popup() {
-                    handle_listen_popup(&mut app, key.code, key.modifiers);
+                    if handle_listen_popup(&mut app, key.code) {
+                        return quit(&mut app);
+                    }
+                    continue;
+                }
+
+                if app.profile_popup.is_some() {
+                    handle_profile_popup(&mut app, key.code);
+                    continue;
+                }
+
+                if app.heuristic_popup.is_some() {
+                    handle_heuristic_popup(&mut app, key.code);
+                    continue;
+                }
+
+                if app.text_popup.is_some() {
+                    handle_text_popup(&mut app, key.code, visible_height);
+                    continue;
+                }
+
+                if app.error_scroll.is_some() {
+                    handle_error_list(&mut app, key.code, visible_height);
+                    continue;
+                }
+
+                if app.help_scroll.is_some() {
+                    handle_help_overlay(&mut app, key.code, visible_height);
                     continue;
                 }
 
                 match app.input_mode {
                     InputMode::Normal => {
-                        handle_normal_mode(&mut app, key.code, key.modifiers, visible_height)?
+                        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                        if handle_normal_mode(&mut app, key.code, ctrl, visible_height)? {
+                            return quit(&mut app);
+                        }
                     }
                     _ => {
-                        if app.handle_input_key(key.code) {
+                        if app.handle_input_key(key) {
                             app.apply_current_input();
                         }
                     }
@@ -170,113 +656,270 @@ fn run_app(
     }
 }
 
-fn handle_quit_confirm(app: &mut App, key_code: KeyCode) -> Result<()> {
-    match key_code {
-        KeyCode::Char('y') | KeyCode::Char('Y') => std::process::exit(0),
-        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc | KeyCode::Char('q') => {
-            app.show_quit_confirm = false;
-        }
-        _ => {}
-    }
+/// Leave `run_app`, which hands the terminal back to `run_tui` to restore.
+fn quit(app: &mut App) -> Result<()> {
+    app.save_file_position();
     Ok(())
 }
 
-fn handle_listen_popup(app: &mut App, key_code: KeyCode, modifiers: KeyModifiers) {
+/// Quit on `q`: ask first unless `--no-confirm` was given. True to quit now.
+fn request_quit(app: &mut App) -> bool {
+    app.show_quit_confirm = app.confirm_quit;
+    !app.confirm_quit
+}
+
+/// `y` quits; any other key cancels. True to quit.
+fn handle_quit_confirm(app: &mut App, key_code: KeyCode) -> bool {
+    app.show_quit_confirm = false;
+    matches!(key_code, KeyCode::Char('y') | KeyCode::Char('Y'))
+}
+
+/// True to quit.
+fn handle_listen_popup(app: &mut App, key_code: KeyCode) -> bool {
     match key_code {
-        KeyCode::Char('q') => app.show_quit_confirm = true,
-        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-            app.show_quit_confirm = true
-        }
+        KeyCode::Char('q') => return request_quit(app),
         KeyCode::Tab => app.listen_state.toggle_display_mode(),
         KeyCode::Up | KeyCode::Char('k') => app.listen_state.select_prev(),
         KeyCode::Down | KeyCode::Char('j') => app.listen_state.select_next(),
         KeyCode::Enter => {
             if let Some(text) = app.listen_state.get_selected_copy_text() {
-                copy_to_clipboard(&text);
-                app.status_message = Some(format!("Copied: {}", text));
+                copy_and_report(app, &text, format!("Copied: {}", text));
             }
         }
         _ => {}
     }
+    false
 }
 
-fn handle_normal_mode(
-    app: &mut App,
-    key_code: KeyCode,
-    modifiers: KeyModifiers,
-    visible_height: usize,
-) -> Result<()> {
+fn handle_profile_popup(app: &mut App, key_code: KeyCode) {
     match key_code {
-        KeyCode::Char('q') => app.show_quit_confirm = true,
-        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-            app.show_quit_confirm = true
+        KeyCode::Esc | KeyCode::Char('q') => app.profile_popup = None,
+        KeyCode::Up | KeyCode::Char('k') => app.move_profile_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('p') => app.move_profile_selection(1),
+        KeyCode::Enter => {
+            if let Some(name) = app.selected_profile_name() {
+                app.profile_popup = None;
+                app.load_profile(&name);
+            }
+        }
+        KeyCode::Char('x') | KeyCode::Delete => {
+            if let Some(name) = app.selected_profile_name() {
+                app.delete_profile(&name);
+                if app.profiles.is_empty() {
+                    app.profile_popup = None;
+                } else {
+                    app.move_profile_selection(0);
+                }
+            }
         }
-        KeyCode::Char('d') => app.input_mode = InputMode::HideEdit,
-        KeyCode::Char('f') => app.input_mode = InputMode::FilterEdit,
-        KeyCode::Char('h') => app.input_mode = InputMode::HighlightEdit,
-        KeyCode::Char('s') => app.input_mode = InputMode::LineStartEdit,
-        KeyCode::Char('c') => app.clear(),
-        KeyCode::Char('t') => app.toggle_time(),
-        KeyCode::Char('w') => app.toggle_wrap(),
-        KeyCode::Char('g') => app.log_state.scroll_to_start(),
-        KeyCode::Char('G') => app.log_state.scroll_to_end(),
-        KeyCode::Up | KeyCode::Char('k') => app.log_state.scroll_up(1),
-        KeyCode::Down | KeyCode::Char('j') => app.log_state.scroll_down(1),
-        KeyCode::PageUp => app.log_state.scroll_up(visible_height),
-        KeyCode::PageDown => app.log_state.scroll_down(visible_height),
-        KeyCode::Home => app.log_state.scroll_to_start(),
-        KeyCode::End => app.log_state.scroll_to_end(),
         _ => {}
     }
-    Ok(())
 }
 
-fn copy_to_clipboard(text: &str) {
-    #[cfg(target_os = "macos")]
-    {
-        use std::io::Write;
-        use std::process::{Command, Stdio};
-        if let Ok(mut child) = Command::new("pbcopy").stdin(Stdio::piped()).spawn() {
-            if let Some(stdin) = child.stdin.as_mut() {
-                let _ = stdin.write_all(text.as_bytes());
+fn handle_heuristic_popup(app: &mut App, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('H') => app.heuristic_popup = None,
+        KeyCode::Up | KeyCode::Char('k') => app.move_heuristic_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.move_heuristic_selection(1),
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            if let Some(selected) = app.heuristic_popup {
+                app.toggle_heuristic_rule(selected);
             }
-            let _ = child.wait();
         }
+        _ => {}
     }
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        use std::io::Write;
-        use std::process::{Command, Stdio};
-        let cmds = ["xclip", "xsel"];
-        for cmd in cmds {
-            if let Ok(mut child) = Command::new(cmd)
-                .args(if cmd == "xclip" {
-                    &["-selection", "clipboard"][..]
-                } else {
-                    &["--clipboard", "--input"][..]
-                })
-                .stdin(Stdio::piped())
-                .spawn()
-            {
-                if let Some(stdin) = child.stdin.as_mut() {
-                    let _ = stdin.write_all(text.as_bytes());
+fn handle_text_popup(app: &mut App, key_code: KeyCode, visible_height: usize) {
+    match key_code {
+        KeyCode::Esc | KeyCode::Char('q') => app.text_popup = None,
+        KeyCode::Up | KeyCode::Char('k') => app.scroll_text_popup(true, 1),
+        KeyCode::Down | KeyCode::Char('j') => app.scroll_text_popup(false, 1),
+        KeyCode::PageUp => app.scroll_text_popup(true, visible_height),
+        KeyCode::PageDown => app.scroll_text_popup(false, visible_height),
+        KeyCode::Home => app.scroll_text_popup(true, usize::MAX),
+        KeyCode::End => app.scroll_text_popup(false, usize::MAX),
+        _ => {}
+    }
+}
+
+/// Run the action bound to the key. True to quit.
+fn handle_normal_mode(
+    app: &mut App,
+    key_code: KeyCode,
+    ctrl: bool,
+    visible_height: usize,
+) -> Result<bool> {
+    let Some(action) = keymap::normal_action(key_code, ctrl) else {
+        return Ok(false);
+    };
+    match action {
+        Action::Quit => return Ok(request_quit(app)),
+        Action::ShowErrors => app.open_error_list(),
+        Action::Diagnostics => app.open_diagnostics(),
+        Action::Help => app.help_scroll = Some(0),
+        Action::EditHide => app.input_mode = InputMode::HideEdit,
+        Action::EditFilter => app.input_mode = InputMode::FilterEdit,
+        Action::EditHighlight => app.input_mode = InputMode::HighlightEdit,
+        Action::EditLineStart => app.input_mode = InputMode::LineStartEdit,
+        Action::Clear => app.clear(),
+        Action::ClearAbove => app.clear_around_cursor(true),
+        Action::ClearBelow => app.clear_around_cursor(false),
+        Action::ToggleTime => app.toggle_time(),
+        Action::ToggleCompactGutter => app.toggle_compact_gutter(),
+        Action::ToggleLineNumbers => app.toggle_line_numbers(),
+        Action::ToggleWrap => app.toggle_wrap(),
+        Action::ToggleJson => app.toggle_json(),
+        Action::ToggleLogfmt => app.toggle_logfmt(),
+        Action::InvertFilter => app.toggle_invert_filter(),
+        Action::CycleSource => app.cycle_source_only(),
+        Action::ToggleFilterAfterHide => app.toggle_filter_after_hide(),
+        Action::ToggleDedup => app.toggle_dedup(),
+        Action::ToggleAlignColumns => app.toggle_align_columns(),
+        Action::LessContext => app.adjust_context(false),
+        Action::MoreContext => app.adjust_context(true),
+        Action::ToggleHeuristic => app.toggle_heuristic(),
+        Action::HeuristicRules => app.heuristic_popup = Some(0),
+        Action::ToggleCaseInsensitive => app.toggle_case_insensitive(),
+        Action::ToggleSmartCase => app.toggle_smart_case(),
+        Action::ToggleLiteral => app.toggle_literal(),
+        Action::ToggleWholeWord => app.toggle_whole_word(),
+        Action::ToggleAnsi => app.toggle_ansi(),
+        Action::ReloadState => app.reload_state(),
+        Action::CopyVisible => match (tui::visible_text(app), app.visible_range) {
+            (Some(text), Some((start, end))) => {
+                copy_and_report(app, &text, format!("Copied {} visible lines", end - start));
+            }
+            _ => app.status_message = Some("Nothing to copy".to_string()),
+        },
+        Action::CopyMatches => match app.highlight_matches_text() {
+            Some((0, _)) => {
+                app.status_message = Some("No highlight matches in the shown lines".to_string())
+            }
+            Some((count, text)) => {
+                copy_and_report(app, &text, format!("Copied {} distinct matches", count))
+            }
+            None => app.status_message = Some("No highlight pattern (h to set one)".to_string()),
+        },
+        Action::OpenProfiles => app.open_profile_popup(),
+        Action::OpenJson => app.open_json_popup(),
+        Action::ToggleBookmark => app.toggle_bookmark(),
+        Action::NextBookmark => app.jump_to_bookmark(true),
+        Action::PrevBookmark => app.jump_to_bookmark(false),
+        Action::SaveProfile => {
+            let name = app.active_profile.clone().unwrap_or_default();
+            app.input_fields.profile_name = TextInput::new(name);
+            app.input_mode = InputMode::ProfileNameEdit;
+        }
+        Action::EditSearch => app.input_mode = InputMode::SearchEdit,
+        Action::EditExtract => app.input_mode = InputMode::ExtractEdit,
+        Action::GotoLine => {
+            app.input_fields.goto_line = TextInput::default();
+            app.input_mode = InputMode::GotoLineEdit;
+        }
+        Action::SearchNext => app.search_next(),
+        Action::SearchPrev => app.search_prev(),
+        Action::ClearSearch if app.visual_anchor.is_some() => app.visual_anchor = None,
+        Action::ClearSearch => {
+            app.clear_search();
+            app.log_state.selected_idx = None;
+        }
+        Action::VisualMode => app.toggle_visual(),
+        Action::CopyLine if app.visual_anchor.is_some() => {
+            let copied = app.visual_text();
+            app.visual_anchor = None;
+            match copied {
+                Some((count, text)) => {
+                    copy_and_report(app, &text, format!("Copied {} lines", count))
                 }
-                let _ = child.wait();
-                break;
+                None => app.status_message = Some("Nothing to copy".to_string()),
             }
         }
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        use std::io::Write;
-        use std::process::{Command, Stdio};
-        if let Ok(mut child) = Command::new("clip").stdin(Stdio::piped()).spawn() {
-            if let Some(stdin) = child.stdin.as_mut() {
-                let _ = stdin.write_all(text.as_bytes());
+        Action::CopyLine => match app.selected_line_text() {
+            Some((number, text)) => {
+                copy_and_report(app, &text, format!("Copied line {}", number));
             }
-            let _ = child.wait();
+            None => app.status_message = Some("Nothing to copy".to_string()),
+        },
+        Action::TogglePause => app.log_state.toggle_pause(),
+        Action::ToggleFollow => {
+            app.log_state.toggle_follow();
+            app.status_message = Some(
+                if app.log_state.follow_tail {
+                    "Following new lines"
+                } else {
+                    "Stopped following (F to resume)"
+                }
+                .to_string(),
+            );
         }
+        Action::SelectUp => app.select_up(),
+        Action::SelectDown => app.select_down(),
+        Action::Nav(cmd) => app.log_state.nav(cmd, visible_height),
+        // Unlike `j`/`k`, these leave the cursor line where it is.
+        Action::HalfPageUp => app
+            .log_state
+            .nav(NavCommand::PageUp, (visible_height / 2).max(1)),
+        Action::HalfPageDown => app
+            .log_state
+            .nav(NavCommand::PageDown, (visible_height / 2).max(1)),
+        Action::PanLeft => app.scroll_horizontal(-(H_SCROLL_STEP as isize)),
+        Action::PanRight => app.scroll_horizontal(H_SCROLL_STEP as isize),
     }
+    Ok(false)
+}
+
+/// Scroll the source error list, clear it with `c`, or close it with `Esc`, `q` or `!`.
+fn handle_error_list(app: &mut App, key_code: KeyCode, visible_height: usize) {
+    let Some(scroll) = app.error_scroll else {
+        return;
+    };
+    app.error_scroll = match key_code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('!') => None,
+        KeyCode::Char('c') => {
+            app.source_errors.clear();
+            app.status_message = Some("Cleared source errors".to_string());
+            None
+        }
+        KeyCode::Up | KeyCode::Char('k') => Some(scroll.saturating_sub(1)),
+        KeyCode::Down | KeyCode::Char('j') => Some(scroll.saturating_add(1)),
+        KeyCode::PageUp => Some(scroll.saturating_sub(visible_height)),
+        KeyCode::PageDown => Some(scroll.saturating_add(visible_height)),
+        KeyCode::Home | KeyCode::Char('g') => Some(0),
+        KeyCode::End | KeyCode::Char('G') => Some(usize::MAX),
+        _ => Some(scroll),
+    };
+}
+
+/// Scroll the full-screen help, or close it with `Esc`, `q` or `?`.
+fn handle_help_overlay(app: &mut App, key_code: KeyCode, visible_height: usize) {
+    let Some(scroll) = app.help_scroll else {
+        return;
+    };
+    app.help_scroll = match key_code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => None,
+        KeyCode::Up | KeyCode::Char('k') => Some(scroll.saturating_sub(1)),
+        KeyCode::Down | KeyCode::Char('j') => Some(scroll + 1),
+        KeyCode::PageUp => Some(scroll.saturating_sub(visible_height)),
+        KeyCode::PageDown => Some(scroll + visible_height),
+        KeyCode::Home | KeyCode::Char('g') => Some(0),
+        KeyCode::End | KeyCode::Char('G') => Some(usize::MAX),
+        _ => Some(scroll),
+    };
+}
+
+/// Copy `text`, reporting `done` or the clipboard error in the status bar.
+fn copy_and_report(app: &mut App, text: &str, done: String) {
+    app.status_message = Some(match clipboard::copy_to_clipboard(text) {
+        Ok(copied) if copied < text.len() => {
+            format!(
+                "{} (truncated to {} of {} bytes for OSC 52)",
+                done,
+                copied,
+                text.len()
+            )
+        }
+        Ok(_) => done,
+        Err(e) => format!("Copy failed: {}", e),
+    });
 }
@@ -1,41 +1,139 @@
-use anyhow::Result;
+use crate::timeparse::TimestampParser;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
 use fancy_regex::Regex;
+use flate2::read::MultiGzDecoder;
 use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::net::{TcpListener, TcpStream};
-use std::path::PathBuf;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use std::collections::VecDeque;
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Cursor, IsTerminal, Read, Seek, SeekFrom};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
 pub enum LogSource {
-    File(PathBuf),
+    File(FileSpec),
+    /// Several files at once, each line tagged with its file's position in the
+    /// list. Their existing content is interleaved by the parsed timestamps.
+    Files(Vec<FileSpec>, TimestampParser),
     Stdin,
-    Network(u16),
+    /// TCP port, on one local address (`--bind`) or else all of them, with the
+    /// TLS setup from `--tls-cert`/`--tls-key` if given.
+    Network(u16, Option<IpAddr>, Option<Arc<ServerConfig>>),
+    /// Program and arguments to run, streaming its stdout and stderr.
+    Command(Vec<String>),
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
+}
+
+/// A file to tail, optionally with its own line-start regex given as `path:regex`.
+pub struct FileSpec {
+    pub path: PathBuf,
+    pub line_start_regex: Option<Arc<Regex>>,
+    /// Start from only the last this many lines instead of the whole file.
+    pub tail: Option<usize>,
+    /// If the file doesn't exist yet, wait for it to be created instead of failing.
+    pub wait: bool,
+}
+
+impl FileSpec {
+    /// Parse `path` or `path:regex`. The split is only taken when the part before
+    /// a `:` names an existing file, so paths that themselves contain `:` still work.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let whole = Path::new(spec);
+        if !whole.exists() {
+            for (i, _) in spec.match_indices(':') {
+                let (path, pattern) = (&spec[..i], &spec[i + 1..]);
+                if path.is_empty() || !Path::new(path).is_file() {
+                    continue;
+                }
+                let line_start_regex = if pattern.trim().is_empty() {
+                    None
+                } else {
+                    let re = Regex::new(pattern)
+                        .map_err(|e| anyhow!("Invalid line start regex for {}: {}", path, e))?;
+                    Some(Arc::new(re))
+                };
+                return Ok(Self {
+                    path: PathBuf::from(path),
+                    line_start_regex,
+                    tail: None,
+                    wait: false,
+                });
+            }
+        }
+        Ok(Self {
+            path: whole.to_path_buf(),
+            line_start_regex: None,
+            tail: None,
+            wait: false,
+        })
+    }
 }
 
 pub enum SourceEvent {
     Line(String),
+    /// A line from the file at this position of `LogSource::Files`.
+    TaggedLine(usize, String),
+    /// A line from a network or socket client, tagged with its address.
+    PeerLine(String, String),
+    /// A line a spawned command wrote to stderr.
+    StderrLine(String),
     SystemLine(String),
     Error(String),
     Connected(String),
     Disconnected(String),
+    /// Everything already in a file has been sent; later lines were appended.
+    CaughtUp,
+    /// The source finished and will send nothing more, e.g. stdin was closed.
+    Eof,
+    /// Overwrite the last line sent: a progress bar redrew it after a bare `\r`.
+    ReplaceLast(String),
 }
 
-pub fn start_source(source: LogSource, tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
+pub fn start_source(
+    source: LogSource,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
     match source {
-        LogSource::File(path) => start_file_source(path, tx, line_start_regex),
+        LogSource::File(spec) => start_file_source(
+            spec.path,
+            spec.tail,
+            spec.wait,
+            tx,
+            spec.line_start_regex.or(line_start_regex),
+        ),
+        LogSource::Files(specs, timestamps) => {
+            start_files_source(specs, timestamps, tx, line_start_regex)
+        }
         LogSource::Stdin => start_stdin_source(tx, line_start_regex),
-        LogSource::Network(port) => start_network_source(port, tx, line_start_regex),
+        LogSource::Network(port, bind, tls) => {
+            start_network_source(port, bind, tls, tx, line_start_regex)
+        }
+        LogSource::Command(command) => start_command_source(command, tx, line_start_regex),
+        #[cfg(unix)]
+        LogSource::UnixSocket(path) => start_unix_source(path, tx, line_start_regex),
     }
 }
 
-fn start_file_source(path: PathBuf, tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
+fn start_file_source(
+    path: PathBuf,
+    tail: Option<usize>,
+    wait: bool,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
     let path_clone = path.clone();
     thread::spawn(move || {
-        if let Err(e) = run_file_source(path_clone, tx.clone(), line_start_regex) {
+        if let Err(e) = run_file_source(path_clone, tail, wait, tx.clone(), line_start_regex) {
             let _ = tx.send(SourceEvent::Error(e.to_string()));
         }
     });
@@ -45,18 +143,74 @@ fn start_file_source(path: PathBuf, tx: Sender<SourceEvent>, line_start_regex: O
 struct MultilineAggregator {
     regex: Option<Arc<Regex>>,
     pending: Option<String>,
+    /// Wraps each finished entry, e.g. `SourceEvent::Line`.
+    event: Box<dyn Fn(String) -> SourceEvent + Send>,
+    /// Whether redrawn lines may be sent as `SourceEvent::ReplaceLast`. Off for
+    /// streams that are interleaved with others, where the last line may not be ours.
+    overwrite: bool,
+    /// The last line ended in a bare `\r`, so the next fragment redraws it.
+    in_progress: bool,
 }
 
 impl MultilineAggregator {
     fn new(regex: Option<Arc<Regex>>) -> Self {
-        Self { regex, pending: None }
+        Self {
+            overwrite: true,
+            ..Self::with_event(regex, SourceEvent::Line)
+        }
     }
 
+    fn with_event(
+        regex: Option<Arc<Regex>>,
+        event: impl Fn(String) -> SourceEvent + Send + 'static,
+    ) -> Self {
+        Self {
+            regex,
+            pending: None,
+            event: Box::new(event),
+            overwrite: false,
+            in_progress: false,
+        }
+    }
+
+    /// Take one segment from `read_line_lossy`, which ends in `\n`, a bare
+    /// `\r`, or nothing at EOF. Text after a `\r` overwrites the line before it,
+    /// like a terminal; the `\n` of a `\r\n` just finishes the line.
     fn process_line(&mut self, line: &str, tx: &Sender<SourceEvent>) -> bool {
+        if let Some(fragment) = line.strip_suffix('\r') {
+            if fragment.is_empty() {
+                return true;
+            }
+            return if std::mem::replace(&mut self.in_progress, true) {
+                self.replace_last(fragment, tx)
+            } else {
+                self.push(fragment, tx)
+            };
+        }
         let trimmed = line.trim_end_matches(['\n', '\r']);
+        if std::mem::take(&mut self.in_progress) {
+            return trimmed.is_empty() || self.replace_last(trimmed, tx);
+        }
+        self.push(trimmed, tx)
+    }
+
+    /// Overwrite the last physical line of the pending entry, or the last line sent.
+    fn replace_last(&mut self, text: &str, tx: &Sender<SourceEvent>) -> bool {
+        if let Some(pending) = &mut self.pending {
+            pending.truncate(pending.rfind('\n').map_or(0, |at| at + 1));
+            pending.push_str(text);
+            true
+        } else if self.overwrite {
+            tx.send(SourceEvent::ReplaceLast(text.to_string())).is_ok()
+        } else {
+            self.push(text, tx)
+        }
+    }
+
+    fn push(&mut self, trimmed: &str, tx: &Sender<SourceEvent>) -> bool {
         match &self.regex {
             None => {
-                if tx.send(SourceEvent::Line(trimmed.to_string())).is_err() {
+                if tx.send((self.event)(trimmed.to_string())).is_err() {
                     return false;
                 }
             }
@@ -64,7 +218,7 @@ impl MultilineAggregator {
                 let is_start = re.is_match(trimmed).unwrap_or(false);
                 if is_start {
                     if let Some(pending) = self.pending.take() {
-                        if tx.send(SourceEvent::Line(pending)).is_err() {
+                        if tx.send((self.event)(pending)).is_err() {
                             return false;
                         }
                     }
@@ -87,96 +241,479 @@ impl MultilineAggregator {
 
     fn flush(&mut self, tx: &Sender<SourceEvent>) {
         if let Some(pending) = self.pending.take() {
-            let _ = tx.send(SourceEvent::Line(pending));
+            let _ = tx.send((self.event)(pending));
         }
     }
 }
 
-fn run_file_source(path: PathBuf, tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
-    let mut file = File::open(&path)?;
-    let mut reader = BufReader::new(&file);
-    let mut line = String::new();
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+fn is_gzip(path: &Path) -> Result<bool> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        return Ok(true);
+    }
+    let mut magic = [0u8; 2];
+    let mut file = File::open(path)?;
+    Ok(file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC)
+}
+
+/// The decompressed contents of `path`, or only its last `tail` lines. There is
+/// no seeking in a compressed file, so those are found by decompressing it all.
+fn open_gzip(path: &Path, tail: Option<usize>) -> Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(MultiGzDecoder::new(File::open(path)?));
+    let Some(n) = tail else {
+        return Ok(Box::new(reader));
+    };
+    let mut last: VecDeque<Vec<u8>> = VecDeque::new();
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        last.push_back(line);
+        if last.len() > n {
+            last.pop_front();
+        }
+    }
+    Ok(Box::new(Cursor::new(
+        last.into_iter().flatten().collect::<Vec<u8>>(),
+    )))
+}
+
+/// Compressed files can't be followed, so decompress once and stop at EOF.
+fn run_gzip_file_source(
+    path: &Path,
+    tail: Option<usize>,
+    tx: &Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
+    let mut reader = open_gzip(path, tail)?;
     let mut aggregator = MultilineAggregator::new(line_start_regex);
+    if read_available(&mut reader, &mut aggregator, tx)? {
+        aggregator.flush(tx);
+        let _ = tx.send(SourceEvent::CaughtUp);
+        let _ = tx.send(SourceEvent::Eof);
+    }
+    Ok(())
+}
 
-    while reader.read_line(&mut line)? > 0 {
-        if !aggregator.process_line(&line, &tx) {
-            return Ok(());
+/// Read the next line including its `\n` or `\r`, decoding invalid UTF-8 as
+/// U+FFFD instead of failing. Stopping at `\r` lets a progress bar's redraws
+/// through as they happen. `None` at EOF.
+fn read_line_lossy<R: BufRead>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<String>> {
+    buf.clear();
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            break;
+        }
+        let (len, done) = match available.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        buf.extend_from_slice(&available[..len]);
+        reader.consume(len);
+        if done {
+            break;
         }
-        line.clear();
+    }
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(buf).into_owned()))
+}
+
+fn run_file_source(
+    path: PathBuf,
+    tail: Option<usize>,
+    wait: bool,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
+    if wait && !path.exists() {
+        wait_for_file(&path, &tx)?;
+    }
+    if is_gzip(&path)? {
+        return run_gzip_file_source(&path, tail, &tx, line_start_regex);
+    }
+    let mut reader = open_file(&path, tail)?;
+    let mut aggregator = MultilineAggregator::new(line_start_regex);
+    if !read_available(&mut reader, &mut aggregator, &tx)? {
+        return Ok(());
     }
     aggregator.flush(&tx);
+    let _ = tx.send(SourceEvent::CaughtUp);
+    follow_file(&path, reader, aggregator, &tx)
+}
 
-    let mut pos = file.seek(SeekFrom::Current(0))?;
+/// Block until `path` exists, watching its directory for it to be created.
+fn wait_for_file(path: &Path, tx: &Sender<SourceEvent>) -> Result<()> {
+    let _ = tx.send(SourceEvent::SystemLine(format!(
+        "[waiting for {}]",
+        path.display()
+    )));
+    let (notify_tx, notify_rx): (
+        Sender<notify::Result<Event>>,
+        Receiver<notify::Result<Event>>,
+    ) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            let _ = notify_tx.send(res);
+        },
+        notify::Config::default().with_poll_interval(Duration::from_millis(100)),
+    )?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // A directory that doesn't exist yet can't be watched; the timeout below
+    // still notices the file once both are created.
+    let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+    while !path.exists() {
+        let _ = notify_rx.recv_timeout(Duration::from_millis(500));
+    }
+    Ok(())
+}
 
-    let (notify_tx, notify_rx): (Sender<notify::Result<Event>>, Receiver<notify::Result<Event>>) =
-        mpsc::channel();
+/// Open `path`, positioned at its last `tail` lines if given.
+fn open_file(path: &Path, tail: Option<usize>) -> Result<BufReader<File>> {
+    let mut file = File::open(path)?;
+    if let Some(n) = tail {
+        let start = read_last_n_lines(&mut file, n)?;
+        file.seek(SeekFrom::Start(start))?;
+    }
+    Ok(BufReader::new(file))
+}
+
+/// Send lines appended to `path` after `reader`'s position until the receiver
+/// is gone, reopening the file when it is rotated.
+fn follow_file(
+    path: &Path,
+    mut reader: BufReader<File>,
+    mut aggregator: MultilineAggregator,
+    tx: &Sender<SourceEvent>,
+) -> Result<()> {
+    let mut identity = file_identity(&reader.get_ref().metadata()?);
+    let (notify_tx, notify_rx): (
+        Sender<notify::Result<Event>>,
+        Receiver<notify::Result<Event>>,
+    ) = mpsc::channel();
     let mut watcher = RecommendedWatcher::new(
         move |res| {
             let _ = notify_tx.send(res);
         },
         notify::Config::default().with_poll_interval(Duration::from_millis(100)),
     )?;
-    watcher.watch(&path, RecursiveMode::NonRecursive)?;
+    watcher.watch(path, RecursiveMode::NonRecursive)?;
 
     loop {
         match notify_rx.recv_timeout(Duration::from_millis(500)) {
             Ok(Ok(_)) | Err(mpsc::RecvTimeoutError::Timeout) => {
-                file = File::open(&path)?;
-                file.seek(SeekFrom::Start(pos))?;
-                reader = BufReader::new(&file);
-
-                while reader.read_line(&mut line)? > 0 {
-                    if !aggregator.process_line(&line, &tx) {
+                // Drain the open handle first: after a rename it still
+                // points at the old file, which may have a last few lines.
+                if !read_available(&mut reader, &mut aggregator, tx)? {
+                    return Ok(());
+                }
+                // Missing while being rotated; wait for it to be recreated.
+                let Ok(metadata) = fs::metadata(path) else {
+                    continue;
+                };
+                let pos = reader.stream_position()?;
+                if file_identity(&metadata) != identity || metadata.len() < pos {
+                    reader = BufReader::new(File::open(path)?);
+                    identity = file_identity(&reader.get_ref().metadata()?);
+                    aggregator.flush(tx);
+                    let _ = tx.send(SourceEvent::SystemLine("[log rotated]".to_string()));
+                    // Re-arm the watch, which follows the old inode on Linux.
+                    let _ = watcher.unwatch(path);
+                    watcher.watch(path, RecursiveMode::NonRecursive)?;
+                    if !read_available(&mut reader, &mut aggregator, tx)? {
                         return Ok(());
                     }
-                    line.clear();
                 }
-                pos = file.seek(SeekFrom::Current(0))?;
             }
             Ok(Err(e)) => {
                 let _ = tx.send(SourceEvent::Error(e.to_string()));
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
-                aggregator.flush(&tx);
+                aggregator.flush(tx);
                 return Ok(());
             }
         }
     }
 }
 
-fn start_stdin_source(tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
+fn start_files_source(
+    specs: Vec<FileSpec>,
+    timestamps: TimestampParser,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
     thread::spawn(move || {
-        let stdin = std::io::stdin();
-        let reader = BufReader::new(stdin.lock());
-        let mut aggregator = MultilineAggregator::new(line_start_regex);
-        for line in reader.lines() {
-            match line {
-                Ok(l) => {
-                    if !aggregator.process_line(&l, &tx) {
-                        break;
+        let mut backlogs = Vec::new();
+        let mut followers = Vec::new();
+        for (id, spec) in specs.into_iter().enumerate() {
+            let regex = spec.line_start_regex.or_else(|| line_start_regex.clone());
+            match read_backlog(&spec.path, spec.tail, regex.clone()) {
+                Ok((lines, reader)) => {
+                    backlogs.push(lines);
+                    if let Some(reader) = reader {
+                        followers.push((id, spec.path, reader, regex));
                     }
                 }
                 Err(e) => {
-                    let _ = tx.send(SourceEvent::Error(e.to_string()));
-                    break;
+                    backlogs.push(Vec::new());
+                    let _ = tx.send(SourceEvent::Error(format!(
+                        "{}: {}",
+                        spec.path.display(),
+                        e
+                    )));
                 }
             }
         }
-        aggregator.flush(&tx);
+        for (id, line) in merge_by_timestamp(backlogs, &timestamps) {
+            if tx.send(SourceEvent::TaggedLine(id, line)).is_err() {
+                return;
+            }
+        }
+        if followers.is_empty() {
+            let _ = tx.send(SourceEvent::Eof);
+        }
+        for (id, path, reader, regex) in followers {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let aggregator = MultilineAggregator::with_event(regex, move |line| {
+                    SourceEvent::TaggedLine(id, line)
+                });
+                if let Err(e) = follow_file(&path, reader, aggregator, &tx) {
+                    let _ = tx.send(SourceEvent::Error(format!("{}: {}", path.display(), e)));
+                }
+            });
+        }
     });
     Ok(())
 }
 
-fn start_network_source(port: u16, tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
-    let listener = TcpListener::bind(format!("[::]:{}", port))
-        .or_else(|_| TcpListener::bind(format!("0.0.0.0:{}", port)))?;
+/// The entries currently in `path`, and a reader positioned after them to
+/// follow from. Compressed files have nothing to follow.
+fn read_backlog(
+    path: &Path,
+    tail: Option<usize>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<(Vec<String>, Option<BufReader<File>>)> {
+    let (tx, rx) = mpsc::channel();
+    let mut aggregator = MultilineAggregator::new(line_start_regex);
+    let reader = if is_gzip(path)? {
+        let mut reader = open_gzip(path, tail)?;
+        read_available(&mut reader, &mut aggregator, &tx)?;
+        None
+    } else {
+        let mut reader = open_file(path, tail)?;
+        read_available(&mut reader, &mut aggregator, &tx)?;
+        Some(reader)
+    };
+    aggregator.flush(&tx);
+    drop(tx);
+    let mut lines: Vec<String> = Vec::new();
+    for event in rx {
+        match event {
+            SourceEvent::Line(line) => lines.push(line),
+            SourceEvent::ReplaceLast(line) => match lines.last_mut() {
+                Some(last) => *last = line,
+                None => lines.push(line),
+            },
+            _ => {}
+        }
+    }
+    Ok((lines, reader))
+}
+
+/// Interleave the lines of several files by timestamp, tagged with the index
+/// of their file. Each file keeps its own order, and a line without a
+/// timestamp goes with the one before it.
+fn merge_by_timestamp(
+    files: Vec<Vec<String>>,
+    timestamps: &TimestampParser,
+) -> Vec<(usize, String)> {
+    let mut keyed: Vec<VecDeque<(Option<DateTime<Local>>, String)>> = files
+        .into_iter()
+        .map(|lines| {
+            let mut last = None;
+            lines
+                .into_iter()
+                .map(|line| {
+                    last = timestamps.parse(&line).or(last);
+                    (last, line)
+                })
+                .collect()
+        })
+        .collect();
+    let mut merged = Vec::new();
+    while let Some(id) = (0..keyed.len())
+        .filter(|&id| !keyed[id].is_empty())
+        .min_by_key(|&id| keyed[id][0].0)
+    {
+        let (_, line) = keyed[id].pop_front().unwrap();
+        merged.push((id, line));
+    }
+    merged
+}
+
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Byte offset where the last `n` lines of `file` start, found by reading
+/// fixed-size chunks backward from the end and counting newlines.
+fn read_last_n_lines(file: &mut File, n: usize) -> Result<u64> {
+    let len = file.metadata()?.len();
+    if n == 0 {
+        return Ok(len);
+    }
+    let mut end = len;
+    let mut newlines = 0;
+    let mut chunk = vec![0u8; TAIL_CHUNK_SIZE as usize];
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_CHUNK_SIZE);
+        let buf = &mut chunk[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(buf)?;
+        for (i, &byte) in buf.iter().enumerate().rev() {
+            let pos = start + i as u64;
+            // The newline ending the last line doesn't start another one.
+            if byte == b'\n' && pos + 1 != len {
+                newlines += 1;
+                if newlines == n {
+                    return Ok(pos + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Feed every complete line currently readable; `false` once the receiver is gone.
+fn read_available<R: BufRead>(
+    reader: &mut R,
+    aggregator: &mut MultilineAggregator,
+    tx: &Sender<SourceEvent>,
+) -> Result<bool> {
+    let mut buf = Vec::new();
+    while let Some(line) = read_line_lossy(reader, &mut buf)? {
+        if !aggregator.process_line(&line, tx) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Device and inode, to notice the path being replaced by a new file.
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn start_stdin_source(tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) -> Result<()> {
+    thread::spawn(move || {
+        let stdin = std::io::stdin();
+        if stdin.is_terminal() {
+            let _ = tx.send(SourceEvent::SystemLine(STDIN_NO_INPUT_MESSAGE.to_string()));
+            return;
+        }
+        run_stdin_reader(stdin.lock(), &tx, line_start_regex);
+    });
+    Ok(())
+}
+
+const STDIN_NO_INPUT_MESSAGE: &str = "[stdin closed / no input — pass a file or -l PORT]";
+
+/// Stream lines from stdin until EOF, then report that input finished.
+/// An immediately-closed pipe gets a hint instead of a silent empty view.
+fn run_stdin_reader<R: BufRead>(
+    mut reader: R,
+    tx: &Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) {
+    let mut aggregator = MultilineAggregator::new(line_start_regex);
+    let mut line_count = 0usize;
+    let mut buf = Vec::new();
+    loop {
+        match read_line_lossy(&mut reader, &mut buf) {
+            Ok(Some(l)) => {
+                if !l.ends_with('\r') {
+                    line_count += 1;
+                }
+                if !aggregator.process_line(&l, tx) {
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                let _ = tx.send(SourceEvent::Error(e.to_string()));
+                break;
+            }
+        }
+    }
+    aggregator.flush(tx);
+    let end_message = if line_count == 0 {
+        STDIN_NO_INPUT_MESSAGE.to_string()
+    } else {
+        format!("[end of stdin: {} lines]", line_count)
+    };
+    let _ = tx.send(SourceEvent::SystemLine(end_message));
+    let _ = tx.send(SourceEvent::Eof);
+}
+
+/// Server config for a PEM certificate chain and private key.
+pub fn load_tls_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| anyhow!("Failed to read certificate {}: {}", cert.display(), e))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| anyhow!("Failed to read key {}: {}", key.display(), e))?;
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| anyhow!("Invalid TLS certificate or key: {}", e))?;
+    Ok(Arc::new(config))
+}
+
+fn start_network_source(
+    port: u16,
+    bind: Option<IpAddr>,
+    tls: Option<Arc<ServerConfig>>,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
+    let listener = match bind {
+        Some(addr) => {
+            let addr = SocketAddr::new(addr, port);
+            TcpListener::bind(addr).map_err(|e| anyhow!("Can't listen on {}: {}", addr, e))?
+        }
+        None => TcpListener::bind(format!("[::]:{}", port))
+            .or_else(|_| TcpListener::bind(format!("0.0.0.0:{}", port)))
+            .map_err(|e| anyhow!("Can't listen on port {}: {}", port, e))?,
+    };
     thread::spawn(move || {
         for stream in listener.incoming() {
             match stream {
                 Ok(s) => {
                     let tx_clone = tx.clone();
                     let regex_clone = line_start_regex.clone();
-                    thread::spawn(move || handle_client(s, tx_clone, regex_clone));
+                    let tls_clone = tls.clone();
+                    thread::spawn(move || handle_client(s, tls_clone, tx_clone, regex_clone));
                 }
                 Err(e) => {
                     let _ = tx.send(SourceEvent::Error(format!("Accept error: {}", e)));
@@ -187,39 +724,101 @@ fn start_network_source(port: u16, tx: Sender<SourceEvent>, line_start_regex: Op
     Ok(())
 }
 
-fn handle_client(stream: TcpStream, tx: Sender<SourceEvent>, line_start_regex: Option<Arc<Regex>>) {
+fn handle_client(
+    stream: TcpStream,
+    tls: Option<Arc<ServerConfig>>,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) {
     let peer = stream
         .peer_addr()
         .map(|a| a.to_string())
         .unwrap_or_else(|_| "unknown".to_string());
-    
+    // Without the port, so a client keeps its tag across reconnects.
+    let tag = stream
+        .peer_addr()
+        .map(|a| a.ip().to_string())
+        .unwrap_or_else(|_| peer.clone());
+
     if let Err(e) = stream.set_nodelay(true) {
-        let _ = tx.send(SourceEvent::Error(format!("Failed to set TCP_NODELAY: {}", e)));
+        let _ = tx.send(SourceEvent::Error(format!(
+            "Failed to set TCP_NODELAY: {}",
+            e
+        )));
     }
-    
+
     let keepalive = socket2::TcpKeepalive::new()
         .with_time(Duration::from_secs(10))
         .with_interval(Duration::from_secs(5));
-    
+
     let socket_ref = socket2::SockRef::from(&stream);
     if let Err(e) = socket_ref.set_tcp_keepalive(&keepalive) {
-        let _ = tx.send(SourceEvent::Error(format!("Failed to set TCP keepalive: {}", e)));
+        let _ = tx.send(SourceEvent::Error(format!(
+            "Failed to set TCP keepalive: {}",
+            e
+        )));
     }
-    
+
+    match tls {
+        Some(config) => match accept_tls(stream, config) {
+            Ok(stream) => read_client(stream, peer, tag, tx, line_start_regex),
+            Err(e) => {
+                let _ = tx.send(SourceEvent::Error(format!(
+                    "TLS handshake with {} failed: {}",
+                    peer, e
+                )));
+            }
+        },
+        None => read_client(&stream, peer, tag, tx, line_start_regex),
+    }
+}
+
+/// Finish the handshake up front, so a client that doesn't speak TLS is
+/// reported as such instead of as a read error.
+fn accept_tls(
+    mut stream: TcpStream,
+    config: Arc<ServerConfig>,
+) -> std::io::Result<StreamOwned<ServerConnection, TcpStream>> {
+    let mut conn = ServerConnection::new(config).map_err(std::io::Error::other)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream)?;
+    }
+    Ok(StreamOwned::new(conn, stream))
+}
+
+/// Stream lines from one connection, bracketed by connect/disconnect events.
+/// Lines are tagged with `tag`.
+fn read_client<R: Read>(
+    stream: R,
+    peer: String,
+    tag: String,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) {
     let _ = tx.send(SourceEvent::Connected(peer.clone()));
     let _ = tx.send(SourceEvent::SystemLine(format!("[connected: {}]", peer)));
 
-    let reader = BufReader::new(&stream);
-    let mut aggregator = MultilineAggregator::new(line_start_regex);
-    for line in reader.lines() {
-        match line {
-            Ok(l) => {
+    let mut reader = BufReader::new(stream);
+    // Clients share one channel, so a redraw is sent as a new line.
+    let mut aggregator = MultilineAggregator::with_event(line_start_regex, move |line| {
+        SourceEvent::PeerLine(tag.clone(), line)
+    });
+    let mut buf = Vec::new();
+    loop {
+        match read_line_lossy(&mut reader, &mut buf) {
+            Ok(Some(l)) => {
                 if !aggregator.process_line(&l, &tx) {
                     break;
                 }
             }
+            Ok(None) => break,
+            // A TLS client that hung up without close_notify; log senders commonly do.
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => {
-                let _ = tx.send(SourceEvent::Error(format!("Read error from {}: {}", peer, e)));
+                let _ = tx.send(SourceEvent::Error(format!(
+                    "Read error from {}: {}",
+                    peer, e
+                )));
                 break;
             }
         }
@@ -228,3 +827,562 @@ fn handle_client(stream: TcpStream, tx: Sender<SourceEvent>, line_start_regex: O
     let _ = tx.send(SourceEvent::SystemLine(format!("[disconnected: {}]", peer)));
     let _ = tx.send(SourceEvent::Disconnected(peer));
 }
+
+/// Spawn `command` and stream its stdout and stderr until it exits. Failing to
+/// start it is reported right away.
+fn start_command_source(
+    command: Vec<String>,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No command given"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run {}: {}", program, e))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let readers = [
+        spawn_pipe_reader(
+            stdout,
+            tx.clone(),
+            line_start_regex.clone(),
+            SourceEvent::Line,
+        ),
+        spawn_pipe_reader(
+            stderr,
+            tx.clone(),
+            line_start_regex,
+            SourceEvent::StderrLine,
+        ),
+    ];
+    thread::spawn(move || {
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let message = match child.wait() {
+            Ok(status) => match status.code() {
+                Some(code) => format!("[process exited: code {}]", code),
+                None => format!("[process exited: {}]", status),
+            },
+            Err(e) => format!("[process exited: {}]", e),
+        };
+        let _ = tx.send(SourceEvent::SystemLine(message));
+        let _ = tx.send(SourceEvent::Eof);
+    });
+    Ok(())
+}
+
+fn spawn_pipe_reader<R: Read + Send + 'static>(
+    pipe: R,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+    event: fn(String) -> SourceEvent,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut aggregator = MultilineAggregator::with_event(line_start_regex, event);
+        match read_available(&mut BufReader::new(pipe), &mut aggregator, &tx) {
+            Ok(true) => aggregator.flush(&tx),
+            Ok(false) => {}
+            Err(e) => {
+                let _ = tx.send(SourceEvent::Error(e.to_string()));
+            }
+        }
+    })
+}
+
+#[cfg(unix)]
+static UNIX_SOCKET_PATH: std::sync::Mutex<Option<PathBuf>> = std::sync::Mutex::new(None);
+
+/// Listen on a Unix domain socket. A leftover socket file nobody is
+/// listening on is replaced; a live one is an error.
+#[cfg(unix)]
+fn start_unix_source(
+    path: PathBuf,
+    tx: Sender<SourceEvent>,
+    line_start_regex: Option<Arc<Regex>>,
+) -> Result<()> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(anyhow!("{} is already in use", path.display()));
+        }
+        fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    *UNIX_SOCKET_PATH.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.clone());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(s) => {
+                    let tx_clone = tx.clone();
+                    let regex_clone = line_start_regex.clone();
+                    let peer = path.display().to_string();
+                    thread::spawn(move || {
+                        read_client(s, peer.clone(), peer, tx_clone, regex_clone)
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(SourceEvent::Error(format!("Accept error: {}", e)));
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Remove files the sources created, i.e. a listening Unix socket. Call before exiting.
+pub fn cleanup() {
+    #[cfg(unix)]
+    if let Some(path) = UNIX_SOCKET_PATH
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+    {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_system_lines(rx: &Receiver<SourceEvent>) -> Vec<String> {
+        rx.try_iter()
+            .filter_map(|event| match event {
+                SourceEvent::SystemLine(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn aggregate(input: &str, regex: Option<Arc<Regex>>) -> Vec<String> {
+        let (tx, rx) = mpsc::channel();
+        let mut aggregator = MultilineAggregator::new(regex);
+        for line in input.lines() {
+            aggregator.process_line(line, &tx);
+        }
+        aggregator.flush(&tx);
+        rx.try_iter()
+            .filter_map(|event| match event {
+                SourceEvent::Line(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_per_file_line_start() {
+        let dir = std::env::temp_dir();
+        let iso_path = dir.join(format!("logviewer-iso-{}.log", std::process::id()));
+        let level_path = dir.join(format!("logviewer-level-{}.log", std::process::id()));
+        let iso_log = "2024-01-02 first\n  at frame\n2024-01-03 second\n";
+        let level_log = "[INFO] start\ncontinued\n[WARN] next\n";
+        std::fs::write(&iso_path, iso_log).unwrap();
+        std::fs::write(&level_path, level_log).unwrap();
+
+        let global = Some(Arc::new(Regex::new(r"^never").unwrap()));
+        let iso = FileSpec::parse(&format!("{}:^\\d{{4}}-", iso_path.display())).unwrap();
+        let level = FileSpec::parse(&format!("{}:^\\[[A-Z]+\\]", level_path.display())).unwrap();
+        let plain = FileSpec::parse(&iso_path.display().to_string()).unwrap();
+
+        assert_eq!(iso.path, iso_path);
+        assert_eq!(level.path, level_path);
+        assert_eq!(plain.path, iso_path);
+        assert!(plain.line_start_regex.is_none());
+
+        assert_eq!(
+            aggregate(iso_log, iso.line_start_regex.or(global.clone())),
+            vec!["2024-01-02 first\n  at frame", "2024-01-03 second"]
+        );
+        assert_eq!(
+            aggregate(level_log, level.line_start_regex.or(global.clone())),
+            vec!["[INFO] start\ncontinued", "[WARN] next"]
+        );
+        assert_eq!(
+            aggregate(level_log, plain.line_start_regex.or(global)),
+            vec!["[INFO] start\ncontinued\n[WARN] next"]
+        );
+
+        let _ = std::fs::remove_file(iso_path);
+        let _ = std::fs::remove_file(level_path);
+    }
+
+    #[test]
+    fn test_gzip_file_is_decompressed_and_aggregated() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        // No .gz extension, so detection has to come from the magic bytes.
+        let path =
+            std::env::temp_dir().join(format!("logviewer-gzip-{}.log.1", std::process::id()));
+        let mut encoder =
+            GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+        encoder
+            .write_all(b"2024-01-02 boom\n\tat Foo.bar(Foo.java:1)\n2024-01-03 ok\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let regex = Some(Arc::new(Regex::new(r"^\d{4}-").unwrap()));
+        run_file_source(path.clone(), None, false, tx, regex).unwrap();
+        let lines: Vec<String> = rx
+            .try_iter()
+            .filter_map(|event| match event {
+                SourceEvent::Line(s) => Some(s),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["2024-01-02 boom\n\tat Foo.bar(Foo.java:1)", "2024-01-03 ok"]
+        );
+
+        // --tail counts decompressed lines.
+        let (tx, rx) = mpsc::channel();
+        run_file_source(path.clone(), Some(2), false, tx, None).unwrap();
+        let lines: Vec<String> = rx
+            .try_iter()
+            .filter_map(|event| match event {
+                SourceEvent::Line(s) => Some(s),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, vec!["\tat Foo.bar(Foo.java:1)", "2024-01-03 ok"]);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_read_last_n_lines() {
+        let path = std::env::temp_dir().join(format!("logviewer-tail-{}.log", std::process::id()));
+        let mut content: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, &content).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let start = read_last_n_lines(&mut file, 3).unwrap() as usize;
+        assert_eq!(&content[start..], "line 19997\nline 19998\nline 19999\n");
+        assert_eq!(read_last_n_lines(&mut file, 50_000).unwrap(), 0);
+        assert_eq!(
+            read_last_n_lines(&mut file, 0).unwrap(),
+            content.len() as u64
+        );
+
+        // An unterminated last line still counts as one.
+        content.push_str("partial");
+        std::fs::write(&path, &content).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let start = read_last_n_lines(&mut file, 2).unwrap() as usize;
+        assert_eq!(&content[start..], "line 19999\npartial");
+
+        let (tx, rx) = mpsc::channel();
+        let source_path = path.clone();
+        thread::spawn(move || run_file_source(source_path, Some(2), false, tx, None));
+        let first = match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SourceEvent::Line(s) => s,
+            _ => String::new(),
+        };
+        assert_eq!(first, "line 19999");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_wait_for_missing_file() {
+        let path = std::env::temp_dir().join(format!("logviewer-wait-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (tx, rx) = mpsc::channel();
+        let source_path = path.clone();
+        thread::spawn(move || run_file_source(source_path, None, true, tx, None));
+        let waiting = match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SourceEvent::SystemLine(s) => s,
+            _ => String::new(),
+        };
+        assert_eq!(waiting, format!("[waiting for {}]", path.display()));
+
+        std::fs::write(&path, "hello\n").unwrap();
+        let first = match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SourceEvent::Line(s) => s,
+            _ => String::new(),
+        };
+        assert_eq!(first, "hello");
+
+        let _ = std::fs::remove_file(path);
+        let missing =
+            std::env::temp_dir().join(format!("logviewer-missing-{}.log", std::process::id()));
+        let (tx, _rx) = mpsc::channel();
+        assert!(run_file_source(missing, None, false, tx, None).is_err());
+    }
+
+    #[test]
+    fn test_merge_by_timestamp() {
+        let access = vec![
+            "2024-01-01 10:00:00 GET /".to_string(),
+            "2024-01-01 10:00:05 GET /login".to_string(),
+        ];
+        let error = vec![
+            "2024-01-01 10:00:02 db timeout".to_string(),
+            "  at pool.rs:10".to_string(),
+            "2024-01-01 10:00:09 retry failed".to_string(),
+        ];
+        let merged = merge_by_timestamp(vec![access, error], &TimestampParser::default());
+        let order: Vec<(usize, &str)> = merged
+            .iter()
+            .map(|(id, line)| (*id, line.rsplit(' ').next().unwrap()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (0, "/"),
+                (1, "timeout"),
+                (1, "pool.rs:10"),
+                (0, "/login"),
+                (1, "failed")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files_source_tags_lines() {
+        let dir = std::env::temp_dir();
+        let first = dir.join(format!("logviewer-multi-a-{}.log", std::process::id()));
+        let second = dir.join(format!("logviewer-multi-b-{}.log", std::process::id()));
+        std::fs::write(&first, "2024-01-01 10:00:03 a\n").unwrap();
+        std::fs::write(&second, "2024-01-01 10:00:01 b\n").unwrap();
+        let specs = [&first, &second]
+            .iter()
+            .map(|path| FileSpec::parse(path.to_str().unwrap()).unwrap())
+            .collect();
+
+        let (tx, rx) = mpsc::channel();
+        start_files_source(specs, TimestampParser::default(), tx, None).unwrap();
+        let next = || match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SourceEvent::TaggedLine(id, line) => (id, line),
+            _ => (usize::MAX, String::new()),
+        };
+        assert_eq!(next(), (1, "2024-01-01 10:00:01 b".to_string()));
+        assert_eq!(next(), (0, "2024-01-01 10:00:03 a".to_string()));
+
+        thread::sleep(Duration::from_millis(200));
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&second)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, b"later\n"))
+            .unwrap();
+        assert_eq!(next(), (1, "later".to_string()));
+
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(second);
+    }
+
+    #[test]
+    fn test_rotated_file_is_followed() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("logviewer-rotate-{}.log", std::process::id()));
+        let rotated = dir.join(format!("logviewer-rotate-{}.log.1", std::process::id()));
+        std::fs::write(&path, "before\n").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let source_path = path.clone();
+        thread::spawn(move || run_file_source(source_path, None, false, tx, None));
+        let next = || match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            SourceEvent::Line(s) | SourceEvent::SystemLine(s) => s,
+            _ => String::new(),
+        };
+        assert_eq!(next(), "before");
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SourceEvent::CaughtUp)
+        ));
+
+        std::fs::rename(&path, &rotated).unwrap();
+        std::fs::write(&path, "after\n").unwrap();
+        assert_eq!(next(), "[log rotated]");
+        assert_eq!(next(), "after");
+
+        // Truncation in place (copytruncate) is a rotation too.
+        std::fs::write(&path, "").unwrap();
+        thread::sleep(Duration::from_millis(700));
+        std::fs::write(&path, "x\n").unwrap();
+        assert_eq!(next(), "[log rotated]");
+        assert_eq!(next(), "x");
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(rotated);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket_source() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("logviewer-{}.sock", std::process::id()));
+        // A stale socket file from an earlier run is replaced.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let (tx, rx) = mpsc::channel();
+        start_unix_source(path.clone(), tx, None).unwrap();
+        assert!(start_unix_source(path.clone(), mpsc::channel().0, None).is_err());
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"hello\n").unwrap();
+        drop(client);
+
+        // The in-use probe above shows up as a connection of its own.
+        let mut events = Vec::new();
+        while let Ok(event) = rx.recv_timeout(Duration::from_secs(5)) {
+            let done = matches!(&event, SourceEvent::PeerLine(..));
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        let peer = path.display().to_string();
+        assert!(matches!(&events[0], SourceEvent::Connected(p) if *p == peer));
+        assert!(
+            matches!(events.last(), Some(SourceEvent::PeerLine(p, l)) if *p == peer && l == "hello")
+        );
+
+        cleanup();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_network_source_binds_given_address() {
+        use std::io::Write;
+
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+        let port = TcpListener::bind((localhost, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (tx, rx) = mpsc::channel();
+        start_network_source(port, Some(localhost), None, tx, None).unwrap();
+        let err =
+            start_network_source(port, Some(localhost), None, mpsc::channel().0, None).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("Can't listen on 127.0.0.1:{}", port)),
+            "{}",
+            err
+        );
+
+        let mut client = TcpStream::connect((localhost, port)).unwrap();
+        client.write_all(b"hello\n").unwrap();
+        drop(client);
+
+        let mut line = None;
+        while let Ok(event) = rx.recv_timeout(Duration::from_secs(5)) {
+            if let SourceEvent::PeerLine(peer, content) = event {
+                line = Some((peer, content));
+                break;
+            }
+        }
+        assert_eq!(line, Some(("127.0.0.1".to_string(), "hello".to_string())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_source_tags_stderr_and_reports_exit() {
+        let (tx, rx) = mpsc::channel();
+        let command = ["sh", "-c", "echo out; echo err >&2; exit 3"];
+        start_command_source(command.iter().map(|s| s.to_string()).collect(), tx, None).unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.recv_timeout(Duration::from_secs(5)) {
+            let done = matches!(&event, SourceEvent::SystemLine(_));
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SourceEvent::Line(l) if l == "out")));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SourceEvent::StderrLine(l) if l == "err")));
+        assert!(
+            matches!(events.last(), Some(SourceEvent::SystemLine(l)) if l == "[process exited: code 3]")
+        );
+
+        assert!(start_command_source(
+            vec!["logviewer-no-such-program".to_string()],
+            mpsc::channel().0,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_spec_rejects_bad_regex() {
+        let path = std::env::temp_dir().join(format!("logviewer-bad-{}.log", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        assert!(FileSpec::parse(&format!("{}:(unclosed", path.display())).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_stdin_closed_immediately() {
+        let (tx, rx) = mpsc::channel();
+        run_stdin_reader(std::io::empty(), &tx, None);
+        let system = collect_system_lines(&rx);
+        assert_eq!(system, vec![STDIN_NO_INPUT_MESSAGE.to_string()]);
+        assert!(system[0].contains("pass a file or -l PORT"));
+    }
+
+    #[test]
+    fn test_invalid_utf8_does_not_stop_the_stream() {
+        let (tx, rx) = mpsc::channel();
+        let regex = Some(Arc::new(Regex::new(r"^\[").unwrap()));
+        run_stdin_reader(&b"[1] caf\xe9\n  more \xff\xfe\n[2] ok\n"[..], &tx, regex);
+        let events: Vec<SourceEvent> = rx.try_iter().collect();
+        assert!(
+            matches!(&events[0], SourceEvent::Line(l) if l == "[1] caf\u{fffd}\n  more \u{fffd}\u{fffd}")
+        );
+        assert!(matches!(&events[1], SourceEvent::Line(l) if l == "[2] ok"));
+        assert!(matches!(&events[2], SourceEvent::SystemLine(l) if l == "[end of stdin: 3 lines]"));
+    }
+
+    #[test]
+    fn test_stdin_end_reported_after_lines() {
+        let (tx, rx) = mpsc::channel();
+        run_stdin_reader(&b"one\ntwo\n"[..], &tx, None);
+        let events: Vec<SourceEvent> = rx.try_iter().collect();
+        assert!(matches!(&events[0], SourceEvent::Line(l) if l == "one"));
+        assert!(matches!(&events[1], SourceEvent::Line(l) if l == "two"));
+        assert!(matches!(&events[2], SourceEvent::SystemLine(l) if l == "[end of stdin: 2 lines]"));
+        assert!(matches!(events[3], SourceEvent::Eof));
+    }
+
+    #[test]
+    fn test_carriage_return_overwrites_line() {
+        let (tx, rx) = mpsc::channel();
+        run_stdin_reader(&b"start\n10%\r20%\r30%\ndone\r\n"[..], &tx, None);
+        let events: Vec<SourceEvent> = rx.try_iter().collect();
+        assert!(matches!(&events[0], SourceEvent::Line(l) if l == "start"));
+        assert!(matches!(&events[1], SourceEvent::Line(l) if l == "10%"));
+        assert!(matches!(&events[2], SourceEvent::ReplaceLast(l) if l == "20%"));
+        assert!(matches!(&events[3], SourceEvent::ReplaceLast(l) if l == "30%"));
+        // A CRLF line ending is not a redraw.
+        assert!(matches!(&events[4], SourceEvent::Line(l) if l == "done"));
+        assert!(matches!(&events[5], SourceEvent::SystemLine(l) if l == "[end of stdin: 3 lines]"));
+
+        // Within a multi-line entry only its last physical line is redrawn.
+        let regex = Some(Arc::new(Regex::new(r"^\[").unwrap()));
+        let (tx, rx) = mpsc::channel();
+        run_stdin_reader(&b"[1] copy\n  1/3\r  3/3\n[2] ok\n"[..], &tx, regex);
+        let events: Vec<SourceEvent> = rx.try_iter().collect();
+        assert!(matches!(&events[0], SourceEvent::Line(l) if l == "[1] copy\n  3/3"));
+        assert!(matches!(&events[1], SourceEvent::Line(l) if l == "[2] ok"));
+    }
+}
@@ -1,8 +1,85 @@
+use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-const STATE_FILE: &str = ".logviewer-state";
+/// Pre-config-dir location, relative to the working directory.
+const LEGACY_STATE_FILE: &str = ".logviewer-state";
+const STATE_FILE_NAME: &str = "state.json";
+
+static STATE_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Override where settings are persisted; `None` disables persistence.
+/// Must be called before the first `load`/`save` to take effect.
+pub fn set_state_path(path: Option<PathBuf>) {
+    let _ = STATE_PATH.set(path);
+}
+
+/// Where settings are persisted, `None` when persistence is off.
+pub fn state_path() -> Option<&'static Path> {
+    STATE_PATH
+        .get_or_init(|| {
+            let config_dir =
+                ProjectDirs::from("", "", "logviewer").map(|dirs| dirs.config_dir().to_path_buf());
+            Some(resolve_state_path(
+                config_dir.as_deref(),
+                Path::new(LEGACY_STATE_FILE),
+            ))
+        })
+        .as_deref()
+}
+
+/// Directory holding the state file, where other config files are looked up.
+pub fn config_dir() -> Option<PathBuf> {
+    match state_path() {
+        Some(path) => path.parent().map(Path::to_path_buf),
+        None => ProjectDirs::from("", "", "logviewer").map(|dirs| dirs.config_dir().to_path_buf()),
+    }
+}
+
+/// `state.json` in the config dir, migrating a legacy file into it on first use.
+/// Falls back to the legacy path when the config dir can't be created.
+fn resolve_state_path(config_dir: Option<&Path>, legacy: &Path) -> PathBuf {
+    let Some(dir) = config_dir.filter(|dir| fs::create_dir_all(dir).is_ok()) else {
+        return legacy.to_path_buf();
+    };
+    let path = dir.join(STATE_FILE_NAME);
+    if !path.exists() && legacy.exists() && fs::copy(legacy, &path).is_ok() {
+        let _ = fs::remove_file(legacy);
+    }
+    path
+}
+
+/// A named hide/filter/highlight setup that can be recalled with `p`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub hide_input: String,
+    pub filter_input: String,
+    pub highlight_input: String,
+    #[serde(default = "default_wrap_lines")]
+    pub wrap_lines: bool,
+    #[serde(default)]
+    pub line_start_regex: String,
+}
+
+/// A window's outer position and inner size, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Where the view was left in a file, restored when it is opened again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilePosition {
+    pub follow_tail: bool,
+    /// Absolute index of the line at the bottom of the view.
+    pub bottom_line: usize,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppState {
@@ -13,12 +90,55 @@ pub struct AppState {
     pub wrap_lines: bool,
     #[serde(default)]
     pub line_start_regex: String,
+    #[serde(default)]
+    pub compact_gutter: bool,
+    #[serde(default = "default_show_line_numbers")]
+    pub show_line_numbers: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Patterns without uppercase ignore case; `case_insensitive` overrides it.
+    #[serde(default = "default_smart_case")]
+    pub smart_case: bool,
+    #[serde(default)]
+    pub literal: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default)]
+    pub filter_after_hide: bool,
+    /// Applied expressions per field, oldest first.
+    #[serde(default)]
+    pub hide_history: Vec<String>,
+    #[serde(default)]
+    pub filter_history: Vec<String>,
+    #[serde(default)]
+    pub highlight_history: Vec<String>,
+    /// Heuristic highlight rules switched off, by name.
+    #[serde(default)]
+    pub disabled_heuristics: Vec<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    /// Keyed by canonical path.
+    #[serde(default)]
+    pub file_positions: HashMap<String, FilePosition>,
+    /// Last size and position of the GUI window.
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
 }
 
 fn default_wrap_lines() -> bool {
     true
 }
 
+fn default_show_line_numbers() -> bool {
+    true
+}
+
+fn default_smart_case() -> bool {
+    true
+}
+
 impl Default for AppState {
     fn default() -> Self {
         Self {
@@ -27,13 +147,30 @@ impl Default for AppState {
             highlight_input: String::new(),
             wrap_lines: true,
             line_start_regex: String::new(),
+            compact_gutter: false,
+            show_line_numbers: true,
+            case_insensitive: false,
+            smart_case: true,
+            literal: false,
+            whole_word: false,
+            filter_after_hide: false,
+            hide_history: Vec::new(),
+            filter_history: Vec::new(),
+            highlight_history: Vec::new(),
+            disabled_heuristics: Vec::new(),
+            profiles: HashMap::new(),
+            active_profile: None,
+            file_positions: HashMap::new(),
+            window: None,
         }
     }
 }
 
 impl AppState {
     pub fn load() -> Self {
-        let path = Path::new(STATE_FILE);
+        let Some(path) = state_path() else {
+            return Self::default();
+        };
         if path.exists() {
             if let Ok(content) = fs::read_to_string(path) {
                 if let Ok(state) = serde_json::from_str(&content) {
@@ -45,9 +182,68 @@ impl AppState {
     }
 
     pub fn save(&self) {
-        let path = Path::new(STATE_FILE);
+        let Some(path) = state_path() else {
+            return;
+        };
         if let Ok(content) = serde_json::to_string_pretty(self) {
             let _ = fs::write(path, content);
         }
     }
 }
+
+/// `--hide`/`--filter`/`--highlight` given on the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Presets {
+    pub hide: Option<String>,
+    pub filter: Option<String>,
+    pub highlight: Option<String>,
+}
+
+impl Presets {
+    /// Put the presets into `state`. Returns the saved expressions they replaced,
+    /// which are written back instead until the user edits that field.
+    pub fn apply_to(&self, state: &mut AppState) -> Presets {
+        fn replace(preset: &Option<String>, saved: &mut String) -> Option<String> {
+            preset
+                .as_ref()
+                .map(|preset| std::mem::replace(saved, preset.clone()))
+        }
+        Presets {
+            hide: replace(&self.hide, &mut state.hide_input),
+            filter: replace(&self.filter, &mut state.filter_input),
+            highlight: replace(&self.highlight, &mut state.highlight_input),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_state_is_migrated() {
+        let root = std::env::temp_dir().join(format!("logviewer-state-{}", std::process::id()));
+        let legacy = root.join(LEGACY_STATE_FILE);
+        let config_dir = root.join("config");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            &legacy,
+            r#"{"hide_input":"","filter_input":"error","highlight_input":""}"#,
+        )
+        .unwrap();
+
+        let path = resolve_state_path(Some(&config_dir), &legacy);
+        assert_eq!(path, config_dir.join(STATE_FILE_NAME));
+        assert!(!legacy.exists());
+        assert!(fs::read_to_string(&path).unwrap().contains("error"));
+
+        // An existing config file wins over a legacy file that reappears.
+        fs::write(&legacy, "{}").unwrap();
+        resolve_state_path(Some(&config_dir), &legacy);
+        assert!(legacy.exists());
+        assert!(fs::read_to_string(&path).unwrap().contains("error"));
+
+        assert_eq!(resolve_state_path(None, &legacy), legacy);
+        let _ = fs::remove_dir_all(root);
+    }
+}
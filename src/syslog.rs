@@ -0,0 +1,212 @@
+use crate::highlight::HighlightStyle;
+use crate::timeparse::parse_syslog;
+use chrono::{DateTime, Local};
+
+/// Length of an RFC3164 timestamp, `Mmm dd hh:mm:ss`.
+const BSD_TIMESTAMP_LEN: usize = 15;
+
+/// Header fields of a syslog frame kept with the line for coloring and filters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogMeta {
+    /// 0 (emergency) to 7 (debug).
+    pub severity: u8,
+    pub host: Option<String>,
+    pub app: Option<String>,
+}
+
+impl SyslogMeta {
+    pub fn level(&self) -> HighlightStyle {
+        match self.severity {
+            0..=3 => HighlightStyle::Error,
+            4 => HighlightStyle::Warning,
+            5 | 6 => HighlightStyle::Info,
+            _ => HighlightStyle::Debug,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogFrame {
+    pub meta: SyslogMeta,
+    pub timestamp: Option<DateTime<Local>>,
+    pub message: String,
+}
+
+/// Parse an RFC5424 (`<34>1 2003-10-11T22:14:15.003Z host app - - - msg`) or
+/// RFC3164 (`<34>Oct 11 22:14:15 host app[12]: msg`) frame. `None` if `line`
+/// doesn't start with a valid `<PRI>`.
+pub fn parse_frame(line: &str) -> Option<SyslogFrame> {
+    let rest = line.strip_prefix('<')?;
+    let (pri, rest) = rest.split_once('>')?;
+    if pri.is_empty() || pri.len() > 3 || !pri.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let pri: u8 = pri.parse().ok().filter(|&pri| pri <= 191)?;
+    let severity = pri % 8;
+    match rest.strip_prefix("1 ") {
+        Some(rest) => parse_rfc5424(rest, severity),
+        None => Some(parse_rfc3164(rest, severity)),
+    }
+}
+
+fn parse_rfc5424(rest: &str, severity: u8) -> Option<SyslogFrame> {
+    let mut fields = rest.splitn(6, ' ');
+    let timestamp = fields.next()?;
+    let host = nil_or(fields.next()?);
+    let app = nil_or(fields.next()?);
+    let _proc_id = fields.next()?;
+    let _msg_id = fields.next()?;
+    let rest = fields.next().unwrap_or("");
+    let message = skip_structured_data(rest)?;
+    let message = message.strip_prefix(' ').unwrap_or(message);
+    Some(SyslogFrame {
+        meta: SyslogMeta {
+            severity,
+            host,
+            app,
+        },
+        timestamp: DateTime::parse_from_rfc3339(timestamp)
+            .ok()
+            .map(|time| time.with_timezone(&Local)),
+        message: message
+            .strip_prefix('\u{feff}')
+            .unwrap_or(message)
+            .to_string(),
+    })
+}
+
+/// Without a parsable timestamp the whole rest is the message.
+fn parse_rfc3164(rest: &str, severity: u8) -> SyslogFrame {
+    let timestamp = rest.get(..BSD_TIMESTAMP_LEN).and_then(parse_syslog);
+    let Some(after_time) = timestamp.and_then(|_| rest[BSD_TIMESTAMP_LEN..].strip_prefix(' '))
+    else {
+        return SyslogFrame {
+            meta: SyslogMeta {
+                severity,
+                host: None,
+                app: None,
+            },
+            timestamp: None,
+            message: rest.to_string(),
+        };
+    };
+    let (host, after_host) = after_time.split_once(' ').unwrap_or((after_time, ""));
+    // TAG is alphanumeric, optionally followed by `[pid]`, then `:`.
+    let tag_end = after_host
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/')))
+        .unwrap_or(after_host.len());
+    let (tag, after_tag) = after_host.split_at(tag_end);
+    let after_pid = match after_tag
+        .strip_prefix('[')
+        .and_then(|pid| pid.split_once(']'))
+    {
+        Some((_, after)) => after,
+        None => after_tag,
+    };
+    let (app, message) = match after_pid.strip_prefix(':') {
+        Some(message) if !tag.is_empty() => (
+            Some(tag.to_string()),
+            message.strip_prefix(' ').unwrap_or(message),
+        ),
+        _ => (None, after_host),
+    };
+    SyslogFrame {
+        meta: SyslogMeta {
+            severity,
+            host: Some(host.to_string()),
+            app,
+        },
+        timestamp,
+        message: message.to_string(),
+    }
+}
+
+fn nil_or(field: &str) -> Option<String> {
+    (field != "-").then(|| field.to_string())
+}
+
+/// The text after RFC5424 STRUCTURED-DATA: `-` or `[id k="v" ...]` elements,
+/// where values may contain escaped `"`, `\` and `]`.
+fn skip_structured_data(text: &str) -> Option<&str> {
+    if let Some(rest) = text.strip_prefix('-') {
+        return Some(rest);
+    }
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    while bytes.get(pos) == Some(&b'[') {
+        let mut in_quotes = false;
+        pos += 1;
+        loop {
+            match *bytes.get(pos)? {
+                b'\\' if in_quotes => pos += 1,
+                b'"' => in_quotes = !in_quotes,
+                b']' if !in_quotes => break,
+                _ => {}
+            }
+            pos += 1;
+        }
+        pos += 1;
+    }
+    (pos > 0).then(|| &text[pos..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Timelike, Utc};
+
+    #[test]
+    fn test_rfc5424() {
+        let frame = parse_frame(
+            "<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - 'su root' failed",
+        )
+        .unwrap();
+        assert_eq!(frame.meta.severity, 2);
+        assert_eq!(frame.meta.level(), HighlightStyle::Error);
+        assert_eq!(frame.meta.host.as_deref(), Some("mymachine.example.com"));
+        assert_eq!(frame.meta.app.as_deref(), Some("su"));
+        assert_eq!(frame.message, "'su root' failed");
+        assert_eq!(frame.timestamp.unwrap().with_timezone(&Utc).hour(), 22);
+
+        let frame = parse_frame(
+            r#"<165>1 2003-10-11T22:14:15Z host evntslog - ID47 [exampleSDID@32473 iut="3" note="a \"]\" b"][x@1 k="v"] An event"#,
+        )
+        .unwrap();
+        assert_eq!(frame.meta.level(), HighlightStyle::Info);
+        assert_eq!(frame.message, "An event");
+
+        let frame = parse_frame("<15>1 - - - - - -").unwrap();
+        assert_eq!(
+            (frame.meta.host, frame.meta.app, frame.timestamp),
+            (None, None, None)
+        );
+        assert_eq!(frame.message, "");
+    }
+
+    #[test]
+    fn test_rfc3164() {
+        let frame = parse_frame("<13>Oct  1 22:14:15 web1 sshd[812]: Accepted publickey").unwrap();
+        assert_eq!(frame.meta.severity, 5);
+        assert_eq!(frame.meta.host.as_deref(), Some("web1"));
+        assert_eq!(frame.meta.app.as_deref(), Some("sshd"));
+        assert_eq!(frame.message, "Accepted publickey");
+        assert_eq!(frame.timestamp.unwrap().minute(), 14);
+
+        let frame = parse_frame("<12>Oct 11 22:14:15 web1 no tag here").unwrap();
+        assert_eq!(frame.meta.app, None);
+        assert_eq!(frame.message, "no tag here");
+    }
+
+    #[test]
+    fn test_malformed_frames() {
+        assert!(parse_frame("plain line").is_none());
+        assert!(parse_frame("<abc>1 x").is_none());
+        assert!(parse_frame("<999>1 x").is_none());
+        assert!(parse_frame("<34>1 2003-10-11T22:14:15Z host app - - [unclosed").is_none());
+        let frame = parse_frame("<11>just text").unwrap();
+        assert_eq!(
+            (frame.meta.host, frame.message.as_str()),
+            (None, "just text")
+        );
+    }
+}
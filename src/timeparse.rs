@@ -0,0 +1,98 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone};
+
+/// Formats with a UTC offset; `%#z` also accepts `Z`.
+const OFFSET_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f%#z", "%Y-%m-%d %H:%M:%S%.f%#z"];
+const LOCAL_FORMATS: [&str; 3] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+];
+/// Syslog timestamps have no year, so the current one is prepended.
+const SYSLOG_FORMAT: &str = "%Y %b %e %H:%M:%S";
+
+/// Parses the timestamp at the start of a log line, optionally inside `[...]`.
+#[derive(Debug, Clone, Default)]
+pub struct TimestampParser {
+    format: Option<String>,
+}
+
+impl TimestampParser {
+    /// `format` is a strftime pattern; `None` tries RFC3339, ISO-like and syslog formats.
+    pub fn new(format: Option<String>) -> Self {
+        Self { format }
+    }
+
+    pub fn parse(&self, line: &str) -> Option<DateTime<Local>> {
+        let text = line.trim_start();
+        let text = text.strip_prefix('[').unwrap_or(text);
+        match &self.format {
+            Some(format) => parse_with_offset(text, format).or_else(|| parse_local(text, format)),
+            None => OFFSET_FORMATS
+                .iter()
+                .find_map(|format| parse_with_offset(text, format))
+                .or_else(|| {
+                    LOCAL_FORMATS
+                        .iter()
+                        .find_map(|format| parse_local(text, format))
+                })
+                .or_else(|| parse_syslog(text)),
+        }
+    }
+}
+
+fn parse_with_offset(text: &str, format: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_and_remainder(text, format)
+        .ok()
+        .map(|(time, _)| time.with_timezone(&Local))
+}
+
+fn parse_local(text: &str, format: &str) -> Option<DateTime<Local>> {
+    let (naive, _) = NaiveDateTime::parse_and_remainder(text, format).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// `Mmm dd hh:mm:ss` at the start of `text`, in the current year.
+pub fn parse_syslog(text: &str) -> Option<DateTime<Local>> {
+    let now = Local::now();
+    let time = parse_local(&format!("{} {}", now.year(), text), SYSLOG_FORMAT)?;
+    // A December line read in January belongs to last year.
+    if time > now + Duration::days(1) {
+        time.with_year(now.year() - 1)
+    } else {
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Timelike, Utc};
+
+    #[test]
+    fn test_default_formats() {
+        let parser = TimestampParser::default();
+        let utc = parser.parse("2024-01-02T03:04:05Z started").unwrap();
+        assert_eq!(utc.with_timezone(&Utc).hour(), 3);
+        let offset = parser
+            .parse("2024-01-02T03:04:05.250+02:00 started")
+            .unwrap();
+        assert_eq!(offset.with_timezone(&Utc).hour(), 1);
+
+        let local = parser.parse("[2024-01-02 03:04:05,123] INFO").unwrap();
+        assert_eq!((local.day(), local.hour(), local.second()), (2, 3, 5));
+
+        let syslog = parser.parse("Jan  2 03:04:05 host sshd[1]: ok").unwrap();
+        assert_eq!((syslog.month(), syslog.day(), syslog.minute()), (1, 2, 4));
+
+        assert!(parser.parse("no timestamp here").is_none());
+        assert!(parser.parse("").is_none());
+    }
+
+    #[test]
+    fn test_custom_format() {
+        let parser = TimestampParser::new(Some("%d/%m/%Y %H:%M".to_string()));
+        let time = parser.parse("02/01/2024 03:04 done").unwrap();
+        assert_eq!((time.day(), time.month(), time.minute()), (2, 1, 4));
+        assert!(parser.parse("2024-01-02T03:04:05Z").is_none());
+    }
+}
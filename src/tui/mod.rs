@@ -1,10 +1,19 @@
+pub mod redraw;
+pub mod theme;
+
 use crate::app::App;
 use crate::constants::{
-    HELP_POPUP_HEIGHT, HELP_POPUP_WIDTH, INPUT_FIELD_HEIGHT, QUIT_POPUP_HEIGHT, QUIT_POPUP_WIDTH,
-    STATUS_BAR_HEIGHT,
+    ALIGN_MAX_COLUMNS, EXTRACT_COLUMN_WIDTH, HELP_POPUP_WIDTH, INPUT_FIELD_HEIGHT,
+    LINE_BREAK_MARKER, MINIMAP_WIDTH, QUIT_POPUP_HEIGHT, QUIT_POPUP_WIDTH, STATUS_BAR_HEIGHT,
+    TEXT_POPUP_MIN_WIDTH,
+};
+use crate::core::{
+    format_absolute_time, format_relative_time, InputMode, LineKind, ListenAddrEntry,
+    ListenDisplayMode, LogLine, TimeFormat,
 };
-use crate::core::{format_relative_time, InputMode, ListenAddrEntry, ListenDisplayMode};
+use crate::highlight::{detect_level, level_letter, HighlightConfig, HEURISTIC_RULE_NAMES};
 use crate::input::TextInput;
+use crate::keymap::{Binding, EDIT_KEYS, NORMAL_KEYMAP};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
@@ -12,6 +21,8 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
+use std::ops::Range;
+use theme::Theme;
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
@@ -28,6 +39,7 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
 
     draw_text_input(
         frame,
+        &app.theme,
         &app.input_fields.hide,
         chunks[0],
         " Hide (d) ",
@@ -35,6 +47,7 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     );
     draw_text_input(
         frame,
+        &app.theme,
         &app.input_fields.filter,
         chunks[1],
         " Filter (f) ",
@@ -42,6 +55,7 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     );
     draw_text_input(
         frame,
+        &app.theme,
         &app.input_fields.highlight,
         chunks[2],
         " Highlight (h) ",
@@ -49,6 +63,7 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     );
     draw_text_input(
         frame,
+        &app.theme,
         &app.input_fields.line_start,
         chunks[3],
         " Line Start (s) ",
@@ -58,21 +73,54 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     draw_status_bar(frame, app, chunks[5]);
 
     if app.input_mode != InputMode::Normal {
-        draw_help_popup(frame);
+        draw_help_popup(frame, app.input_mode);
     }
 
     if app.listen_state.show_popup() {
         draw_listen_popup(frame, app);
     }
 
+    if let Some(selected) = app.profile_popup {
+        draw_profile_popup(frame, app, selected);
+    }
+
+    if let Some(selected) = app.heuristic_popup {
+        draw_heuristic_popup(frame, app, selected);
+    }
+
+    if app.text_popup.is_some() {
+        draw_text_popup(frame, app);
+    }
+
+    if app.error_scroll.is_some() {
+        draw_error_list(frame, app);
+    }
+
+    if app.help_scroll.is_some() {
+        draw_help_overlay(frame, app);
+    }
+
     if app.show_quit_confirm {
         draw_quit_confirm(frame);
     }
+
+    if app.no_color {
+        for cell in &mut frame.buffer_mut().content {
+            cell.set_fg(Color::Reset).set_bg(Color::Reset);
+        }
+    }
 }
 
-fn draw_text_input(frame: &mut Frame, input: &TextInput, area: Rect, label: &str, is_active: bool) {
+fn draw_text_input(
+    frame: &mut Frame,
+    theme: &Theme,
+    input: &TextInput,
+    area: Rect,
+    label: &str,
+    is_active: bool,
+) {
     let style = if is_active {
-        Style::default().fg(Color::Yellow)
+        Style::default().fg(theme.accent)
     } else {
         Style::default()
     };
@@ -105,19 +153,32 @@ fn draw_text_input(frame: &mut Frame, input: &TextInput, area: Rect, label: &str
 }
 
 fn draw_log_view(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.begin_frame();
     let inner_height = area.height.saturating_sub(2) as usize;
-    let inner_width = area.width.saturating_sub(2) as usize;
+    app.log_view_area = area;
 
+    let context_lines = app.context_line_count();
+    let shown = match context_lines {
+        0 => app.log_state.filtered_indices.len().to_string(),
+        n => format!("{}+{}", app.log_state.filtered_indices.len() - n, n),
+    };
     let title = format!(
-        " Logs [{}/{}] {}{} ",
-        app.log_state.filtered_indices.len(),
+        " Logs [{}/{}] {}{}{} ",
+        shown,
         app.log_state.lines.len(),
-        if app.log_state.follow_tail {
+        if app.log_state.paused {
+            "[PAUSED]"
+        } else if app.log_state.follow_tail && !app.input_ended {
             "[FOLLOW]"
         } else {
             ""
         },
-        if app.wrap_lines { "[WRAP]" } else { "" }
+        if app.wrap_lines { "[WRAP]" } else { "" },
+        if app.filter_state.inverted {
+            "[INVERTED]"
+        } else {
+            ""
+        }
     );
 
     let block = Block::default()
@@ -126,17 +187,33 @@ fn draw_log_view(frame: &mut Frame, app: &mut App, area: Rect) {
         .border_style(Style::default().fg(Color::Cyan));
 
     if app.log_state.filtered_indices.is_empty() {
+        app.visible_range = None;
+        app.minimap_area = Rect::default();
         let list = List::new(Vec::<ListItem>::new()).block(block);
         frame.render_widget(list, area);
         return;
     }
 
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let [text_area, minimap_area] =
+        Layout::horizontal([Constraint::Min(0), Constraint::Length(MINIMAP_WIDTH)]).areas(inner);
+    draw_minimap(frame, app, minimap_area);
+    let inner_width = text_area.width as usize;
+
     let prefix_width = app.prefix_width();
     let content_width = inner_width.saturating_sub(prefix_width);
     let bottom_idx = app.log_state.get_bottom_line_idx();
+    let column_widths = app
+        .align_columns
+        .then(|| visible_column_widths(app, bottom_idx, inner_height));
+    let wrap = app.wrap_lines && content_width > 0;
+    let visual = app.visual_range();
 
     let mut collected_lines: Vec<Line> = Vec::new();
     let mut current_filtered_idx = bottom_idx as i64;
+    let mut top_filtered_idx = bottom_idx;
+    let mut widest_line = 0;
 
     while collected_lines.len() < inner_height && current_filtered_idx >= 0 {
         let filtered_idx = current_filtered_idx as usize;
@@ -145,35 +222,55 @@ fn draw_log_view(frame: &mut Frame, app: &mut App, area: Rect) {
             continue;
         }
         let line_idx = app.log_state.filtered_indices[filtered_idx];
-        let log_line = app.log_state.lines[line_idx].clone();
-
-        let mut prefix_spans = Vec::new();
-        if app.show_time {
-            let time_age = crate::core::get_time_age(log_line.timestamp);
-            let (time_color, is_bold) = match time_age {
-                crate::core::TimeAge::VeryRecent => (Color::LightGreen, true),
-                crate::core::TimeAge::Recent => (Color::Green, false),
-                crate::core::TimeAge::Minutes => (Color::Rgb(136, 136, 136), false),
-                crate::core::TimeAge::Hours => (Color::Rgb(102, 102, 102), false),
-                crate::core::TimeAge::Days => (Color::Rgb(85, 85, 85), false),
-            };
-            let mut style = Style::default().fg(time_color);
-            if is_bold {
-                style = style.add_modifier(ratatui::style::Modifier::BOLD);
+        let log_line = app.log_state.line(line_idx).clone();
+        let run = if app.dedup {
+            app.duplicate_run(filtered_idx)
+        } else {
+            filtered_idx..filtered_idx + 1
+        };
+        top_filtered_idx = run.start;
+
+        let in_visual = visual
+            .as_ref()
+            .is_some_and(|range| range.contains(&filtered_idx));
+        let mut prefix_spans = gutter_spans(app, &log_line, line_idx);
+        if app.log_state.selected_idx == Some(filtered_idx) || in_visual {
+            for span in &mut prefix_spans {
+                span.style = span.style.add_modifier(ratatui::style::Modifier::REVERSED);
             }
-            prefix_spans.push(Span::styled(
-                format!("{:>6} ", format_relative_time(log_line.timestamp)),
-                style,
-            ));
         }
-        prefix_spans.push(Span::styled(
-            format!("{:>6} │ ", line_idx + 1),
-            Style::default().fg(Color::DarkGray),
-        ));
 
-        let highlighted = app.render_line(&log_line);
+        let mut highlighted = if app.is_context_line(line_idx) {
+            vec![(
+                app.log_state.display_content(line_idx).to_string(),
+                Style::default().fg(app.theme.faint),
+            )]
+        } else {
+            app.render_line_cached(line_idx)
+        };
+        highlighted = expand_tabs(highlighted, app.tab_width);
+        if let (Some(widths), LineKind::Output | LineKind::Stderr) = (&column_widths, log_line.kind)
+        {
+            highlighted = align_cells(&highlighted, widths, app.column_delimiter);
+        }
+        if !wrap {
+            highlighted = mark_line_breaks(highlighted, Style::default().fg(app.theme.faint));
+        }
+        if run.len() > 1 {
+            highlighted.push((
+                format!(" (x{})", run.len()),
+                Style::default().fg(app.theme.accent),
+            ));
+        }
+        if in_visual {
+            let selected = Style::default().bg(app.theme.visual_bg);
+            highlighted = highlighted
+                .into_iter()
+                .map(|(text, style)| (text, selected.patch(style)))
+                .collect();
+        }
 
-        if app.wrap_lines && content_width > 0 {
+        if wrap {
             let wrapped = wrap_highlighted(&highlighted, content_width);
             let mut line_group: Vec<Line> = Vec::new();
 
@@ -195,20 +292,347 @@ fn draw_log_view(frame: &mut Frame, app: &mut App, area: Rect) {
                 }
             }
         } else {
+            widest_line = widest_line.max(
+                highlighted
+                    .iter()
+                    .map(|(text, _)| text.chars().count())
+                    .sum(),
+            );
             let mut spans = prefix_spans;
-            for (text, style) in highlighted {
+            for (text, style) in skip_columns(highlighted, app.h_scroll) {
                 spans.push(Span::styled(text, style));
             }
             collected_lines.push(Line::from(spans));
         }
 
-        current_filtered_idx -= 1;
+        current_filtered_idx = run.start as i64 - 1;
     }
 
     collected_lines.reverse();
+    app.visible_range = Some((top_filtered_idx, bottom_idx + 1));
+    app.h_scroll_max = widest_line.saturating_sub(content_width);
+    app.h_scroll = app.h_scroll.min(app.h_scroll_max);
+
+    frame.render_widget(Paragraph::new(collected_lines), text_area);
+}
+
+/// Shade each minimap row by the share of error lines in its slice of the
+/// filtered lines, so clusters of errors stand out.
+fn draw_minimap(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.minimap_area = area;
+    let rows: Vec<Line> = app
+        .error_density(area.height as usize)
+        .into_iter()
+        .map(|(errors, total)| {
+            let style = match errors * 4 {
+                0 => Style::default(),
+                shade if shade < total => Style::default().bg(Color::Rgb(95, 0, 0)),
+                shade if shade < total * 2 => Style::default().bg(Color::Rgb(175, 0, 0)),
+                _ => Style::default().bg(Color::Rgb(255, 0, 0)),
+            };
+            Line::from(Span::styled(" ".repeat(area.width as usize), style))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(rows), area);
+}
+
+fn gutter_spans(app: &App, log_line: &LogLine, line_idx: usize) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    if app.compact_gutter {
+        spans.push(compact_gutter_span(
+            log_line,
+            app.time_format,
+            &app.theme,
+            &app.highlight,
+        ));
+    } else if app.show_time {
+        let style = app
+            .theme
+            .time_style(crate::core::get_time_age(log_line.timestamp));
+        spans.push(Span::styled(
+            format!("{:>6} ", format_relative_time(log_line.timestamp)),
+            style,
+        ));
+    }
+    let faint = Style::default().fg(app.theme.faint);
+    if app.show_line_numbers {
+        spans.push(Span::styled(format!("{:>6} ", line_idx + 1), faint));
+    }
+    if app.bookmarks.contains(&line_idx) {
+        let bookmark = Style::default()
+            .fg(app.theme.accent)
+            .add_modifier(ratatui::style::Modifier::BOLD);
+        spans.push(Span::styled("◆ ", bookmark));
+    } else {
+        spans.push(Span::styled("│ ", faint));
+    }
+    if let Some((cell, color)) = source_label_cell(app, log_line) {
+        spans.push(Span::styled(cell, Style::default().fg(color)));
+    }
+    if let Some(cell) = extract_cell(app, log_line) {
+        spans.push(Span::styled(cell, Style::default().fg(app.theme.extract)));
+    }
+    spans
+}
+
+/// The padded file label and its color, when viewing several files.
+fn source_label_cell(app: &App, log_line: &LogLine) -> Option<(String, Color)> {
+    let width = app.source_label_width();
+    if width == 0 {
+        return None;
+    }
+    let label = app.source_label(log_line).unwrap_or_default();
+    let sources = &app.theme.sources;
+    let color = sources[log_line.source.unwrap_or(0) % sources.len()];
+    Some((format!("{:<width$}", label), color))
+}
+
+/// The extracted field padded or cut to the column width, blank when the line
+/// has no match. `None` when no extract regex is set.
+fn extract_cell(app: &App, log_line: &LogLine) -> Option<String> {
+    app.extract_regex.as_ref()?;
+    let width = EXTRACT_COLUMN_WIDTH - 1;
+    let field: String = app
+        .extracted_field(log_line)
+        .unwrap_or_default()
+        .chars()
+        .take(width)
+        .collect();
+    Some(format!("{:<width$} ", field))
+}
+
+/// Plain text of the lines shown in the last drawn frame, one per log line,
+/// with the same gutter as on screen except that times are absolute, or as
+/// `--output-template` writes them.
+pub fn visible_text(app: &App) -> Option<String> {
+    let (start, end) = app.visible_range?;
+    if start > end || end > app.log_state.filtered_indices.len() {
+        return None;
+    }
+    let lines: Vec<String> = app
+        .log_state
+        .filtered_indices
+        .range(start..end)
+        .map(|&line_idx| {
+            if let Some(text) = app.templated_line(line_idx) {
+                return text;
+            }
+            let line = app.log_state.line(line_idx);
+            let gutter: String = if app.compact_gutter {
+                gutter_spans(app, line, line_idx)
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            } else {
+                let label = source_label_cell(app, line)
+                    .map(|(cell, _)| cell)
+                    .unwrap_or_default();
+                let extract = extract_cell(app, line).unwrap_or_default();
+                let number = if app.show_line_numbers {
+                    format!("{:>6} ", line_idx + 1)
+                } else {
+                    String::new()
+                };
+                format!("{}{}│ {}{}", app.time_prefix(line), number, label, extract)
+            };
+            format!("{}{}", gutter, app.plain_content(line))
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Build the journalctl-style `HH:MM:SS E` gutter, colored by the line's level.
+fn compact_gutter_span(
+    line: &LogLine,
+    time_format: TimeFormat,
+    theme: &Theme,
+    highlight: &HighlightConfig,
+) -> Span<'static> {
+    let level = match &line.syslog {
+        Some(meta) => Some(meta.level()),
+        None => detect_level(&line.content),
+    };
+    let style = match level {
+        Some(level) => highlight.style_for(level),
+        None => Style::default().fg(theme.faint),
+    };
+    Span::styled(
+        format!(
+            "{} {} ",
+            format_absolute_time(line.timestamp, time_format),
+            level_letter(level)
+        ),
+        style,
+    )
+}
+
+/// Char ranges of a line's cells, split on `delimiter` or else on runs of
+/// whitespace. The last of at most `ALIGN_MAX_COLUMNS` cells takes the rest.
+fn split_cells(text: &str, delimiter: Option<char>) -> Vec<Range<usize>> {
+    let is_separator = |c: char| match delimiter {
+        Some(d) => c == d,
+        None => c.is_whitespace(),
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let mut cells = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while i < chars.len() && cells.len() + 1 < ALIGN_MAX_COLUMNS {
+        if !is_separator(chars[i]) {
+            i += 1;
+            continue;
+        }
+        cells.push(start..i);
+        i += 1;
+        while delimiter.is_none() && i < chars.len() && is_separator(chars[i]) {
+            i += 1;
+        }
+        start = i;
+    }
+    cells.push(start..chars.len());
+    cells
+}
 
-    let para = Paragraph::new(collected_lines).block(block);
-    frame.render_widget(para, area);
+/// Widest cell per column over the lines that can be on screen, the `height`
+/// filtered lines ending at `bottom_idx`, measured with tabs expanded as drawn.
+/// The last cell of a line is left out.
+fn visible_column_widths(app: &App, bottom_idx: usize, height: usize) -> Vec<usize> {
+    let indices = &app.log_state.filtered_indices;
+    let mut widths: Vec<usize> = Vec::new();
+    let end = (bottom_idx + 1).min(indices.len());
+    for &line_idx in indices.range(end.saturating_sub(height)..end) {
+        let line = app.log_state.line(line_idx);
+        if line.kind == LineKind::System {
+            continue;
+        }
+        let text = expand_tabs(
+            vec![(app.plain_content(line), Style::default())],
+            app.tab_width,
+        )
+        .remove(0)
+        .0;
+        let cells = split_cells(&text, app.column_delimiter);
+        for (column, cell) in cells[..cells.len() - 1].iter().enumerate() {
+            match widths.get_mut(column) {
+                Some(width) => *width = (*width).max(cell.len()),
+                None => widths.push(cell.len()),
+            }
+        }
+    }
+    widths
+}
+
+/// Pad each cell of a highlighted line to its column width, keeping the
+/// cells' styles. Separators become a space, or ` d ` for a visible delimiter.
+fn align_cells(
+    spans: &[(String, Style)],
+    widths: &[usize],
+    delimiter: Option<char>,
+) -> Vec<(String, Style)> {
+    let text: String = spans.iter().map(|(text, _)| text.as_str()).collect();
+    let cells = split_cells(&text, delimiter);
+    if cells.len() < 2 {
+        return spans.to_vec();
+    }
+    let styled: Vec<(char, Style)> = spans
+        .iter()
+        .flat_map(|(text, style)| text.chars().map(move |c| (c, *style)))
+        .collect();
+    let gap = match delimiter {
+        Some(d) if !d.is_whitespace() => format!(" {} ", d),
+        _ => " ".to_string(),
+    };
+    let mut aligned: Vec<(String, Style)> = Vec::new();
+    let mut push = |c: char, style: Style| match aligned.last_mut() {
+        Some((text, last)) if *last == style => text.push(c),
+        _ => aligned.push((c.to_string(), style)),
+    };
+    let last = cells.len() - 1;
+    for (column, cell) in cells.into_iter().enumerate() {
+        let len = cell.len();
+        for &(c, style) in &styled[cell] {
+            push(c, style);
+        }
+        if column < last {
+            let pad = widths
+                .get(column)
+                .map_or(0, |width| width.saturating_sub(len));
+            for c in std::iter::repeat_n(' ', pad).chain(gap.chars()) {
+                push(c, Style::default());
+            }
+        }
+    }
+    aligned
+}
+
+/// Replace tabs with spaces up to the next multiple of `tab_width`, counting
+/// columns across spans so every character keeps its style. Done before
+/// column alignment, wrapping and panning, which count characters.
+fn expand_tabs(spans: Vec<(String, Style)>, tab_width: usize) -> Vec<(String, Style)> {
+    if !spans.iter().any(|(text, _)| text.contains('\t')) {
+        return spans;
+    }
+    let mut column = 0;
+    spans
+        .into_iter()
+        .map(|(text, style)| {
+            let mut expanded = String::with_capacity(text.len());
+            for c in text.chars() {
+                if c == '\t' {
+                    let pad = tab_width - column % tab_width;
+                    expanded.extend(std::iter::repeat_n(' ', pad));
+                    column += pad;
+                } else if c == '\n' {
+                    expanded.push(c);
+                    column = 0;
+                } else {
+                    expanded.push(c);
+                    column += 1;
+                }
+            }
+            (expanded, style)
+        })
+        .collect()
+}
+
+/// Replace the line breaks of a multi-line entry with `LINE_BREAK_MARKER`
+/// in `marker_style`, so an unwrapped entry stays on one row.
+fn mark_line_breaks(spans: Vec<(String, Style)>, marker_style: Style) -> Vec<(String, Style)> {
+    if !spans.iter().any(|(text, _)| text.contains('\n')) {
+        return spans;
+    }
+    let mut marked = Vec::new();
+    for (text, style) in spans {
+        for (i, segment) in text.split('\n').enumerate() {
+            if i > 0 {
+                marked.push((LINE_BREAK_MARKER.to_string(), marker_style));
+            }
+            if !segment.is_empty() {
+                marked.push((segment.to_string(), style));
+            }
+        }
+    }
+    marked
+}
+
+/// Drop the first `columns` characters of a highlighted line.
+fn skip_columns(spans: Vec<(String, Style)>, columns: usize) -> Vec<(String, Style)> {
+    let mut remaining = columns;
+    spans
+        .into_iter()
+        .filter_map(|(text, style)| {
+            if remaining == 0 {
+                return Some((text, style));
+            }
+            let len = text.chars().count();
+            if len <= remaining {
+                remaining -= len;
+                return None;
+            }
+            let rest = text.chars().skip(remaining).collect();
+            remaining = 0;
+            Some((rest, style))
+        })
+        .collect()
 }
 
 fn wrap_highlighted(spans: &[(String, Style)], width: usize) -> Vec<Vec<Span<'static>>> {
@@ -223,35 +647,42 @@ fn wrap_highlighted(spans: &[(String, Style)], width: usize) -> Vec<Vec<Span<'st
     let mut current_width = 0;
 
     for (text, style) in spans {
-        let mut remaining = text.as_str();
-        while !remaining.is_empty() {
-            let available = width.saturating_sub(current_width);
-            if available == 0 {
+        // Line breaks in a multi-line entry always start a new row.
+        for (segment_idx, segment) in text.split('\n').enumerate() {
+            if segment_idx > 0 {
                 result.push(Vec::new());
                 current_width = 0;
-                continue;
             }
+            let mut remaining = segment;
+            while !remaining.is_empty() {
+                let available = width.saturating_sub(current_width);
+                if available == 0 {
+                    result.push(Vec::new());
+                    current_width = 0;
+                    continue;
+                }
 
-            let take_chars: usize = remaining.chars().take(available).count();
-            let byte_end = remaining
-                .char_indices()
-                .nth(take_chars)
-                .map(|(i, _)| i)
-                .unwrap_or(remaining.len());
-
-            let (chunk, rest) = remaining.split_at(byte_end);
-            if !chunk.is_empty() {
-                result
-                    .last_mut()
-                    .unwrap()
-                    .push(Span::styled(chunk.to_string(), *style));
-                current_width += chunk.chars().count();
-            }
-            remaining = rest;
+                let take_chars: usize = remaining.chars().take(available).count();
+                let byte_end = remaining
+                    .char_indices()
+                    .nth(take_chars)
+                    .map(|(i, _)| i)
+                    .unwrap_or(remaining.len());
+
+                let (chunk, rest) = remaining.split_at(byte_end);
+                if !chunk.is_empty() {
+                    result
+                        .last_mut()
+                        .unwrap()
+                        .push(Span::styled(chunk.to_string(), *style));
+                    current_width += chunk.chars().count();
+                }
+                remaining = rest;
 
-            if !remaining.is_empty() {
-                result.push(Vec::new());
-                current_width = 0;
+                if !remaining.is_empty() {
+                    result.push(Vec::new());
+                    current_width = 0;
+                }
             }
         }
     }
@@ -260,42 +691,155 @@ fn wrap_highlighted(spans: &[(String, Style)], width: usize) -> Vec<Vec<Span<'st
 }
 
 fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
+    let prompt = match app.input_mode {
+        InputMode::SearchEdit => Some(("/", &app.input_fields.search)),
+        InputMode::GotoLineEdit => Some((":", &app.input_fields.goto_line)),
+        InputMode::ExtractEdit => Some(("Extract column regex: ", &app.input_fields.extract)),
+        InputMode::ProfileNameEdit => Some(("Save profile as: ", &app.input_fields.profile_name)),
+        _ => None,
+    };
+    if let Some((prefix, input)) = prompt {
+        let text = match &input.error {
+            Some(err) => format!("{}{}  (Error: {})", prefix, input.text, err),
+            None => format!("{}{}", prefix, input.text),
+        };
+        let style = if input.has_error() {
+            Style::default().fg(Color::White).bg(Color::Red)
+        } else {
+            Style::default().fg(Color::White).bg(Color::Blue)
+        };
+        frame.render_widget(Paragraph::new(text).style(style), area);
+        let prefix_width = prefix.chars().count() as u16;
+        frame.set_cursor_position((area.x + input.cursor as u16 + prefix_width, area.y));
+        return;
+    }
+
     let status = if let Some(msg) = &app.status_message {
         msg.clone()
     } else {
-        let last_update = if let Some(time) = app.log_state.last_update_time {
-            format!(" | Last: {}", format_relative_time(time))
-        } else {
-            String::new()
-        };
-        format!(
-            "q:Quit d:Hide f:Filter h:Highlight s:LineStart c:Clear t:Time({}) w:Wrap({}){}",
-            if app.show_time { "ON" } else { "OFF" },
-            if app.wrap_lines { "ON" } else { "OFF" },
-            last_update
-        )
+        // Live state first, so a narrow terminal cuts off the key legend rather than it.
+        let mut fields = Vec::new();
+        if app.input_ended {
+            fields.push("[end of input]".to_string());
+        }
+        if let Some(name) = &app.active_profile {
+            fields.push(format!("profile: {}", name));
+        }
+        if let Some(label) = app
+            .source_only
+            .and_then(|source| app.source_labels.get(source))
+        {
+            fields.push(format!("source:{}", label));
+        }
+        if let Some((current, total)) = app.search_state.position {
+            fields.push(format!("match {}/{}", current, total));
+        }
+        if app.h_scroll > 0 && !app.wrap_lines {
+            fields.push(format!("col {}", app.h_scroll + 1));
+        }
+        if let Some(range) = app.visual_range() {
+            fields.push(format!("VISUAL {} lines", range.len()));
+        }
+        if let Some(time) = app.log_state.last_update_time {
+            fields.push(format!("Last: {}", format_relative_time(time)));
+        }
+        match app.throughput.summary() {
+            rate if rate.is_empty() => {}
+            rate => fields.push(rate),
+        }
+        if let Some(status) = app.listen_state.connection_status() {
+            fields.push(status);
+        }
+        // The keys themselves are in the `?` help.
+        let toggles = [
+            (app.filter_state.filter_after_hide, "AfterHide"),
+            (app.show_time, "Time"),
+            (app.compact_gutter, "Compact"),
+            (app.show_line_numbers, "LineNumbers"),
+            (app.wrap_lines, "Wrap"),
+            (app.dedup, "Dedup"),
+            (app.align_columns, "Columns"),
+            (app.heuristic_highlight, "Heuristic"),
+            (app.json_highlight, "JSON"),
+            (app.logfmt_highlight, "logfmt"),
+            (app.case_insensitive, "IgnoreCase"),
+            (app.smart_case, "SmartCase"),
+            (app.literal, "Literal"),
+            (app.whole_word, "Word"),
+            (app.ansi_colors, "ANSI"),
+        ];
+        let mut enabled: Vec<String> = toggles
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        if app.context > 0 {
+            enabled.push(format!("Context({})", app.context));
+        }
+        fields.push("q:Quit ?:Help".to_string());
+        if !enabled.is_empty() {
+            fields.push(format!("on: {}", enabled.join(" ")));
+        }
+        fields.join(" | ")
     };
 
+    let mut spans = Vec::new();
+    match app.source_errors.unseen() {
+        0 => {}
+        count => spans.push(Span::styled(
+            format!(
+                " ⚠ {} error{} (!) ",
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(ratatui::style::Modifier::BOLD),
+        )),
+    }
+    spans.push(Span::raw(status));
     let paragraph =
-        Paragraph::new(status).style(Style::default().fg(Color::White).bg(Color::Blue));
+        Paragraph::new(Line::from(spans)).style(Style::default().fg(Color::White).bg(Color::Blue));
     frame.render_widget(paragraph, area);
 }
 
-fn draw_help_popup(frame: &mut Frame) {
+/// What the field being edited takes, below the editing keys.
+fn edit_mode_hints(mode: InputMode) -> &'static [&'static str] {
+    match mode {
+        InputMode::FilterEdit | InputMode::HighlightEdit | InputMode::SearchEdit => &[
+            "Syntax: a && !b || (c ^^ d)",
+            "Quote special chars: \"a||b\"",
+            "Terms: file: source: host: app: json: level: line: since: until:",
+        ],
+        InputMode::HideEdit => &["Regex; matching text is hidden"],
+        InputMode::LineStartEdit => &["Regex matching the first line of an entry"],
+        InputMode::ExtractEdit => &["Regex; its first group is shown as a column"],
+        InputMode::GotoLineEdit => &["Line number to jump to"],
+        InputMode::ProfileNameEdit => &["Name to save the current setup as"],
+        InputMode::Normal => &[],
+    }
+}
+
+fn draw_help_popup(frame: &mut Frame, mode: InputMode) {
+    let keys: Vec<String> = EDIT_KEYS
+        .iter()
+        .map(|binding| format!("{}:{}", binding.keys_label(), binding.help))
+        .collect();
+    let mut help_text = vec![Line::from(keys[..2].join(" | "))];
+    help_text.extend(keys[2..].chunks(4).map(|row| Line::from(row.join(" "))));
+    help_text.extend(edit_mode_hints(mode).iter().map(|hint| Line::from(*hint)));
+
     let area = frame.area();
+    let content_width = help_text.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let width = (content_width + 2).max(HELP_POPUP_WIDTH).min(area.width);
     let popup_area = Rect {
-        x: area.width.saturating_sub(HELP_POPUP_WIDTH).max(area.x),
+        x: area.width.saturating_sub(width).max(area.x),
         y: area.y,
-        width: HELP_POPUP_WIDTH.min(area.width),
-        height: HELP_POPUP_HEIGHT.min(area.height),
+        width,
+        height: (help_text.len() as u16 + 2).min(area.height),
     };
 
-    let help_text = vec![
-        Line::from("Enter: Apply | Esc: Cancel | ←→: Move cursor"),
-        Line::from("Syntax: pattern && !pattern || pattern"),
-        Line::from("Use quotes for special chars: \"a||b\""),
-    ];
-
     let help = Paragraph::new(help_text)
         .block(
             Block::default()
@@ -309,6 +853,68 @@ fn draw_help_popup(frame: &mut Frame) {
     frame.render_widget(help, popup_area);
 }
 
+/// Every key from the keymap, one section after another.
+fn help_rows() -> Vec<Line<'static>> {
+    let mut rows = Vec::new();
+    for section in NORMAL_KEYMAP {
+        push_help_section(&mut rows, section.title, section.bindings);
+    }
+    push_help_section(&mut rows, "Editing a field", EDIT_KEYS);
+    rows
+}
+
+fn push_help_section<A>(
+    rows: &mut Vec<Line<'static>>,
+    title: &'static str,
+    bindings: &[Binding<A>],
+) {
+    if !rows.is_empty() {
+        rows.push(Line::default());
+    }
+    let title_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(ratatui::style::Modifier::BOLD);
+    rows.push(Line::from(Span::styled(title, title_style)));
+    for binding in bindings {
+        rows.push(Line::from(vec![
+            Span::styled(
+                format!("  {:<14}", binding.keys_label()),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(binding.help),
+        ]));
+    }
+}
+
+fn draw_help_overlay(frame: &mut Frame, app: &mut App) {
+    let area = frame.area();
+    let rows = help_rows();
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let scroll = app
+        .help_scroll
+        .unwrap_or(0)
+        .min(rows.len().saturating_sub(inner_height));
+    app.help_scroll = Some(scroll);
+
+    let paragraph = Paragraph::new(
+        rows.into_iter()
+            .skip(scroll)
+            .take(inner_height)
+            .collect::<Vec<_>>(),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Keys ")
+            .title_bottom("↑↓:Scroll PgUp/PgDn Esc:Close")
+            .border_style(Style::default().fg(Color::Green)),
+    )
+    .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
 fn draw_listen_popup(frame: &mut Frame, app: &mut App) {
     let port = app.listen_state.port.unwrap_or(0);
     let interfaces = &app.listen_state.network_interfaces;
@@ -332,10 +938,18 @@ fn draw_listen_popup(frame: &mut Frame, app: &mut App) {
     let mut lines: Vec<Line> = Vec::new();
     let mut addr_entries: Vec<ListenAddrEntry> = Vec::new();
 
-    lines.push(Line::from(vec![
+    let mut header = vec![
         Span::styled("Listening on port ", Style::default().fg(Color::White)),
         Span::styled(format!("{}", port), Style::default().fg(Color::Yellow)),
-    ]));
+    ];
+    if let Some(addr) = app.listen_state.bind_addr {
+        header.push(Span::styled(" at ", Style::default().fg(Color::White)));
+        header.push(Span::styled(
+            addr.to_string(),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    lines.push(Line::from(header));
     lines.push(Line::from(""));
 
     let mode_str = match display_mode {
@@ -530,6 +1144,216 @@ fn build_addr_line<'a>(
     }
 }
 
+fn draw_profile_popup(frame: &mut Frame, app: &App, selected: usize) {
+    let names = app.profile_names();
+    let items: Vec<ListItem> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let marker = if app.active_profile.as_deref() == Some(name) {
+                "* "
+            } else {
+                "  "
+            };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{}{}", marker, name)).style(style)
+        })
+        .collect();
+
+    let area = frame.area();
+    let content_width = names
+        .iter()
+        .map(|name| name.chars().count() + 2)
+        .max()
+        .unwrap_or(0);
+    let hint = "Enter:Load x:Delete Esc:Close";
+    let popup_width = (content_width.max(hint.len()) as u16 + 2).min(area.width.saturating_sub(4));
+    let popup_height = (names.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: area.width.saturating_sub(popup_width) / 2,
+        y: area.height.saturating_sub(popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Profiles ")
+                .title_bottom(hint)
+                .border_style(Style::default().fg(Color::Magenta)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(list, popup_area);
+}
+
+fn draw_heuristic_popup(frame: &mut Frame, app: &App, selected: usize) {
+    let items: Vec<ListItem> = HEURISTIC_RULE_NAMES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let mark = if app.heuristic_rules.is_enabled(i) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{} {}", mark, name)).style(style)
+        })
+        .collect();
+
+    let area = frame.area();
+    let content_width = HEURISTIC_RULE_NAMES
+        .iter()
+        .map(|name| name.len() + 4)
+        .max()
+        .unwrap_or(0);
+    let hint = "Space:Toggle Esc:Close";
+    let popup_width = (content_width.max(hint.len()) as u16 + 2).min(area.width.saturating_sub(4));
+    let popup_height = (HEURISTIC_RULE_NAMES.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: area.width.saturating_sub(popup_width) / 2,
+        y: area.height.saturating_sub(popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let title = if app.heuristic_highlight {
+        " Heuristic rules "
+    } else {
+        " Heuristic rules (S: off) "
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(hint)
+                .border_style(Style::default().fg(Color::Magenta)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(list, popup_area);
+}
+
+fn draw_text_popup(frame: &mut Frame, app: &mut App) {
+    let Some(popup) = &mut app.text_popup else {
+        return;
+    };
+    let area = frame.area();
+    let content_width = popup
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|(part, _)| part.chars().count())
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap_or(0);
+    let popup_width = (content_width as u16 + 2)
+        .max(TEXT_POPUP_MIN_WIDTH)
+        .min(area.width.saturating_sub(4));
+    let popup_height = (popup.rows.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: area.width.saturating_sub(popup_width) / 2,
+        y: area.height.saturating_sub(popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let inner_height = popup_height.saturating_sub(2) as usize;
+    popup.scroll = popup
+        .scroll
+        .min(popup.rows.len().saturating_sub(inner_height));
+    let lines: Vec<Line> = popup
+        .rows
+        .iter()
+        .skip(popup.scroll)
+        .take(inner_height)
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|(part, style)| Span::styled(part.clone(), *style))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(popup.title.as_str())
+                .title_bottom("↑↓:Scroll Esc:Close")
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Source errors with their times, newest at the bottom.
+fn draw_error_list(frame: &mut Frame, app: &mut App) {
+    let area = frame.area();
+    let entries = app.source_errors.entries();
+    let popup_width = area.width.saturating_sub(4);
+    let popup_height = (entries.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: area.width.saturating_sub(popup_width) / 2,
+        y: area.height.saturating_sub(popup_height) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+
+    let inner_height = popup_height.saturating_sub(2) as usize;
+    let scroll = app
+        .error_scroll
+        .unwrap_or(0)
+        .min(entries.len().saturating_sub(inner_height));
+    let lines: Vec<Line> = entries
+        .iter()
+        .skip(scroll)
+        .take(inner_height)
+        .map(|(time, message)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", format_absolute_time(*time, app.time_format)),
+                    Style::default().fg(app.theme.faint),
+                ),
+                Span::raw(message.clone()),
+            ])
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Source errors ({}) ", entries.len()))
+                .title_bottom("↑↓:Scroll c:Clear Esc:Close")
+                .border_style(Style::default().fg(Color::Red)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    app.error_scroll = Some(scroll);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
 fn draw_quit_confirm(frame: &mut Frame) {
     let area = frame.area();
     let popup_width = QUIT_POPUP_WIDTH.min(area.width.saturating_sub(4));
@@ -562,3 +1386,330 @@ fn draw_quit_confirm(frame: &mut Frame) {
     frame.render_widget(Clear, popup_area);
     frame.render_widget(popup, popup_area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn gutter_for(content: &str) -> String {
+        let line = LogLine {
+            timestamp: Local::now(),
+            timestamp_parsed: false,
+            content: content.to_string(),
+            kind: Default::default(),
+            source: None,
+            syslog: None,
+        };
+        compact_gutter_span(
+            &line,
+            TimeFormat::Clock,
+            &Theme::DARK,
+            &HighlightConfig::default(),
+        )
+        .content
+        .into_owned()
+    }
+
+    fn screen_log_rows(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let backend = ratatui::backend::TestBackend::new(width, height);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = terminal.backend().buffer().clone();
+        let log_top = INPUT_FIELD_HEIGHT * 4 + 1;
+        let log_bottom = height - STATUS_BAR_HEIGHT - 1;
+        (log_top..log_bottom)
+            .map(|y| {
+                let row: String = (1..width - 1)
+                    .map(|x| buffer[(x, y)].symbol().to_string())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .filter(|row| !row.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_help_overlay_clamps_scroll() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.help_scroll = Some(usize::MAX);
+        let backend = ratatui::backend::TestBackend::new(60, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert_eq!(app.help_scroll, Some(help_rows().len() - 18));
+        let buffer = terminal.backend().buffer();
+        let last_row: String = (0..60)
+            .map(|x| buffer[(x, 18)].symbol().to_string())
+            .collect();
+        assert!(last_row.contains("Next applied expression"), "{}", last_row);
+    }
+
+    #[test]
+    fn test_status_bar_shows_live_state_before_keys() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.input_ended = true;
+        app.active_profile = Some("prod".to_string());
+        app.search_state.position = Some((2, 5));
+        let backend = ratatui::backend::TestBackend::new(100, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status: String = (0..100)
+            .map(|x| buffer[(x, 19)].symbol().to_string())
+            .collect();
+        assert!(
+            status.starts_with(
+                "[end of input] | profile: prod | match 2/5 | q:Quit ?:Help | on: Time"
+            ),
+            "{}",
+            status
+        );
+    }
+
+    #[test]
+    fn test_no_color_keeps_structure() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.no_color = true;
+        app.log_state.add_line("ERROR disk full".to_string());
+        app.log_state.filtered_indices.push_back(0);
+        let backend = ratatui::backend::TestBackend::new(80, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        assert!(buffer
+            .content
+            .iter()
+            .all(|cell| cell.fg == Color::Reset && cell.bg == Color::Reset));
+        assert_eq!(buffer[(0, 0)].symbol(), "┌");
+        let rows = screen_log_rows(&mut app, 80, 20);
+        assert!(
+            rows.iter().any(|row| row.ends_with("1 │ ERROR disk full")),
+            "{:?}",
+            rows
+        );
+    }
+
+    #[test]
+    fn test_visible_text_matches_screen() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = false;
+        for i in 0..50 {
+            app.log_state.add_line(format!("line number {}", i));
+            app.log_state.filtered_indices.push_back(i);
+        }
+        app.log_state.scroll_up(10);
+
+        let rows = screen_log_rows(&mut app, 60, 30);
+        let copied = visible_text(&app).unwrap();
+        let copied: Vec<&str> = copied.lines().collect();
+        assert_eq!(rows, copied);
+        assert_eq!(copied.last(), Some(&"    40 │ line number 39"));
+    }
+
+    #[test]
+    fn test_align_columns() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = false;
+        app.align_columns = true;
+        for (i, text) in ["GET /  200 1ms", "POST /login\t500   12ms and more", "x"]
+            .iter()
+            .enumerate()
+        {
+            app.log_state.add_line(text.to_string());
+            app.log_state.filtered_indices.push_back(i);
+        }
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(
+            rows,
+            vec![
+                "     1 │ GET  /      200 1ms",
+                "     2 │ POST /login 500 12ms and more",
+                "     3 │ x",
+            ]
+        );
+
+        let runs = vec![
+            ("a,b".to_string(), Style::default().fg(Color::Red)),
+            (",c,d".to_string(), Style::default()),
+        ];
+        let aligned = align_cells(&runs, &[3, 2], Some(','));
+        assert_eq!(
+            aligned[0],
+            ("a".to_string(), Style::default().fg(Color::Red))
+        );
+        let text: String = aligned.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(text, "a   , b  , c , d");
+        assert_eq!(split_cells(&"a ".repeat(40), None).len(), ALIGN_MAX_COLUMNS);
+
+        // A tab inside a cell is measured as the spaces it is drawn as.
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = false;
+        app.align_columns = true;
+        app.column_delimiter = Some(',');
+        app.tab_width = 4;
+        for (i, text) in ["a\tb,1", "abcdefgh,2"].iter().enumerate() {
+            app.log_state.add_line(text.to_string());
+            app.log_state.filtered_indices.push_back(i);
+        }
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(rows, vec!["     1 │ a   b    , 1", "     2 │ abcdefgh , 2"]);
+    }
+
+    #[test]
+    fn test_multiline_entries_break_rows() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = true;
+        app.log_state
+            .add_line("boom\n\tat Foo.bar\ncaused by x".to_string());
+        app.log_state.filtered_indices.push_back(0);
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(
+            rows,
+            vec![
+                "     1 │ boom",
+                "             at Foo.bar",
+                "         caused by x"
+            ]
+        );
+
+        app.wrap_lines = false;
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(rows, vec!["     1 │ boom↵    at Foo.bar↵caused by x"]);
+    }
+
+    #[test]
+    fn test_tabs_expand_to_tab_stops() {
+        let red = Style::default().fg(Color::Red);
+        let runs = vec![
+            ("ab\t".to_string(), Style::default()),
+            ("ü\tc".to_string(), red),
+        ];
+        assert_eq!(
+            expand_tabs(runs, 4),
+            vec![
+                ("ab  ".to_string(), Style::default()),
+                ("ü   c".to_string(), red)
+            ]
+        );
+
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.tab_width = 8;
+        app.log_state.add_line(format!("{}\tend", "x".repeat(44)));
+        app.log_state.filtered_indices.push_back(0);
+        // 48 columns for content: counted as one column the tab would leave room
+        // for "end", but it fills the row.
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(
+            rows,
+            vec![
+                format!("     1 │ {}", "x".repeat(44)),
+                "         end".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_page_size_matches_log_view() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = false;
+        for i in 0..100 {
+            app.log_state.add_line(format!("line {}", i));
+            app.log_state.filtered_indices.push_back(i);
+        }
+
+        let rows = screen_log_rows(&mut app, 60, 40);
+        assert_eq!(
+            rows.len(),
+            40 - INPUT_FIELD_HEIGHT as usize * 4 - STATUS_BAR_HEIGHT as usize - 2
+        );
+        assert_eq!(app.log_view_height(), rows.len());
+    }
+
+    #[test]
+    fn test_dedup_collapses_repeated_lines() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.dedup = true;
+        for (i, text) in ["start", "retrying", "retrying", "retrying", "done"]
+            .iter()
+            .enumerate()
+        {
+            app.log_state.add_line(text.to_string());
+            app.log_state.hidden_content.push_back(None);
+            app.log_state.filtered_indices.push_back(i);
+        }
+
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert_eq!(
+            rows,
+            vec!["     1 │ start", "     4 │ retrying (x3)", "     5 │ done"]
+        );
+    }
+
+    #[test]
+    fn test_horizontal_scroll_skips_leading_columns() {
+        let (_tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::from_state(rx, None, crate::state::AppState::default(), false);
+        app.show_time = false;
+        app.wrap_lines = false;
+        app.log_state.add_line(format!("{}END", "x".repeat(80)));
+        app.log_state.filtered_indices.push_back(0);
+
+        screen_log_rows(&mut app, 60, 30);
+        app.scroll_horizontal(1000);
+        let rows = screen_log_rows(&mut app, 60, 30);
+        assert!(rows[0].ends_with("xEND"), "{}", rows[0]);
+        assert_eq!(app.h_scroll, app.h_scroll_max);
+    }
+
+    #[test]
+    fn test_compact_gutter_level_letter() {
+        assert!(gutter_for("ERROR: disk full").ends_with(" E "));
+        assert!(gutter_for("[warn] slow query").ends_with(" W "));
+        assert!(gutter_for("info: started").ends_with(" I "));
+        assert!(gutter_for("debug value=3").ends_with(" D "));
+        assert!(gutter_for("plain line").ends_with("   "));
+    }
+
+    #[test]
+    fn test_compact_gutter_leftmost_level_wins() {
+        assert!(gutter_for("INFO retrying after error").ends_with(" I "));
+    }
+
+    #[test]
+    fn test_compact_gutter_width() {
+        let line = LogLine {
+            timestamp: Local::now(),
+            timestamp_parsed: false,
+            content: "error".to_string(),
+            kind: Default::default(),
+            source: None,
+            syslog: None,
+        };
+        for format in [TimeFormat::Clock, TimeFormat::Rfc3339, TimeFormat::Epoch] {
+            assert_eq!(
+                compact_gutter_span(&line, format, &Theme::DARK, &HighlightConfig::default())
+                    .content
+                    .chars()
+                    .count(),
+                format.width() + crate::constants::LEVEL_GUTTER_WIDTH
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::constants::{POLL_INTERVAL_MAX_MS, POLL_INTERVAL_MS, RENDER_INTERVAL_MAX_MS};
+use std::time::{Duration, Instant};
+
+/// How much longer the next frame waits while lines keep arriving.
+const FRAME_GROWTH_FACTOR: f64 = 1.5;
+
+/// Decides when the TUI redraws, so bursts of source lines are drawn once per
+/// frame instead of on every poll. Input is drawn right away. Lines wait for the
+/// frame interval, which stretches with each frame that brought lines and snaps
+/// back once one doesn't. Otherwise it redraws every `POLL_INTERVAL_MAX_MS` so relative times tick.
+pub struct RedrawThrottle {
+    last_draw: Option<Instant>,
+    input: bool,
+    /// Lines arrived since the last draw.
+    data: bool,
+    frame: Duration,
+}
+
+impl Default for RedrawThrottle {
+    fn default() -> Self {
+        Self {
+            last_draw: None,
+            input: false,
+            data: false,
+            frame: Duration::from_millis(POLL_INTERVAL_MS),
+        }
+    }
+}
+
+impl RedrawThrottle {
+    pub fn on_input(&mut self) {
+        self.input = true;
+    }
+
+    pub fn on_data(&mut self) {
+        self.data = true;
+    }
+
+    /// Time until the next draw, zero once it is due.
+    pub fn due_in(&self, now: Instant) -> Duration {
+        let Some(last_draw) = self.last_draw else {
+            return Duration::ZERO;
+        };
+        let wait = if self.input {
+            Duration::ZERO
+        } else if self.data {
+            self.frame
+        } else {
+            Duration::from_millis(POLL_INTERVAL_MAX_MS)
+        };
+        wait.saturating_sub(now.saturating_duration_since(last_draw))
+    }
+
+    pub fn drawn(&mut self, now: Instant) {
+        self.frame = if self.data {
+            self.frame
+                .mul_f64(FRAME_GROWTH_FACTOR)
+                .min(Duration::from_millis(RENDER_INTERVAL_MAX_MS))
+        } else {
+            Duration::from_millis(POLL_INTERVAL_MS)
+        };
+        self.last_draw = Some(now);
+        self.input = false;
+        self.data = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_lines_stretch_frames() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut redraw = RedrawThrottle::default();
+        assert_eq!(redraw.due_in(start), Duration::ZERO);
+        redraw.drawn(start);
+        assert_eq!(redraw.due_in(start), ms(POLL_INTERVAL_MAX_MS), "idle");
+
+        redraw.on_data();
+        assert_eq!(redraw.due_in(start + ms(10)), ms(POLL_INTERVAL_MS - 10));
+        redraw.on_input();
+        assert_eq!(redraw.due_in(start + ms(10)), Duration::ZERO);
+
+        // A steady stream waits longer each frame, up to the cap.
+        let mut now = start;
+        for _ in 0..10 {
+            redraw.on_data();
+            now += ms(RENDER_INTERVAL_MAX_MS);
+            redraw.drawn(now);
+        }
+        redraw.on_data();
+        assert_eq!(redraw.due_in(now), ms(RENDER_INTERVAL_MAX_MS));
+
+        // Once a frame passes without lines, the next one is drawn at the normal pace.
+        now += ms(RENDER_INTERVAL_MAX_MS);
+        redraw.drawn(now);
+        redraw.drawn(now);
+        redraw.on_data();
+        assert_eq!(redraw.due_in(now), ms(POLL_INTERVAL_MS));
+    }
+}
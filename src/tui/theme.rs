@@ -0,0 +1,145 @@
+use crate::core::TimeAge;
+use ratatui::style::{Color, Modifier, Style};
+
+/// Terminal background to pick colors for (`--theme`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ThemeMode {
+    /// Light if `COLORFGBG` names a light background, else dark.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl ThemeMode {
+    pub fn is_light(self) -> bool {
+        match self {
+            ThemeMode::Light => true,
+            ThemeMode::Dark => false,
+            ThemeMode::Auto => std::env::var("COLORFGBG")
+                .ok()
+                .and_then(|value| light_background(&value))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Whether a `COLORFGBG` value such as `15;0` or `0;default;15` ends in a
+/// light background color: white or one of the bright colors except dark gray.
+fn light_background(colorfgbg: &str) -> Option<bool> {
+    let background: u8 = colorfgbg.rsplit(';').next()?.parse().ok()?;
+    Some(matches!(background, 7 | 9..=15))
+}
+
+/// Colors the TUI draws with besides highlighting, which `HighlightTheme`
+/// adapts on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    /// Relative times from very recent to days old.
+    time_ages: [Style; 5],
+    /// Line numbers, separators, context lines and lines without a level.
+    pub faint: Color,
+    /// Bookmarks, the dedup count and the field being edited.
+    pub accent: Color,
+    /// The extract column.
+    pub extract: Color,
+    /// Stderr lines from a command.
+    pub stderr: Color,
+    /// Lines in a `V` selection.
+    pub visual_bg: Color,
+    /// File labels when viewing several files.
+    pub sources: [Color; 6],
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        time_ages: [
+            Style::new()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            Style::new().fg(Color::Green),
+            Style::new().fg(Color::Rgb(136, 136, 136)),
+            Style::new().fg(Color::Rgb(102, 102, 102)),
+            Style::new().fg(Color::Rgb(85, 85, 85)),
+        ],
+        faint: Color::DarkGray,
+        accent: Color::Yellow,
+        extract: Color::Cyan,
+        stderr: Color::LightRed,
+        visual_bg: Color::Rgb(40, 50, 80),
+        sources: [
+            Color::LightCyan,
+            Color::LightMagenta,
+            Color::LightGreen,
+            Color::LightYellow,
+            Color::LightBlue,
+            Color::LightRed,
+        ],
+    };
+
+    /// Older times fade towards the background here too, so they get lighter.
+    pub const LIGHT: Theme = Theme {
+        time_ages: [
+            Style::new()
+                .fg(Color::Rgb(0, 135, 0))
+                .add_modifier(Modifier::BOLD),
+            Style::new().fg(Color::Rgb(0, 110, 0)),
+            Style::new().fg(Color::Rgb(110, 110, 110)),
+            Style::new().fg(Color::Rgb(140, 140, 140)),
+            Style::new().fg(Color::Rgb(165, 165, 165)),
+        ],
+        faint: Color::Rgb(140, 140, 140),
+        accent: Color::Rgb(175, 95, 0),
+        extract: Color::Rgb(0, 110, 140),
+        stderr: Color::Red,
+        visual_bg: Color::Rgb(205, 220, 245),
+        sources: [
+            Color::Rgb(0, 120, 140),
+            Color::Rgb(150, 0, 150),
+            Color::Rgb(0, 130, 0),
+            Color::Rgb(150, 110, 0),
+            Color::Rgb(0, 70, 200),
+            Color::Rgb(190, 0, 0),
+        ],
+    };
+
+    pub fn new(light: bool) -> Self {
+        if light {
+            Self::LIGHT
+        } else {
+            Self::DARK
+        }
+    }
+
+    pub fn time_style(&self, age: TimeAge) -> Style {
+        let idx = match age {
+            TimeAge::VeryRecent => 0,
+            TimeAge::Recent => 1,
+            TimeAge::Minutes => 2,
+            TimeAge::Hours => 3,
+            TimeAge::Days => 4,
+        };
+        self.time_ages[idx]
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_background_from_colorfgbg() {
+        assert_eq!(light_background("15;0"), Some(false));
+        assert_eq!(light_background("0;15"), Some(true));
+        assert_eq!(light_background("0;default;7"), Some(true));
+        assert_eq!(light_background("7;8"), Some(false), "dark gray is dark");
+        assert_eq!(light_background("15;default"), None);
+        assert_eq!(light_background(""), None);
+    }
+}